@@ -0,0 +1,76 @@
+//! Feeds arbitrary gamedata to the reader behind each loading sequence it recognises.
+//!
+//! The first byte picks the format, the rest is put where that format's gamedata goes. Run with
+//! `cargo fuzz run from_exe` from the gm8exe directory. Anything but a panic, a hang or running out
+//! of memory is fine: garbage is meant to be reported as an error.
+
+#![no_main]
+
+use gm8exe::{
+    gamedata::DETECTORS,
+    reader::{from_exe, Limits, ReaderEvent},
+};
+use libfuzzer_sys::fuzz_target;
+
+const PE_HEADER: usize = 0x80;
+const SECTION_HEADERS: usize = PE_HEADER + 0x18;
+
+/// An exe with a PE header and the given sections, as (name, virtual size, disk address), padded to `len` bytes.
+fn exe(len: usize, sections: &[(&[u8; 8], u32, u32)]) -> Vec<u8> {
+    let mut exe = vec![0u8; len.max(0x400)];
+    exe[..2].copy_from_slice(b"MZ");
+    exe[0x3C..0x40].copy_from_slice(&(PE_HEADER as u32).to_le_bytes());
+    exe[PE_HEADER..PE_HEADER + 6].copy_from_slice(b"PE\0\0\x4C\x01");
+    exe[PE_HEADER + 6..PE_HEADER + 8].copy_from_slice(&(sections.len() as u16).to_le_bytes());
+    for (i, (name, virtual_size, disk_address)) in sections.iter().enumerate() {
+        let header = SECTION_HEADERS + i * 40;
+        exe[header..header + 8].copy_from_slice(*name);
+        exe[header + 8..header + 12].copy_from_slice(&virtual_size.to_le_bytes());
+        exe[header + 20..header + 24].copy_from_slice(&disk_address.to_le_bytes());
+    }
+    exe
+}
+
+fn put(exe: &mut [u8], pos: usize, bytes: &[u8]) {
+    exe[pos..pos + bytes.len()].copy_from_slice(bytes);
+}
+
+fuzz_target!(|data: &[u8]| {
+    let (&selector, gamedata) = match data.split_first() {
+        Some(split) => split,
+        None => return,
+    };
+    let mut exe = match selector % 5 {
+        // GM8.0, with both magic checks patched out, so the header is wherever 0x144AC0 says
+        0 => {
+            let mut exe = exe(0x144AC4, &[]);
+            put(&mut exe, 0xA49BE, &[0x8B, 0x45, 0xF4, 0xE8, 0x2A, 0xBD, 0xFD, 0xFF, 0x90]);
+            put(&mut exe, 0x144AC0, &0x144AC4u32.to_le_bytes());
+            exe
+        },
+        // GM8.1, found by its default header past where the runner ends
+        1 => {
+            let mut exe = exe(3800004, &[]);
+            exe.extend(0xF7140067u32.to_le_bytes());
+            exe
+        },
+        // antidec2 on GM8.0, with no masks
+        2 => {
+            let mut exe = exe(0x144AC4, &[]);
+            put(&mut exe, 0x32337, &[0xE2, 0xF7, 0xC7, 0x05, 0x2E, 0x2F, 0x43, 0x00]);
+            put(&mut exe, 0x144AC0, &0x144AC4u32.to_le_bytes());
+            exe
+        },
+        // antidec2 on GM8.1, with no masks
+        3 => {
+            let mut exe = exe(0x1F0C57, &[]);
+            put(&mut exe, 0x462CC, &[0x80, 0x34, 0x08, 0x00, 0xE2, 0xFA, 0xE9]);
+            put(&mut exe, 0x1F0C53, &0x1F0C57u32.to_le_bytes());
+            exe
+        },
+        // UPX packed, with the packed data right after the headers
+        _ => exe(0x400, &[(b"UPX0\0\0\0\0", 0x10000, 0), (b"UPX1\0\0\0\0", 0x10000, 0x400)]),
+    };
+    exe.extend_from_slice(gamedata);
+    let _ = from_exe(exe, None::<fn(ReaderEvent)>, true, false, true, Limits::UNTRUSTED, DETECTORS);
+});
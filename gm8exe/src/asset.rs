@@ -31,10 +31,10 @@ pub use self::{
 };
 
 use crate::GameVersion;
-use byteorder::LE;
+use byteorder::{ReadBytesExt, LE};
 use std::{
     fmt::{self, Display},
-    io,
+    io::{self, Read},
 };
 
 pub trait Asset: Sized {
@@ -42,6 +42,21 @@ pub trait Asset: Sized {
     fn deserialize_exe(reader: impl io::Read, version: GameVersion, strict: bool) -> Result<Self, Error>;
     /// Serializes the asset to the format used in game executables.
     fn serialize_exe(&self, writer: impl io::Write, version: GameVersion) -> io::Result<()>;
+
+    /// Reads just the name from the format used in game executables, to tell which asset failed to deserialize.
+    fn read_name(reader: impl io::Read) -> Option<PascalString> {
+        read_name(reader)
+    }
+}
+
+/// Reads a name at the start of `reader`, giving up on names no one would write rather than trusting the length
+/// of data that's already known to be broken.
+pub(crate) fn read_name(mut reader: impl io::Read) -> Option<PascalString> {
+    let len = reader.read_u32::<LE>().ok()? as usize;
+    if len > 1024 {
+        return None
+    }
+    Some(PascalString(reader.read_chunk(len).ok()?.into_boxed_slice()))
 }
 
 #[derive(Debug)]
@@ -105,13 +120,69 @@ impl From<&str> for PascalString {
     }
 }
 
+/// Serializes as a plain string when it's valid UTF-8, which is nearly always. Anything else is serialized as
+/// `{ "base64": "..." }` so that it still comes back byte for byte. This needs a self-describing format.
+#[cfg(feature = "serde")]
+impl serde::Serialize for PascalString {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeMap;
+        match std::str::from_utf8(&self.0) {
+            Ok(s) => serializer.serialize_str(s),
+            Err(_) => {
+                let mut map = serializer.serialize_map(Some(1))?;
+                map.serialize_entry("base64", &crate::base64::encode(&self.0))?;
+                map.end()
+            },
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for PascalString {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        use serde::de::{self, MapAccess, Visitor};
+
+        struct PascalStringVisitor;
+        impl<'de> Visitor<'de> for PascalStringVisitor {
+            type Value = PascalString;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a string, or a map with a base64 string")
+            }
+
+            fn visit_str<E: de::Error>(self, s: &str) -> Result<PascalString, E> {
+                Ok(PascalString::from(s))
+            }
+
+            fn visit_bytes<E: de::Error>(self, bytes: &[u8]) -> Result<PascalString, E> {
+                Ok(PascalString(bytes.into()))
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<PascalString, A::Error> {
+                match map.next_entry::<String, String>()? {
+                    Some((key, value)) if key == "base64" => match crate::base64::decode(&value) {
+                        Some(data) => Ok(PascalString(data.into_boxed_slice())),
+                        None => Err(de::Error::custom("invalid base64")),
+                    },
+                    Some((key, _)) => Err(de::Error::unknown_field(&key, &["base64"])),
+                    None => Err(de::Error::missing_field("base64")),
+                }
+            }
+        }
+
+        deserializer.deserialize_any(PascalStringVisitor)
+    }
+}
+
 /// Helper trait to read big blocks of raw data.
 pub trait ReadChunk: io::Read {
+    /// Reads `len` bytes, only allocating as much as there turns out to be, since lengths come from the file.
     fn read_chunk(&mut self, len: usize) -> io::Result<Vec<u8>> {
-        // safety: read_exact specifies to expect buf to be uninitialized and never read from it
-        let mut buf = Vec::with_capacity(len);
-        unsafe { buf.set_len(len) };
-        self.read_exact(&mut buf[..])?;
+        let mut buf = Vec::with_capacity(len.min(1 << 20));
+        (&mut *self).take(len as u64).read_to_end(&mut buf)?;
+        if buf.len() != len {
+            return Err(io::ErrorKind::UnexpectedEof.into())
+        }
         Ok(buf)
     }
 }
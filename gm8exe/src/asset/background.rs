@@ -1,3 +1,6 @@
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
 use crate::{
     asset::{assert_ver, Asset, Error, PascalString, ReadChunk, ReadPascalString, WritePascalString},
     GameVersion,
@@ -8,6 +11,7 @@ use std::io::{self, Read};
 pub const VERSION1: u32 = 710;
 pub const VERSION2: u32 = 800;
 
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Background {
     /// The asset name present in GML and the editor.
     pub name: PascalString,
@@ -21,6 +25,7 @@ pub struct Background {
     /// The raw BGRA pixeldata.
     /// This is optional because the associated data can be blank
     /// since in the IDE when you create a new background it has no associated data.
+    #[cfg_attr(feature = "serde", serde(with = "crate::base64::option"))]
     pub data: Option<Box<[u8]>>,
 }
 
@@ -41,7 +46,7 @@ impl Asset for Background {
             let len = reader.read_u32::<LE>()? as usize;
 
             // sanity check
-            if len != (width as usize * height as usize * 4) {
+            if Some(len) != (width as usize).checked_mul(height as usize).and_then(|n| n.checked_mul(4)) {
                 return Err(Error::MalformedData)
             }
 
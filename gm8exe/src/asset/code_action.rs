@@ -1,3 +1,6 @@
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
 use crate::{
     asset::{assert_ver, Error, PascalString, ReadPascalString, WritePascalString},
     def::ID,
@@ -9,6 +12,7 @@ use std::io::{self, Read};
 pub const VERSION: u32 = 440;
 pub const PARAM_COUNT: usize = 8;
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct CodeAction {
     /// Unique ID that identifies what type of DnD action this is.
     pub id: u32,
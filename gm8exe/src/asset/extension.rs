@@ -1,3 +1,6 @@
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
 use crate::{
     asset::{assert_ver, Error, PascalString, ReadPascalString},
     reader::inflate,
@@ -9,6 +12,7 @@ pub const VERSION: u32 = 700;
 
 const ARG_MAX: usize = 17;
 
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Extension {
     /// The name of the extension.
     pub name: PascalString,
@@ -20,6 +24,7 @@ pub struct Extension {
     pub files: Vec<File>,
 }
 
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct File {
     /// The name of the file.
     pub name: PascalString,
@@ -40,9 +45,11 @@ pub struct File {
     pub consts: Vec<FileConst>,
 
     /// The raw filedata itself.
+    #[cfg_attr(feature = "serde", serde(with = "crate::base64"))]
     pub contents: Box<[u8]>,
 }
 
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct FileConst {
     pub name: PascalString,
     pub value: PascalString,
@@ -50,6 +57,7 @@ pub struct FileConst {
 
 /// These const values are in line with the GM8 format. There is no zero.
 #[derive(Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum FileKind {
     DynamicLibrary = 1,
     GmlScript = 2,
@@ -71,6 +79,7 @@ impl From<u32> for FileKind {
 
 /// This is in line with GM8 data and there is no value corresponding to zero.
 #[derive(Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum FunctionValueKind {
     GMString = 1,
     GMReal = 2,
@@ -86,6 +95,7 @@ impl From<u32> for FunctionValueKind {
     }
 }
 
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct FileFunction {
     pub name: PascalString,
     pub external_name: PascalString,
@@ -97,6 +107,7 @@ pub struct FileFunction {
 }
 
 #[derive(Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum CallingConvention {
     Gml = 2,
     Stdcall = 11,
@@ -116,7 +127,8 @@ impl From<u32> for CallingConvention {
 }
 
 impl Extension {
-    pub fn read(reader: &mut io::Cursor<&mut [u8]>, strict: bool) -> Result<Self, Error> {
+    /// Reads an extension, inflating each of its files to at most `max_file_len` bytes.
+    pub fn read(reader: &mut io::Cursor<&mut [u8]>, strict: bool, max_file_len: u64) -> Result<Self, Error> {
         if strict {
             let version = reader.read_u32::<LE>()?;
             assert_ver(version, VERSION)?;
@@ -187,7 +199,7 @@ impl Extension {
             })
             .collect::<Result<_, Error>>()?;
 
-        let contents_len = reader.read_u32::<LE>()? as usize - 4;
+        let contents_len = (reader.read_u32::<LE>()? as usize).checked_sub(4).ok_or(Error::MalformedData)?;
         let seed1_raw = reader.read_u32::<LE>()?;
         let data_pos = reader.position() as usize;
         reader.seek(SeekFrom::Current(contents_len as _))?;
@@ -210,8 +222,8 @@ impl Extension {
             }
 
             // calculating char table - pass 1: pseudorandom byteswap
-            for i in 1..0x2711 {
-                let idx: usize = ((((i * seed2 as u32) + seed1 as u32) % 0xFE) + 1) as _;
+            for i in 1u32..0x2711 {
+                let idx: usize = ((i.wrapping_mul(seed2 as u32).wrapping_add(seed1 as u32) % 0xFE) + 1) as _;
                 let b1 = char_table[idx];
                 let b2 = char_table[idx + 1];
                 char_table[idx] = b2;
@@ -225,7 +237,8 @@ impl Extension {
             }
 
             // decrypt data chunk
-            for byte in &mut reader.get_mut()[data_pos + 1..data_pos + contents_len] {
+            let contents = reader.get_mut().get_mut(data_pos + 1..data_pos + contents_len);
+            for byte in contents.ok_or(io::Error::from(io::ErrorKind::UnexpectedEof))? {
                 *byte = char_table[*byte as usize + 0x100];
             }
 
@@ -240,8 +253,8 @@ impl Extension {
 
                     reader.seek(SeekFrom::Current(len as i64))?; // pre-check for next get
                     let mut file_bytes = Vec::new();
-                    inflate(reader.get_ref().get(pos..pos + len).unwrap_or_else(|| unreachable!()))
-                        .read_to_end(&mut file_bytes)?;
+                    let data = reader.get_ref().get(pos..pos.saturating_add(len)).ok_or(Error::MalformedData)?;
+                    inflate(data, max_file_len).read_to_end(&mut file_bytes)?;
                     file.contents = file_bytes.into_boxed_slice();
                 }
             }
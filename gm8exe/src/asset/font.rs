@@ -1,3 +1,6 @@
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
 use crate::{
     asset::{assert_ver, Asset, Error, PascalString, ReadChunk, ReadPascalString, WritePascalString},
     GameVersion,
@@ -7,6 +10,7 @@ use std::io::{self, Read};
 
 pub const VERSION: u32 = 800;
 
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Font {
     /// The asset name present in GML and the editor.
     pub name: PascalString,
@@ -44,6 +48,7 @@ pub struct Font {
     /// - height
     /// - cursor offset (ie. how far right of the cursor to draw)
     /// - cursor distance (ie. how far right to move the cursor after drawing.)
+    #[cfg_attr(feature = "serde", serde(with = "dmap"))]
     pub dmap: Box<[u32; 0x600]>,
 
     /// The width of the pixel map.
@@ -53,6 +58,7 @@ pub struct Font {
     pub map_height: u32,
 
     /// The raw pixel data for this font. It's a map of alpha values for each pixel, 0 to 255.
+    #[cfg_attr(feature = "serde", serde(with = "crate::base64"))]
     pub pixel_map: Box<[u8]>,
 }
 
@@ -121,6 +127,9 @@ impl Asset for Font {
                 .write_u32::<LE>(self.range_start | ((self.aa_level % 0x100) << 24) | ((self.charset % 0x100) << 16))?,
         }
         writer.write_u32::<LE>(self.range_end)?;
+        for val in self.dmap.iter() {
+            writer.write_u32::<LE>(*val)?;
+        }
         writer.write_u32::<LE>(self.map_width)?;
         writer.write_u32::<LE>(self.map_height)?;
         writer.write_u32::<LE>(self.pixel_map.len() as u32)?; // TODO: len as u32
@@ -128,3 +137,21 @@ impl Asset for Font {
         Ok(())
     }
 }
+
+/// Serde only handles arrays of up to 32 elements, so the dmap goes through a slice.
+#[cfg(feature = "serde")]
+mod dmap {
+    use serde::{de, Deserialize, Deserializer, Serializer};
+    use std::convert::TryFrom;
+
+    pub fn serialize<S: Serializer>(dmap: &[u32; 0x600], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(dmap.iter())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Box<[u32; 0x600]>, D::Error> {
+        let dmap = Vec::<u32>::deserialize(deserializer)?;
+        let len = dmap.len();
+        <Box<[u32; 0x600]>>::try_from(dmap.into_boxed_slice())
+            .map_err(|_| de::Error::invalid_length(len, &"0x600 values"))
+    }
+}
@@ -1,5 +1,8 @@
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
 use crate::{
-    asset::{assert_ver, Asset, Error, PascalString, ReadChunk, ReadPascalString, WritePascalString},
+    asset::{self, assert_ver, Asset, Error, PascalString, ReadChunk, ReadPascalString, WritePascalString},
     GameVersion,
 };
 use byteorder::{ReadBytesExt, WriteBytesExt, LE};
@@ -7,6 +10,7 @@ use std::io::{self, Read};
 
 pub const VERSION: u32 = 800;
 
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct IncludedFile {
     /// The name of the included file.
     pub file_name: PascalString,
@@ -24,6 +28,7 @@ pub struct IncludedFile {
     pub stored_in_gmk: bool,
 
     /// Contains the embedded data, if it is embedded.
+    #[cfg_attr(feature = "serde", serde(with = "crate::base64::option"))]
     pub embedded_data: Option<Box<[u8]>>,
 
     /// The export settings used for the file on load.
@@ -40,6 +45,7 @@ pub struct IncludedFile {
     pub remove_at_end: bool,
 }
 
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum ExportSetting {
     NoExport,
     TempFolder,
@@ -134,4 +140,9 @@ impl Asset for IncludedFile {
         writer.write_u32::<LE>(self.remove_at_end.into())?;
         Ok(())
     }
+
+    fn read_name(mut reader: impl Read) -> Option<PascalString> {
+        reader.read_u32::<LE>().ok()?;
+        asset::read_name(reader)
+    }
 }
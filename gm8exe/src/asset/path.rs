@@ -1,3 +1,6 @@
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
 use crate::{
     asset::{assert_ver, Asset, Error, PascalString, ReadPascalString, WritePascalString},
     GameVersion,
@@ -7,6 +10,7 @@ use std::io::{self, Read};
 
 pub const VERSION: u32 = 530;
 
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Path {
     /// The asset name present in GML and the editor.
     pub name: PascalString,
@@ -26,6 +30,7 @@ pub struct Path {
 }
 
 #[derive(Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum ConnectionKind {
     /// Normal, linear point-to-point path.
     StraightLine = 0,
@@ -34,6 +39,7 @@ pub enum ConnectionKind {
     SmoothCurve = 1,
 }
 
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Point {
     pub x: f64,
     pub y: f64,
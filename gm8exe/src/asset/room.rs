@@ -1,3 +1,6 @@
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
 use crate::{
     asset::{assert_ver_multiple, Asset, Error, PascalString, ReadPascalString, WritePascalString},
     colour::Colour,
@@ -9,6 +12,7 @@ use std::io::{self, Read};
 
 pub const VERSION: u32 = 541;
 
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Room {
     /// The asset name present in GML and the editor.
     pub name: PascalString,
@@ -55,6 +59,7 @@ pub struct Room {
     pub uses_811_features: bool,
 }
 
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Background {
     pub visible_on_start: bool,
 
@@ -72,6 +77,7 @@ pub struct Background {
     pub stretch: bool,
 }
 
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Instance {
     pub x: i32,
     pub y: i32,
@@ -84,6 +90,7 @@ pub struct Instance {
     pub angle: f64,
 }
 
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Tile {
     pub x: i32,
     pub y: i32,
@@ -99,6 +106,7 @@ pub struct Tile {
     pub blend: u32,
 }
 
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct View {
     pub visible: bool,
     pub source_x: i32,
@@ -112,6 +120,7 @@ pub struct View {
     pub following: ViewFollowData,
 }
 
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct ViewFollowData {
     pub hborder: i32,
     pub vborder: i32,
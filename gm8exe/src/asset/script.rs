@@ -1,3 +1,6 @@
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
 use crate::{
     asset::{assert_ver, Asset, Error, PascalString, ReadPascalString, WritePascalString},
     GameVersion,
@@ -7,6 +10,7 @@ use std::io::{self, Read};
 
 pub const VERSION: u32 = 800;
 
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Script {
     /// The asset name present in GML and the editor.
     pub name: PascalString,
@@ -1,3 +1,6 @@
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
 use crate::{
     asset::{assert_ver, Asset, Error, PascalString, ReadChunk, ReadPascalString, WritePascalString},
     GameVersion,
@@ -7,6 +10,7 @@ use std::io::{self, Read};
 
 pub const VERSION: u32 = 800;
 
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Sound {
     /// The asset name present in GML and the editor.
     pub name: PascalString,
@@ -20,6 +24,7 @@ pub struct Sound {
     /// The raw filedata.
     /// This is optional because the associated data can be blank
     /// since in the IDE when you create a new sound it has no associated data.
+    #[cfg_attr(feature = "serde", serde(with = "crate::base64::option"))]
     pub data: Option<Box<[u8]>>,
 
     /// Stupid legacy garbage indicating what kind of sound it is.
@@ -47,6 +52,7 @@ pub struct Sound {
 }
 
 /// Various filters which can be set on any sound.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct SoundFX {
     pub chorus: bool,
     pub echo: bool,
@@ -56,6 +62,7 @@ pub struct SoundFX {
 }
 
 #[derive(Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum SoundKind {
     /// Normal Sound
     Normal = 0,
@@ -1,3 +1,6 @@
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
 use crate::{
     asset::{
         assert_ver, assert_ver_multiple, Asset, Error, PascalString, ReadChunk, ReadPascalString, WritePascalString,
@@ -11,6 +14,7 @@ pub const VERSION: u32 = 800;
 pub const VERSION_COLLISION: u32 = 800;
 pub const VERSION_FRAME: u32 = 800;
 
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Sprite {
     /// The asset name present in GML and the editor.
     pub name: PascalString,
@@ -32,12 +36,15 @@ pub struct Sprite {
     pub per_frame_colliders: bool,
 }
 
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Frame {
     pub width: u32,
     pub height: u32,
+    #[cfg_attr(feature = "serde", serde(with = "crate::base64"))]
     pub data: Box<[u8]>,
 }
 
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct CollisionMap {
     // width of the boolean map
     pub width: u32,
@@ -104,7 +111,7 @@ impl Asset for Sprite {
                 let bbox_bottom = reader.read_u32::<LE>()?;
                 let bbox_top = reader.read_u32::<LE>()?;
 
-                let pixel_count = width as usize * height as usize;
+                let pixel_count = (width as usize).checked_mul(height as usize).ok_or(Error::MalformedData)?;
                 let data = (0..pixel_count)
                     .map(|_| reader.read_u32::<LE>().map(|x| x != 0))
                     .collect::<Result<Vec<_>, _>>()?
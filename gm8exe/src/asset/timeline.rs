@@ -1,3 +1,6 @@
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
 use crate::{
     asset::{assert_ver, Asset, CodeAction, Error, PascalString, ReadPascalString, WritePascalString},
     GameVersion,
@@ -8,6 +11,7 @@ use std::io::{self, Read};
 pub const VERSION: u32 = 500;
 pub const VERSION_MOMENT: u32 = 400;
 
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Timeline {
     /// The asset name present in GML and the editor.
     pub name: PascalString,
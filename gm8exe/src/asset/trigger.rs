@@ -1,5 +1,8 @@
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
 use crate::{
-    asset::{assert_ver, Asset, Error, PascalString, ReadPascalString, WritePascalString},
+    asset::{self, assert_ver, Asset, Error, PascalString, ReadPascalString, WritePascalString},
     GameVersion,
 };
 use byteorder::{ReadBytesExt, WriteBytesExt, LE};
@@ -10,6 +13,7 @@ use std::{
 
 pub const VERSION: u32 = 800;
 
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Trigger {
     /// The asset name present in the editor.
     ///
@@ -28,6 +32,7 @@ pub struct Trigger {
 }
 
 #[derive(Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum TriggerKind {
     Step = 0,
     BeginStep = 1,
@@ -79,4 +84,9 @@ impl Asset for Trigger {
         writer.write_pas_string(&self.constant_name)?;
         Ok(())
     }
+
+    fn read_name(mut reader: impl Read) -> Option<PascalString> {
+        reader.read_u32::<LE>().ok()?;
+        asset::read_name(reader)
+    }
 }
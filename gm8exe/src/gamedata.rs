@@ -1,124 +1,267 @@
 pub mod antidec;
 pub mod gm80;
 pub mod gm81;
+pub mod legacy;
 
-use crate::{reader::ReaderError, upx, GameVersion};
-use std::io::{self, Seek, SeekFrom};
+use crate::{
+    reader::{ReaderError, ReaderEvent},
+    upx, GameVersion,
+};
+use std::{
+    fmt::{self, Display},
+    io::{self, Seek, SeekFrom},
+};
 
-/// Identifies the game version and start of gamedata header, given a data cursor.
-/// Also removes any version-specific encryptions.
+/// Something put on top of a game to stop it being read, which had to be removed first.
+#[derive(Clone, Copy, Debug)]
+pub enum Protection {
+    /// The runner is UPX packed. `max_size` is what the section headers allow for, `unpacked_len` what it took.
+    Upx { max_size: u32, unpacked_len: usize },
+
+    /// The gamedata is encrypted by antidec2, with these settings.
+    Antidec { version: GameVersion, settings: antidec::Metadata },
+
+    /// GM8.1 gamedata is encrypted with SUDALV's variation on the usual XOR.
+    Sudalv,
+}
+impl Display for Protection {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Protection::Upx { unpacked_len, .. } => write!(f, "UPX (runner unpacked to {} bytes)", unpacked_len),
+            Protection::Antidec { version, settings } => write!(
+                f,
+                "antidec2 for {} (xor mask 0x{:X}, add mask 0x{:X}, sub mask 0x{:X})",
+                match version {
+                    GameVersion::GameMaker8_0 => "GameMaker 8.0",
+                    GameVersion::GameMaker8_1 => "GameMaker 8.1",
+                },
+                settings.xor_mask,
+                settings.add_mask,
+                settings.sub_mask
+            ),
+            Protection::Sudalv => write!(f, "SUDALV's GameMaker 8.1 encryption"),
+        }
+    }
+}
+
+/// Finds the gamedata in an exe, removing whatever protects it, and returns which version of GameMaker it's for.
+/// Returns `None` if the exe doesn't look like what it detects. Once it's started changing the exe, it has to
+/// either finish or fail, since the next detector would be left with a mess.
+///
+/// `runner` is the unpacked runner, for games where UPX packed it. Otherwise, the runner is `exe` itself.
+pub type Detect = fn(
+    exe: &mut io::Cursor<&mut [u8]>,
+    runner: Option<&mut io::Cursor<&mut [u8]>>,
+    events: Option<&dyn Fn(ReaderEvent)>,
+) -> Result<Option<GameVersion>, ReaderError>;
+
+/// A way of finding the gamedata of some games. See [`DETECTORS`].
+#[derive(Clone, Copy, Debug)]
+pub struct Detector {
+    /// A short name, for picking one by hand.
+    pub name: &'static str,
+
+    /// What it finds, for telling users about.
+    pub description: &'static str,
+
+    /// Whether it can find the gamedata when the runner is UPX packed. Those that can't are skipped for such games.
+    pub upx: bool,
+
+    pub detect: Detect,
+}
+
+/// Every known way of finding gamedata, in the order they're tried. The ones which search the whole exe come last,
+/// since they're slow and only needed for runners which were modified by hand.
+pub const DETECTORS: &[Detector] = &[
+    Detector { name: "antidec80", description: "antidec2 on GameMaker 8.0", upx: true, detect: detect_antidec80 },
+    Detector { name: "antidec81", description: "antidec2 on GameMaker 8.1", upx: true, detect: detect_antidec81 },
+    Detector { name: "gm80", description: "GameMaker 8.0", upx: false, detect: detect_gm80 },
+    Detector { name: "gm81", description: "GameMaker 8.1", upx: false, detect: detect_gm81 },
+    Detector {
+        name: "gm81-default",
+        description: "GameMaker 8.1, found at its default header",
+        upx: false,
+        detect: detect_gm81_default,
+    },
+    Detector {
+        name: "gm80-scan",
+        description: "GameMaker 8.0 with a modified runner, found by searching for its header",
+        upx: true,
+        detect: detect_gm80_scan,
+    },
+    Detector {
+        name: "gm81-scan",
+        description: "GameMaker 8.1 with a modified runner, found by searching for its header",
+        upx: true,
+        detect: detect_gm81_scan,
+    },
+];
+
+/// Looks up one of [`DETECTORS`] by name.
+pub fn detector(name: &str) -> Option<&'static Detector> {
+    DETECTORS.iter().find(|detector| detector.name == name)
+}
+
+/// Identifies the game version and start of gamedata header, given a data cursor, by trying each of `detectors`.
+/// Also removes any version-specific encryptions. UPX is unpacked to at most `max_unpacked_len` bytes.
 pub fn find<F>(
     exe: &mut io::Cursor<&mut [u8]>,
-    logger: Option<F>,
+    events: Option<F>,
     upx_data: Option<(u32, u32)>,
+    max_unpacked_len: usize,
+    detectors: &[Detector],
 ) -> Result<GameVersion, ReaderError>
 where
-    F: Copy + Fn(&str),
+    F: Copy + Fn(ReaderEvent),
 {
-    // helper fn for logging antidec settings
-    let log_antidec = |data: antidec::Metadata| {
-        log!(
-            logger,
-            "exe_load_offset:0x{:X} header_start:0x{:X} xor_mask:0x{:X} add_mask:0x{:X} sub_mask:0x{:X}",
-            data.exe_load_offset,
-            data.header_start,
-            data.xor_mask,
-            data.add_mask,
-            data.sub_mask
-        );
-    };
+    let events = events.as_ref().map(|events| events as &dyn Fn(ReaderEvent));
 
     // Check if UPX is in use first
-    match upx_data {
+    let mut unpacked = match upx_data {
         Some((max_size, disk_offset)) => {
             // UPX in use, let's unpack it
-            let mut unpacked = upx::unpack(exe, max_size, disk_offset, logger)?;
-            log!(logger, "Successfully unpacked UPX - output is {} bytes", unpacked.len());
-            let mut unpacked = io::Cursor::new(&mut *unpacked);
-
-            // UPX unpacked, now check if this is a supported data format
-            if let Some(antidec_settings) = antidec::check80(&mut unpacked)? {
-                if logger.is_some() {
-                    log!(logger, "Found GM8.0 antidec2 loading sequence, decrypting with these settings:");
-                    log_antidec(antidec_settings);
-                }
-                if antidec::decrypt(exe, antidec_settings)? {
-                    // 8.0-specific header, but no point strict-checking it because antidec puts random garbage there.
-                    exe.seek(SeekFrom::Current(16))?;
-                    Ok(GameVersion::GameMaker8_0)
-                } else {
-                    // Antidec couldn't be decrypted with the settings we read, so we must have got the format wrong
-                    Err(ReaderError::UnknownFormat)
-                }
-            } else if let Some(antidec_settings) = antidec::check81(&mut unpacked)? {
-                log!(logger, "Found GM8.1 antidec2 loading sequence, decrypting with these settings:");
-                log_antidec(antidec_settings);
-                if antidec::decrypt(exe, antidec_settings)? {
-                    // Search for header
-                    let found_header = gm81::seek_value(exe, 0xF7140067)?.is_some();
-
-                    if found_header {
-                        gm81::decrypt(exe, logger, gm81::XorMethod::Normal)?;
-                        exe.seek(SeekFrom::Current(20))?;
-                        Ok(GameVersion::GameMaker8_1)
-                    } else {
-                        log!(logger, "Didn't find GM81 magic value (0xF7140017) before EOF, so giving up");
-                        Err(ReaderError::UnknownFormat)
-                    }
-                } else {
-                    // Antidec couldn't be decrypted with the settings we read, so we must have got the format wrong
-                    Err(ReaderError::UnknownFormat)
-                }
-            } else {
-                Err(ReaderError::UnknownFormat)
-            }
-        },
-        None => {
-            if let Some(antidec_settings) = antidec::check80(exe)? {
-                // antidec2 protection in the base exe (so without UPX on top of it)
-                if logger.is_some() {
-                    log!(logger, "Found GM8.0 antidec2 loading sequence [no UPX], decrypting with these settings:");
-                    log_antidec(antidec_settings);
-                }
-                if antidec::decrypt(exe, antidec_settings)? {
-                    // 8.0-specific header, but no point strict-checking it because antidec puts random garbage there.
-                    exe.seek(SeekFrom::Current(16))?;
-                    Ok(GameVersion::GameMaker8_0)
-                } else {
-                    // Antidec couldn't be decrypted with the settings we read, so we must have got the format wrong
-                    Err(ReaderError::UnknownFormat)
-                }
-            } else if let Some(antidec_settings) = antidec::check81(exe)? {
-                // antidec81 protection in the base exe (so without UPX on top of it)
-                if logger.is_some() {
-                    log!(logger, "Found GM8.1 antidec2 loading sequence [no UPX], decrypting with these settings:");
-                    log_antidec(antidec_settings);
-                }
-                if antidec::decrypt(exe, antidec_settings)? {
-                    let found_header = gm81::seek_value(exe, 0xF7140067)?.is_some();
-
-                    if found_header {
-                        gm81::decrypt(exe, logger, gm81::XorMethod::Normal)?;
-                        exe.seek(SeekFrom::Current(20))?;
-                        Ok(GameVersion::GameMaker8_1)
-                    } else {
-                        log!(logger, "Didn't find GM81 magic value (0xF7140017) before EOF, so giving up");
-                        Err(ReaderError::UnknownFormat)
-                    }
-                } else {
-                    // Antidec couldn't be decrypted with the settings we read, so we must have got the format wrong
-                    Err(ReaderError::UnknownFormat)
-                }
-            } else {
-                // Standard formats
-                if gm80::check(exe, logger)? {
-                    Ok(GameVersion::GameMaker8_0)
-                } else if gm81::check(exe, logger)? || gm81::check_lazy(exe, logger)? {
-                    Ok(GameVersion::GameMaker8_1)
-                } else {
-                    Err(ReaderError::UnknownFormat)
-                }
-            }
+            let unpacked = upx::unpack(exe, max_size, disk_offset, max_unpacked_len, events)?;
+            log!(events, "Successfully unpacked UPX - output is {} bytes", unpacked.len());
+            let upx = Protection::Upx { max_size, unpacked_len: unpacked.len() };
+            event!(events, ReaderEvent::ProtectionDetected(upx));
+            Some(unpacked)
         },
+        None => None,
+    };
+
+    for detector in detectors {
+        if unpacked.is_some() && !detector.upx {
+            continue
+        }
+        let mut runner = unpacked.as_mut().map(|unpacked| io::Cursor::new(&mut unpacked[..]));
+        if let Some(version) = (detector.detect)(exe, runner.as_mut(), events)? {
+            log!(events, "Found gamedata with '{}' ({})", detector.name, detector.description);
+            event!(events, ReaderEvent::Detected(detector));
+            return Ok(version)
+        }
+    }
+
+    if let Some(version) = legacy::check(exe, events) {
+        Err(ReaderError::UnsupportedVersion(version))
+    } else {
+        Err(ReaderError::UnknownFormat)
+    }
+}
+
+/// Logs and reports the settings antidec2 was found with.
+fn report_antidec(events: Option<&dyn Fn(ReaderEvent)>, version: GameVersion, data: antidec::Metadata) {
+    event!(events, ReaderEvent::ProtectionDetected(Protection::Antidec { version, settings: data }));
+    log!(
+        events,
+        "exe_load_offset:0x{:X} header_start:0x{:X} xor_mask:0x{:X} add_mask:0x{:X} sub_mask:0x{:X}",
+        data.exe_load_offset,
+        data.header_start,
+        data.xor_mask,
+        data.add_mask,
+        data.sub_mask
+    );
+}
+
+fn detect_antidec80(
+    exe: &mut io::Cursor<&mut [u8]>,
+    runner: Option<&mut io::Cursor<&mut [u8]>>,
+    events: Option<&dyn Fn(ReaderEvent)>,
+) -> Result<Option<GameVersion>, ReaderError> {
+    let antidec_settings = match runner {
+        Some(runner) => antidec::check80(runner)?,
+        None => antidec::check80(exe)?,
+    };
+    let antidec_settings = match antidec_settings {
+        Some(settings) => settings,
+        None => return Ok(None),
+    };
+    log!(events, "Found GM8.0 antidec2 loading sequence, decrypting with these settings:");
+    report_antidec(events, GameVersion::GameMaker8_0, antidec_settings);
+    if antidec::decrypt(exe, antidec_settings)? {
+        // 8.0-specific header, but no point strict-checking it because antidec puts random garbage there.
+        exe.seek(SeekFrom::Current(16))?;
+        Ok(Some(GameVersion::GameMaker8_0))
+    } else {
+        // Antidec couldn't be decrypted with the settings we read, so we must have got the format wrong
+        Err(ReaderError::UnknownFormat)
+    }
+}
+
+fn detect_antidec81(
+    exe: &mut io::Cursor<&mut [u8]>,
+    runner: Option<&mut io::Cursor<&mut [u8]>>,
+    events: Option<&dyn Fn(ReaderEvent)>,
+) -> Result<Option<GameVersion>, ReaderError> {
+    let antidec_settings = match runner {
+        Some(runner) => antidec::check81(runner)?,
+        None => antidec::check81(exe)?,
+    };
+    let antidec_settings = match antidec_settings {
+        Some(settings) => settings,
+        None => return Ok(None),
+    };
+    log!(events, "Found GM8.1 antidec2 loading sequence, decrypting with these settings:");
+    report_antidec(events, GameVersion::GameMaker8_1, antidec_settings);
+    if antidec::decrypt(exe, antidec_settings)? {
+        // Search for header
+        if gm81::seek_value(exe, 0xF7140067)?.is_some() {
+            gm81::decrypt(exe, events, gm81::XorMethod::Normal)?;
+            exe.seek(SeekFrom::Current(20))?;
+            Ok(Some(GameVersion::GameMaker8_1))
+        } else {
+            log!(events, "Didn't find GM81 magic value (0xF7140017) before EOF, so giving up");
+            Err(ReaderError::UnknownFormat)
+        }
+    } else {
+        // Antidec couldn't be decrypted with the settings we read, so we must have got the format wrong
+        Err(ReaderError::UnknownFormat)
+    }
+}
+
+fn detect_gm80(
+    exe: &mut io::Cursor<&mut [u8]>,
+    _runner: Option<&mut io::Cursor<&mut [u8]>>,
+    events: Option<&dyn Fn(ReaderEvent)>,
+) -> Result<Option<GameVersion>, ReaderError> {
+    Ok(if gm80::check(exe, events)? { Some(GameVersion::GameMaker8_0) } else { None })
+}
+
+fn detect_gm81(
+    exe: &mut io::Cursor<&mut [u8]>,
+    _runner: Option<&mut io::Cursor<&mut [u8]>>,
+    events: Option<&dyn Fn(ReaderEvent)>,
+) -> Result<Option<GameVersion>, ReaderError> {
+    Ok(if gm81::check(exe, events)? { Some(GameVersion::GameMaker8_1) } else { None })
+}
+
+fn detect_gm81_default(
+    exe: &mut io::Cursor<&mut [u8]>,
+    _runner: Option<&mut io::Cursor<&mut [u8]>>,
+    events: Option<&dyn Fn(ReaderEvent)>,
+) -> Result<Option<GameVersion>, ReaderError> {
+    Ok(if gm81::check_lazy(exe, events)? { Some(GameVersion::GameMaker8_1) } else { None })
+}
+
+fn detect_gm80_scan(
+    exe: &mut io::Cursor<&mut [u8]>,
+    _runner: Option<&mut io::Cursor<&mut [u8]>>,
+    events: Option<&dyn Fn(ReaderEvent)>,
+) -> Result<Option<GameVersion>, ReaderError> {
+    Ok(if gm80::scan(exe, events) { Some(GameVersion::GameMaker8_0) } else { None })
+}
+
+fn detect_gm81_scan(
+    exe: &mut io::Cursor<&mut [u8]>,
+    _runner: Option<&mut io::Cursor<&mut [u8]>>,
+    events: Option<&dyn Fn(ReaderEvent)>,
+) -> Result<Option<GameVersion>, ReaderError> {
+    log!(events, "Searching the whole exe for a GM8.1 data header");
+    exe.set_position(0);
+    if gm81::seek_value(exe, 0xF7140067)?.is_some() {
+        gm81::decrypt(exe, events, gm81::XorMethod::Normal)?;
+        exe.seek(SeekFrom::Current(20))?;
+        Ok(Some(GameVersion::GameMaker8_1))
+    } else {
+        Ok(None)
     }
 }
@@ -2,7 +2,7 @@ use byteorder::{ReadBytesExt, LE};
 use std::io::{self, Read, Seek, SeekFrom};
 
 /// The settings used to decrypt antidec2-protected data, usually extracted from machine code
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug)]
 pub struct Metadata {
     pub exe_load_offset: u32,
     pub header_start: u32,
@@ -94,7 +94,10 @@ pub fn check81(exe: &mut io::Cursor<&mut [u8]>) -> io::Result<Option<Metadata>>
 /// Returns true on success, or false indicating that the provided settings are incompatible with the data.
 pub fn decrypt(data: &mut io::Cursor<&mut [u8]>, settings: Metadata) -> io::Result<bool> {
     // Offset in the file where the header is
-    let offset = settings.exe_load_offset + settings.header_start;
+    let offset = match settings.exe_load_offset.checked_add(settings.header_start) {
+        Some(offset) if offset >= 4 => offset,
+        _ => return Ok(false),
+    };
     // Subtract 4 from that position to make sure the first chunk gets decrypted, in case it isn't 4-byte aligned
     let game_data = match data.get_mut().get_mut((offset - 4) as usize..) {
         Some(d) => d,
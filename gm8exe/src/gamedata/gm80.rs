@@ -1,3 +1,4 @@
+use crate::reader::ReaderEvent;
 use byteorder::{ReadBytesExt, LE};
 use std::{
     cmp::max,
@@ -6,15 +7,15 @@ use std::{
 
 /// Check if this is a standard gm8.0 game by looking for the loading sequence
 /// If so, sets the cursor to the start of the gamedata.
-pub fn check<F>(exe: &mut io::Cursor<&mut [u8]>, logger: Option<F>) -> io::Result<bool>
+pub fn check<F>(exe: &mut io::Cursor<&mut [u8]>, events: Option<F>) -> io::Result<bool>
 where
-    F: Copy + Fn(&str),
+    F: Copy + Fn(ReaderEvent),
 {
-    log!(logger, "Checking for standard GM8.0 format...");
+    log!(events, "Checking for standard GM8.0 format...");
 
     // Verify size is large enough to do the following checks - otherwise it can't be this format
     if exe.get_ref().len() < 0x144AC0 + 4 {
-        log!(logger, "File too short for this format (0x{:X} bytes)", exe.get_ref().len());
+        log!(events, "File too short for this format (0x{:X} bytes)", exe.get_ref().len());
         return Ok(false)
     }
 
@@ -31,20 +32,20 @@ where
                 let mut buf = [0u8; 6];
                 exe.read_exact(&mut buf)?;
                 if buf == [0x0F, 0x85, 0x18, 0x01, 0x00, 0x00] {
-                    log!(logger, "GM8.0 magic check looks intact - value is {}", magic);
+                    log!(events, "GM8.0 magic check looks intact - value is {}", magic);
                     Some(magic)
                 } else {
-                    log!(logger, "GM8.0 magic check's JNZ is patched out");
+                    log!(events, "GM8.0 magic check's JNZ is patched out");
                     None
                 }
             },
             0x90 => {
                 exe.seek(SeekFrom::Current(4))?;
-                log!(logger, "GM8.0 magic check is patched out with NOP");
+                log!(events, "GM8.0 magic check is patched out with NOP");
                 None
             },
             i => {
-                log!(logger, "Unknown instruction in place of magic CMP: {}", i);
+                log!(events, "Unknown instruction in place of magic CMP: {}", i);
                 return Ok(false)
             },
         };
@@ -61,7 +62,7 @@ where
                         let mut buf = [0u8; 6];
                         exe.read_exact(&mut buf)?;
                         if buf == [0x0F, 0x85, 0xF5, 0x00, 0x00, 0x00] {
-                            log!(logger, "GM8.0 header version check looks intact - value is {}", magic);
+                            log!(events, "GM8.0 header version check looks intact - value is {}", magic);
                             Some(magic)
                         } else {
                             println!("GM8.0 header version check's JNZ is patched out");
@@ -70,16 +71,16 @@ where
                     },
                     0x90 => {
                         exe.seek(SeekFrom::Current(4))?;
-                        log!(logger, "GM8.0 header version check is patched out with NOP");
+                        log!(events, "GM8.0 header version check is patched out with NOP");
                         None
                     },
                     i => {
-                        log!(logger, "Unknown instruction in place of magic CMP: {}", i);
+                        log!(events, "Unknown instruction in place of magic CMP: {}", i);
                         return Ok(false)
                     },
                 }
             } else {
-                log!(logger, "GM8.0 header version check appears patched out");
+                log!(events, "GM8.0 header version check appears patched out");
                 None
             }
         };
@@ -87,7 +88,7 @@ where
         // Read header start pos
         exe.set_position(0x144AC0);
         let header_start = exe.read_u32::<LE>()?;
-        log!(logger, "Reading header from 0x{:X}", header_start);
+        log!(events, "Reading header from 0x{:X}", header_start);
         exe.set_position(header_start as u64);
 
         // Check the header magic numbers are what we read them as
@@ -97,7 +98,7 @@ where
                     let header1 = match exe.read_u32::<LE>() {
                         Ok(h) => h,
                         _ => {
-                            log!(logger, "Passed end of stream looking for GM8.0 header, so quitting");
+                            log!(events, "Passed end of stream looking for GM8.0 header, so quitting");
                             return Ok(false)
                         },
                     };
@@ -105,7 +106,7 @@ where
                         break
                     } else {
                         log!(
-                            logger,
+                            events,
                             "Didn't find GM8.0 header at {}: expected {}, got {}",
                             exe.position() - 4,
                             n,
@@ -124,7 +125,7 @@ where
             Some(n) => {
                 let header2 = exe.read_u32::<LE>()?;
                 if header2 != n {
-                    log!(logger, "Failed to read GM8.0 header: expected version {}, got {}", n, header2);
+                    log!(events, "Failed to read GM8.0 header: expected version {}, got {}", n, header2);
                     return Ok(false)
                 }
             },
@@ -140,10 +141,30 @@ where
     }
 }
 
+/// Searches the whole exe for a standard gm8.0 header, for runners which were changed so that `check` can't read them.
+/// If found, sets the cursor to the start of the gamedata.
+pub fn scan<F>(exe: &mut io::Cursor<&mut [u8]>, events: Option<F>) -> bool
+where
+    F: Copy + Fn(ReaderEvent),
+{
+    log!(events, "Searching the whole exe for a GM8.0 data header");
+    let mut header = [0u8; 8];
+    header[..4].copy_from_slice(&1234321u32.to_le_bytes());
+    header[4..].copy_from_slice(&800u32.to_le_bytes());
+    match exe.get_ref().windows(header.len()).position(|window| window == header) {
+        Some(pos) => {
+            log!(events, "Found GM8.0 header at {}", pos);
+            exe.set_position(pos as u64 + 16);
+            true
+        },
+        None => false,
+    }
+}
+
 /// Removes GameMaker 8.0 protection in-place.
-pub fn decrypt<F>(data: &mut io::Cursor<&mut [u8]>, logger: Option<F>) -> io::Result<()>
+pub fn decrypt<F>(data: &mut io::Cursor<&mut [u8]>, events: Option<F>) -> io::Result<()>
 where
-    F: Copy + Fn(&str),
+    F: Copy + Fn(ReaderEvent),
 {
     let mut swap_table = [0u8; 256];
     let mut reverse_table = [0u8; 256];
@@ -152,7 +173,7 @@ where
     let garbage1_size = data.read_u32::<LE>()? as i64 * 4;
     let garbage2_size = data.read_u32::<LE>()? as i64 * 4;
     data.seek(SeekFrom::Current(garbage1_size))?;
-    assert_eq!(data.read(&mut swap_table)?, 256);
+    data.read_exact(&mut swap_table)?;
     data.seek(SeekFrom::Current(garbage2_size))?;
 
     // fill up reverse table
@@ -166,7 +187,10 @@ where
     // simplifying for expressions below
     let pos = data.position() as usize; // stream position
     let data = data.get_mut(); // mutable ref for writing
-    log!(logger, "Decrypting asset data... (size: {}, garbage1: {}, garbage2: {})", len, garbage1_size, garbage2_size);
+    if pos.checked_add(len).map_or(true, |end| end > data.len()) {
+        return Err(io::ErrorKind::UnexpectedEof.into())
+    }
+    log!(events, "Decrypting asset data... (size: {}, garbage1: {}, garbage2: {})", len, garbage1_size, garbage2_size);
 
     // decryption: first pass
     //   in reverse, data[i-1] = rev[data[i-1]] - (data[i-2] + (i - (pos+1)))
@@ -178,7 +202,7 @@ where
     // decryption: second pass
     //   for each byte from end of the file, calculate a byte position to swap with, then swap them over
     for i in (pos..pos + len).rev() {
-        let b = max(i as u32 - swap_table[(i - pos) & 0xFF] as u32, pos as u32);
+        let b = max((i as u32).saturating_sub(swap_table[(i - pos) & 0xFF] as u32), pos as u32);
         data.swap(i, b as usize);
     }
 
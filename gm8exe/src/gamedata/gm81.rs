@@ -1,3 +1,4 @@
+use crate::{gamedata::Protection, reader::ReaderEvent};
 use byteorder::{ReadBytesExt, LE};
 use std::{
     io::{self, Read, Seek, SeekFrom},
@@ -11,15 +12,15 @@ pub enum XorMethod {
 
 /// Check if this is a standard gm8.1 game by looking for the loading sequence
 /// If so, removes gm81 encryption and sets the cursor to the start of the gamedata.
-pub fn check<F>(exe: &mut io::Cursor<&mut [u8]>, logger: Option<F>) -> io::Result<bool>
+pub fn check<F>(exe: &mut io::Cursor<&mut [u8]>, events: Option<F>) -> io::Result<bool>
 where
-    F: Copy + Fn(&str),
+    F: Copy + Fn(ReaderEvent),
 {
-    log!(logger, "Checking for standard GM8.1 format");
+    log!(events, "Checking for standard GM8.1 format");
 
     // Verify size is large enough to do the following checks - otherwise it can't be this format
     if exe.get_ref().len() < 0x226D8A {
-        log!(logger, "File too short for this format (0x{:X} bytes)", exe.get_ref().len());
+        log!(events, "File too short for this format (0x{:X} bytes)", exe.get_ref().len());
         return Ok(false)
     }
 
@@ -40,15 +41,15 @@ where
             [0x81, 0x7D, 0xEC] => {
                 let magic = exe.read_u32::<LE>()?;
                 if exe.read_u8()? == 0x74 {
-                    log!(logger, "GM8.1 magic check looks intact - value is 0x{:X}", magic);
+                    log!(events, "GM8.1 magic check looks intact - value is 0x{:X}", magic);
                     Some(magic)
                 } else {
-                    log!(logger, "GM8.1 magic check's JE is patched out");
+                    log!(events, "GM8.1 magic check's JE is patched out");
                     None
                 }
             },
             b => {
-                log!(logger, "GM8.1 magic check's CMP is patched out ({:?})", b);
+                log!(events, "GM8.1 magic check's CMP is patched out ({:?})", b);
                 None
             },
         };
@@ -59,7 +60,8 @@ where
         exe.read_exact(&mut buf)?;
         let xor_method = match buf {
             [0x8B, 0x02, 0xC1, 0xE0, 0x10, 0x8B, 0x11, 0x81] => {
-                log!(logger, "Found SUDALV re-encryption");
+                log!(events, "Found SUDALV re-encryption");
+                event!(events, ReaderEvent::ProtectionDetected(Protection::Sudalv));
                 XorMethod::Sudalv
             },
             _ => XorMethod::Normal,
@@ -69,10 +71,10 @@ where
         exe.set_position(header_start as u64);
         match gm81_magic {
             Some(n) => {
-                log!(logger, "Searching for GM8.1 magic number {} from position {}", n, header_start);
+                log!(events, "Searching for GM8.1 magic number {} from position {}", n, header_start);
                 let found_header = seek_value(exe, n)?.is_some();
                 if !found_header {
-                    log!(logger, "Didn't find GM81 magic value (0x{:X}) before EOF, so giving up", n);
+                    log!(events, "Didn't find GM81 magic value (0x{:X}) before EOF, so giving up", n);
                     return Ok(false)
                 }
             },
@@ -81,7 +83,7 @@ where
             },
         }
 
-        decrypt(exe, logger, xor_method)?;
+        decrypt(exe, events, xor_method)?;
         exe.seek(SeekFrom::Current(20))?;
         Ok(true)
     } else {
@@ -91,15 +93,15 @@ where
 
 /// Check if this is a standard gm8.1 game by looking for the default header (last-resort method)
 /// If so, removes gm81 encryption and sets the cursor to the start of the gamedata.
-pub fn check_lazy<F>(exe: &mut io::Cursor<&mut [u8]>, logger: Option<F>) -> io::Result<bool>
+pub fn check_lazy<F>(exe: &mut io::Cursor<&mut [u8]>, events: Option<F>) -> io::Result<bool>
 where
-    F: Copy + Fn(&str),
+    F: Copy + Fn(ReaderEvent),
 {
-    log!(logger, "Searching for default GM8.1 data header");
+    log!(events, "Searching for default GM8.1 data header");
     exe.set_position(3800004);
     let found_header = seek_value(exe, 0xF7140067)?.is_some();
     if found_header {
-        decrypt(exe, logger, XorMethod::Normal)?;
+        decrypt(exe, events, XorMethod::Normal)?;
         exe.seek(SeekFrom::Current(20))?;
         Ok(true)
     } else {
@@ -113,6 +115,9 @@ where
 pub fn seek_value(exe: &mut io::Cursor<&mut [u8]>, value: u32) -> io::Result<Option<u32>> {
     let mut pos = exe.position();
     loop {
+        if pos.saturating_add(8) > exe.get_ref().len() as u64 {
+            break Ok(None)
+        }
         exe.set_position(pos);
         let d1 = exe.read_u32::<LE>()?;
         let d2 = exe.read_u32::<LE>()?;
@@ -122,16 +127,13 @@ pub fn seek_value(exe: &mut io::Cursor<&mut [u8]>, value: u32) -> io::Result<Opt
             break Ok(Some(parsed_xor))
         }
         pos += 1;
-        if ((pos + 8) as usize) >= exe.get_ref().len() {
-            break Ok(None)
-        }
     }
 }
 
 /// Removes GM8.1 encryption in-place.
-pub fn decrypt<F>(data: &mut io::Cursor<&mut [u8]>, logger: Option<F>, xor_method: XorMethod) -> io::Result<()>
+pub fn decrypt<F>(data: &mut io::Cursor<&mut [u8]>, events: Option<F>, xor_method: XorMethod) -> io::Result<()>
 where
-    F: Copy + Fn(&str),
+    F: Copy + Fn(ReaderEvent),
 {
     // YYG's crc32 implementation
     let crc_32 = |hash_key: &Vec<u8>, crc_table: &[u32; 256]| -> u32 {
@@ -152,7 +154,8 @@ where
         rvalue
     };
 
-    let sudalv_magic_point = (data.position() - 12) as u32;
+    let malformed = || io::Error::new(io::ErrorKind::InvalidData, "malformed GM8.1 encryption");
+    let sudalv_magic_point = data.position().checked_sub(12).ok_or_else(malformed)? as usize;
     let hash_key = format!("_MJD{}#RWK", data.read_u32::<LE>()?);
     let hash_key_utf16: Vec<u8> = hash_key.bytes().flat_map(|c| once(c).chain(once(0))).collect();
 
@@ -171,7 +174,7 @@ where
     let seed1 = data.read_u32::<LE>()?;
     let seed2 = crc_32(&hash_key_utf16, &crc_table);
 
-    log!(logger, "Decrypting GM8.1 protection (hashkey: {}, seed1: {}, seed2: {})", hash_key, seed1, seed2);
+    log!(events, "Decrypting GM8.1 protection (hashkey: {}, seed1: {}, seed2: {})", hash_key, seed1, seed2);
 
     // work out where gm81 encryption starts
     let encryption_start = data.position() + u64::from(seed2 & 0xFF) + 10;
@@ -180,13 +183,13 @@ where
     let mut generator = match xor_method {
         XorMethod::Normal => Box::new(NormalMaskGenerator { seed1, seed2 }) as Box<dyn Iterator<Item = u32>>,
         XorMethod::Sudalv => {
-            let mask_data = &data.get_ref()[..(sudalv_magic_point + 4) as usize];
+            let mask_data = data.get_ref().get(..sudalv_magic_point + 4).ok_or_else(malformed)?;
             let mask_count = mask_data
                 .rchunks_exact(2)
                 .skip(1)
                 .zip(mask_data.rchunks_exact(2))
                 .position(|xy| xy == (&[0, 0], &[0, 0]))
-                .unwrap();
+                .ok_or_else(malformed)?;
             let iter = mask_data
                 .rchunks_exact(2)
                 .skip(1)
@@ -200,7 +203,7 @@ where
     };
 
     // Decrypt stream from encryption_start
-    let game_data = &mut data.get_mut()[encryption_start as usize..];
+    let game_data = data.get_mut().get_mut(encryption_start as usize..).ok_or_else(malformed)?;
     let array_hack = |slice| <&mut [u8] as TryInto<&mut [u8; 4]>>::try_into(slice).unwrap();
     for chunk in game_data.chunks_exact_mut(4).map(array_hack) {
         let dword = u32::from_le_bytes(*chunk);
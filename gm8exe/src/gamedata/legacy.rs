@@ -0,0 +1,58 @@
+use crate::reader::ReaderEvent;
+use std::io;
+
+/// The magic number which starts the gamedata header, followed by the version it was made with.
+const MAGIC: u32 = 1234321;
+
+/// Looks for the gamedata header of a game made before GameMaker 8.0, returning its version (600 for 6.x,
+/// 700 for 7.0 and so on). These can't be read yet, but are worth telling apart from files that aren't games.
+pub fn check<F>(exe: &mut io::Cursor<&mut [u8]>, events: Option<F>) -> Option<u32>
+where
+    F: Copy + Fn(ReaderEvent),
+{
+    log!(events, "Searching for a pre-8.0 gamedata header");
+    let magic = MAGIC.to_le_bytes();
+    exe.get_ref().windows(8).find_map(|header| {
+        let version = u32::from_le_bytes([header[4], header[5], header[6], header[7]]);
+        if header[..4] == magic && (500..800).contains(&version) {
+            log!(events, "Found gamedata header for version {}", version);
+            Some(version)
+        } else {
+            None
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Some bytes of runner, then a gamedata header for `version`.
+    fn exe(version: u32) -> Vec<u8> {
+        let mut exe = b"MZ\x90\0\x03\0\0\0".repeat(8);
+        exe.extend_from_slice(&MAGIC.to_le_bytes());
+        exe.extend_from_slice(&version.to_le_bytes());
+        exe.extend_from_slice(&[0; 16]);
+        exe
+    }
+
+    fn check_exe(mut exe: Vec<u8>) -> Option<u32> {
+        check(&mut io::Cursor::new(&mut exe[..]), None::<fn(ReaderEvent)>)
+    }
+
+    #[test]
+    fn finds_older_versions() {
+        assert_eq!(check_exe(exe(700)), Some(700));
+        assert_eq!(check_exe(exe(610)), Some(610));
+    }
+
+    #[test]
+    fn ignores_everything_else() {
+        assert_eq!(check_exe(exe(800)), None);
+        assert_eq!(check_exe(exe(810)), None);
+        let mut no_magic = exe(700);
+        no_magic[64] ^= 1;
+        assert_eq!(check_exe(no_magic), None);
+        assert_eq!(check_exe(MAGIC.to_le_bytes().to_vec()), None);
+    }
+}
@@ -0,0 +1,364 @@
+use crate::{
+    asset::*,
+    reader::{inflate, AssetKind, AssetLocation, InvalidAsset, ReaderError, ReaderEvent},
+    rsrc::VersionInfo,
+    settings::{GameHelpDialog, Settings},
+    AssetList, GameVersion,
+};
+use byteorder::{ReadBytesExt, LE};
+use rayon::prelude::{IndexedParallelIterator, IntoParallelRefIterator, ParallelIterator};
+use std::marker::PhantomData;
+
+/// A game which has been indexed but not decoded, from [`from_exe_lazy`](crate::reader::from_exe_lazy).
+///
+/// Everything small is read up front. Assets are left compressed in the exe until they're asked for, so looking
+/// through a game's names and code doesn't mean inflating every sprite and sound in it.
+pub struct LazyAssets<'a> {
+    pub triggers: LazyList<'a, Trigger>,
+    pub constants: Vec<Constant>,
+    pub extensions: Vec<Extension>,
+    pub sprites: LazyList<'a, Sprite>,
+    pub sounds: LazyList<'a, Sound>,
+    pub backgrounds: LazyList<'a, Background>,
+    pub paths: LazyList<'a, Path>,
+    pub scripts: LazyList<'a, Script>,
+    pub fonts: LazyList<'a, Font>,
+    pub timelines: LazyList<'a, Timeline>,
+    pub objects: LazyList<'a, Object>,
+    pub rooms: LazyList<'a, Room>,
+    pub included_files: LazyList<'a, IncludedFile>,
+    pub version: GameVersion,
+
+    pub dx_dll: &'a [u8],
+    pub ico_file_raw: Option<Vec<u8>>,
+    pub version_info: Option<VersionInfo>,
+    pub manifest: Option<String>,
+    pub help_dialog: GameHelpDialog,
+    pub last_instance_id: i32,
+    pub last_tile_id: i32,
+    pub library_init_strings: Vec<PascalString>,
+    pub room_order: Vec<i32>,
+
+    pub settings: Settings,
+    pub game_id: u32,
+    pub guid: [u32; 4],
+}
+
+/// One kind of asset, each still compressed. They're decoded every time they're asked for, so keep hold of any
+/// which are needed more than once.
+pub struct LazyList<'a, T> {
+    kind: AssetKind,
+    version: GameVersion,
+    strict: bool,
+    max_len: u64,
+
+    /// Whether each asset starts with a dword saying if it exists, which is every kind but included files.
+    deletable: bool,
+
+    /// Each asset's compressed data along with where it starts.
+    refs: Vec<(usize, &'a [u8])>,
+
+    asset: PhantomData<fn() -> T>,
+}
+
+impl<'a, T: Asset> LazyList<'a, T> {
+    pub(crate) fn new(
+        kind: AssetKind,
+        version: GameVersion,
+        strict: bool,
+        max_len: u64,
+        refs: Vec<(usize, &'a [u8])>,
+    ) -> Self {
+        let deletable = kind != AssetKind::IncludedFile;
+        LazyList { kind, version, strict, max_len, deletable, refs, asset: PhantomData }
+    }
+
+    /// How many assets there are, including deleted ones.
+    pub fn len(&self) -> usize {
+        self.refs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.refs.is_empty()
+    }
+
+    /// Decodes the asset at `index`, or returns `None` if it was deleted or `index` is out of range.
+    pub fn get(&self, index: usize) -> Result<Option<T>, InvalidAsset> {
+        let (offset, data) = match self.refs.get(index) {
+            Some(&asset_ref) => asset_ref,
+            None => return Ok(None),
+        };
+        let mut reader = inflate(data, self.max_len);
+        let result = if self.deletable {
+            // Skip block if it's just a deflated `00 00 00 00` (normal compression level, as GM8 does).
+            // This will short circuit on length, but it checks against this literal to make sure.
+            if data == [0x78, 0x9C, 0x63, 0x60, 0x60, 0x60, 0x00, 0x00, 0x00, 0x04, 0x00, 0x01] {
+                return Ok(None)
+            }
+
+            // If the first u32 is 0 then it's a deleted asset, and is None.
+            match reader.read_u32::<LE>() {
+                Ok(0) => return Ok(None),
+                Ok(_) => T::deserialize_exe(&mut reader, self.version, self.strict),
+                Err(_) => Err(Error::MalformedData),
+            }
+        } else {
+            T::deserialize_exe(&mut reader, self.version, self.strict)
+        };
+        result.map(Some).map_err(|error| {
+            let name = self.name(index);
+            let location = AssetLocation { kind: self.kind, index, name, offset, position: reader.position() };
+            InvalidAsset { location, error }
+        })
+    }
+
+    /// Reads just the name of the asset at `index`, which only inflates the start of it. Returns `None` if it was
+    /// deleted, or if its name couldn't be read.
+    pub fn name(&self, index: usize) -> Option<PascalString> {
+        let &(_, data) = self.refs.get(index)?;
+        let mut reader = inflate(data, self.max_len);
+        if self.deletable && reader.read_u32::<LE>().ok()? == 0 {
+            return None
+        }
+        T::read_name(reader)
+    }
+
+    /// Reads every asset's name, in order, as with [`name`](Self::name).
+    pub fn names(&self) -> impl Iterator<Item = Option<PascalString>> + '_ {
+        (0..self.len()).map(move |index| self.name(index))
+    }
+
+    /// Decodes every asset. With `diagnostics` (in tolerant mode), those that can't be read are left as `None`
+    /// and noted there instead of failing.
+    pub(crate) fn get_all<E>(
+        &self,
+        multithread: bool,
+        diagnostics: Option<&mut Vec<InvalidAsset>>,
+        events: Option<E>,
+    ) -> Result<AssetList<T>, ReaderError>
+    where
+        T: Send,
+        E: Copy + Sync + Fn(ReaderEvent),
+    {
+        let kind = self.kind;
+        let to_asset = |(index, _): (usize, &(usize, &[u8]))| {
+            let result = self.get(index).map(|asset| asset.map(Box::new));
+            event!(events, ReaderEvent::AssetDone { kind, index });
+            result
+        };
+
+        event!(events, ReaderEvent::ChunkStart { kind, count: self.refs.len() });
+        let results = if multithread {
+            self.refs.par_iter().enumerate().map(to_asset).collect::<Vec<_>>()
+        } else {
+            self.refs.iter().enumerate().map(to_asset).collect::<Vec<_>>()
+        };
+        match diagnostics {
+            Some(diagnostics) => Ok(results
+                .into_iter()
+                .map(|result| {
+                    result.unwrap_or_else(|invalid| {
+                        diagnostics.push(invalid);
+                        None
+                    })
+                })
+                .collect()),
+            None => Ok(results.into_iter().collect::<Result<Vec<_>, _>>()?),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        asset::included_file::ExportSetting,
+        gamedata,
+        reader::{from_exe, from_exe_lazy, Limits},
+    };
+    use byteorder::WriteBytesExt;
+    use flate2::{write::ZlibEncoder, Compression};
+    use std::io::Write;
+
+    fn zlib(data: &[u8]) -> Vec<u8> {
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    fn write_u32s(out: &mut Vec<u8>, values: &[u32]) {
+        values.iter().for_each(|&n| out.write_u32::<LE>(n).unwrap());
+    }
+
+    fn write_pas(out: &mut Vec<u8>, s: &str) {
+        write_u32s(out, &[s.len() as u32]);
+        out.extend_from_slice(s.as_bytes());
+    }
+
+    /// Writes a list of assets, each compressed on its own.
+    fn write_assets(out: &mut Vec<u8>, version: u32, assets: &[Vec<u8>]) {
+        write_u32s(out, &[version, assets.len() as u32]);
+        for asset in assets {
+            let asset = zlib(asset);
+            write_u32s(out, &[asset.len() as u32]);
+            out.extend(asset);
+        }
+    }
+
+    fn serialize<T: Asset>(asset: &T) -> Vec<u8> {
+        let mut data = Vec::new();
+        asset.serialize_exe(&mut data, GameVersion::GameMaker8_0).unwrap();
+        data
+    }
+
+    /// Serializes an asset the way it's stored in a list, with the dword saying it exists.
+    fn existing<T: Asset>(asset: &T) -> Vec<u8> {
+        let mut data = 1u32.to_le_bytes().to_vec();
+        data.extend(serialize(asset));
+        data
+    }
+
+    fn script(name: &str, source: &str) -> Script {
+        Script { name: name.into(), source: source.into() }
+    }
+
+    fn included_file() -> IncludedFile {
+        IncludedFile {
+            file_name: "music.ogg".into(),
+            source_path: "C:\\music.ogg".into(),
+            data_exists: true,
+            source_length: 3,
+            stored_in_gmk: true,
+            embedded_data: Some(vec![1, 2, 3].into_boxed_slice()),
+            export_settings: ExportSetting::TempFolder,
+            overwrite_file: false,
+            free_memory: true,
+            remove_at_end: true,
+        }
+    }
+
+    /// Encrypts gamedata the way `gm80::decrypt` undoes it, with a swap table which leaves every byte be.
+    fn gm80_encrypt(out: &mut Vec<u8>, mut data: Vec<u8>) {
+        write_u32s(out, &[0, 0]);
+        out.extend(0..=255u8);
+        write_u32s(out, &[data.len() as u32]);
+        for i in 0..data.len() {
+            data.swap(i, i & !0xFF);
+        }
+        for i in 1..data.len() {
+            data[i] = data[i].wrapping_add(data[i - 1]).wrapping_add(i as u8);
+        }
+        out.extend(data);
+    }
+
+    /// A GameMaker 8.0 game holding `scripts` (as stored, see [`existing`]) and an included file, in an exe with
+    /// nothing else to it, so only the `gm80-scan` detector finds it.
+    fn game(scripts: &[Vec<u8>]) -> Vec<u8> {
+        let mut exe = b"MZ".to_vec();
+        exe.resize(0x3C, 0);
+        write_u32s(&mut exe, &[0x40]);
+        exe.extend_from_slice(b"PE\0\0\x4C\x01");
+        exe.extend_from_slice(&[0; 16]); // no sections, no optional header
+
+        write_u32s(&mut exe, &[1234321, 800, 0, 0]);
+        let settings = zlib(&[0; 34 * 4]);
+        write_u32s(&mut exe, &[settings.len() as u32]);
+        exe.extend(settings);
+        write_pas(&mut exe, "D3DX8.dll");
+        write_u32s(&mut exe, &[4]);
+        exe.extend_from_slice(b"dll!");
+
+        let mut data = Vec::new();
+        write_u32s(&mut data, &[0, 0, 1234, 1, 2, 3, 4]); // garbage, pro flag, game ID, GUID
+        write_u32s(&mut data, &[700, 0, 800, 0, 800, 0]); // extensions, triggers, constants
+        for _ in ["sounds", "sprites", "backgrounds", "paths"] {
+            write_assets(&mut data, 800, &[]);
+        }
+        write_assets(&mut data, 800, scripts);
+        for _ in ["fonts", "timelines", "objects", "rooms"] {
+            write_assets(&mut data, 800, &[]);
+        }
+        write_u32s(&mut data, &[100000, 10000000]);
+        write_assets(&mut data, 800, &[serialize(&included_file())]);
+        let mut help = Vec::new();
+        write_u32s(&mut help, &[0xFFFFE1, 0]);
+        write_pas(&mut help, "Game Information");
+        write_u32s(&mut help, &[0, 0, 600, 400, 1, 1, 0, 1]);
+        write_pas(&mut help, "");
+        let help = zlib(&help);
+        write_u32s(&mut data, &[800, help.len() as u32]);
+        data.extend(help);
+        write_u32s(&mut data, &[500, 0, 700, 0]); // library init strings, room order
+        gm80_encrypt(&mut exe, data);
+        exe
+    }
+
+    fn detectors() -> [gamedata::Detector; 1] {
+        [*gamedata::detector("gm80-scan").unwrap()]
+    }
+
+    fn read(exe: Vec<u8>, tolerant: bool) -> Result<crate::GameAssets, ReaderError> {
+        from_exe(exe, None::<fn(ReaderEvent)>, true, false, tolerant, Limits::NONE, &detectors())
+    }
+
+    #[test]
+    fn get_matches_from_exe() {
+        let deleted = vec![0; 4];
+        let scripts = [existing(&script("scr_a", "return 1;")), deleted, existing(&script("scr_b", "exit;"))];
+        let assets = read(game(&scripts), false).unwrap();
+        let mut exe = game(&scripts);
+        let lazy = from_exe_lazy(&mut exe, None::<fn(ReaderEvent)>, true, Limits::NONE, &detectors()).unwrap();
+
+        assert_eq!((lazy.scripts.len(), assets.scripts.len()), (3, 3));
+        for (index, script) in assets.scripts.iter().enumerate() {
+            let lazy_script = lazy.scripts.get(index).unwrap();
+            assert_eq!(lazy_script.as_ref().map(serialize), script.as_deref().map(serialize), "script {}", index);
+        }
+        assert!(lazy.scripts.get(3).unwrap().is_none());
+        let names = lazy.scripts.names().map(|name| name.map(|name| name.to_string())).collect::<Vec<_>>();
+        assert_eq!(names, [Some("scr_a".to_string()), None, Some("scr_b".to_string())]);
+        let file = lazy.included_files.get(0).unwrap().unwrap();
+        assert_eq!(serialize(&file), serialize(&assets.included_files[0]));
+        assert_eq!(serialize(&file), serialize(&included_file()));
+        assert_eq!((lazy.game_id, assets.game_id, lazy.dx_dll), (1234, 1234, &b"dll!"[..]));
+    }
+
+    #[test]
+    fn tolerant_mode_leaves_out_broken_assets() {
+        // a script which ends after its version
+        let mut broken = existing(&script("scr_broken", ""));
+        broken.truncate(broken.len() - 4);
+        let scripts = [existing(&script("scr_a", "return 1;")), broken];
+
+        let invalid = match read(game(&scripts), false) {
+            Err(ReaderError::InvalidAsset(invalid)) => invalid,
+            Err(err) => panic!("expected an invalid asset, got {}", err),
+            Ok(_) => panic!("expected an invalid asset"),
+        };
+        let location = &invalid.location;
+        assert_eq!((location.kind, location.index), (AssetKind::Script, 1));
+        assert_eq!(location.name.as_ref().map(|name| name.to_string()).as_deref(), Some("scr_broken"));
+
+        let assets = read(game(&scripts), true).unwrap();
+        assert!(assets.scripts[0].is_some() && assets.scripts[1].is_none());
+        assert_eq!(assets.diagnostics.len(), 1);
+        assert_eq!(assets.diagnostics[0].location.to_string(), location.to_string());
+    }
+
+    #[test]
+    fn reports_progress() {
+        let scripts = [existing(&script("scr_a", "return 1;")), vec![0; 4]];
+        let seen = std::sync::Mutex::new(Vec::new());
+        let events = |event: ReaderEvent| match event {
+            ReaderEvent::ChunkStart { kind, count } => seen.lock().unwrap().push((kind, count, true)),
+            ReaderEvent::AssetDone { kind, index } => seen.lock().unwrap().push((kind, index, false)),
+            _ => (),
+        };
+        from_exe(game(&scripts), Some(&events), true, false, false, Limits::NONE, &detectors()).unwrap();
+        let seen = seen.into_inner().unwrap();
+        let scripts = seen.iter().filter(|(kind, _, _)| *kind == AssetKind::Script).copied().collect::<Vec<_>>();
+        let script = AssetKind::Script;
+        assert_eq!(scripts, [(script, 2, true), (script, 0, false), (script, 1, false)]);
+        assert!(seen.contains(&(AssetKind::IncludedFile, 0, false)));
+    }
+}
@@ -1,19 +1,24 @@
 #![allow(clippy::cognitive_complexity)]
 #![allow(clippy::unreadable_literal)]
 
-macro_rules! log {
-    ($logger: expr, $x: expr) => {
-        if let Some(logger) = &$logger {
-            logger($x.into());
+macro_rules! event {
+    ($events: expr, $event: expr) => {
+        if let Some(events) = &$events {
+            events($event);
         }
     };
-    ($logger: expr, $format: expr, $($x: expr),*) => {
-        if let Some(logger) = &$logger {
-            logger(&format!(
-                $format,
-                $($x),*
-            ));
-        }
+}
+
+// Messages are passed on as format arguments, so nothing is formatted unless whoever gets them prints them.
+macro_rules! log {
+    ($events: expr, $x: expr) => {
+        event!($events, $crate::reader::ReaderEvent::Message(format_args!("{}", $x)))
+    };
+    ($events: expr, $format: expr, $($x: expr),*) => {
+        event!($events, $crate::reader::ReaderEvent::Message(format_args!(
+            $format,
+            $($x),*
+        )))
     };
     ($($x:expr,)*) => (log![$($x),*]); // leveraged from vec![]
 }
@@ -21,6 +26,7 @@ macro_rules! log {
 pub mod asset;
 pub mod def;
 pub mod gamedata;
+pub mod lazy;
 pub mod reader;
 pub mod rsrc;
 pub mod settings;
@@ -28,11 +34,19 @@ pub mod upx;
 
 mod colour;
 
+#[cfg(feature = "serde")]
+mod base64;
+
 use crate::asset::*;
+use reader::InvalidAsset;
+use rsrc::VersionInfo;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 use settings::{GameHelpDialog, Settings};
 
 pub type AssetList<T> = Vec<Option<Box<T>>>;
 
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct GameAssets {
     pub triggers: AssetList<Trigger>,
     pub constants: Vec<Constant>,
@@ -49,8 +63,14 @@ pub struct GameAssets {
     pub included_files: Vec<IncludedFile>,
     pub version: GameVersion,
 
+    #[cfg_attr(feature = "serde", serde(with = "crate::base64"))]
     pub dx_dll: Vec<u8>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::base64::option"))]
     pub ico_file_raw: Option<Vec<u8>>,
+    /// The exe's version resource, which usually has the game's title in it.
+    pub version_info: Option<VersionInfo>,
+    /// The exe's application manifest, as XML.
+    pub manifest: Option<String>,
     pub help_dialog: GameHelpDialog,
     pub last_instance_id: i32,
     pub last_tile_id: i32,
@@ -60,9 +80,15 @@ pub struct GameAssets {
     pub settings: Settings,
     pub game_id: u32,
     pub guid: [u32; 4],
+
+    /// The assets which couldn't be read and were left out, when reading in tolerant mode.
+    /// These aren't serialized, since they're about reading the exe rather than part of the game.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub diagnostics: Vec<InvalidAsset>,
 }
 
 #[derive(Copy, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum GameVersion {
     GameMaker8_0,
     GameMaker8_1,
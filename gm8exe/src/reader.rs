@@ -1,13 +1,13 @@
 use crate::{
-    asset::*,
-    gamedata::{self, gm80},
+    asset::{self, *},
+    gamedata::{self, gm80, Detector, Protection},
+    lazy::{LazyAssets, LazyList},
     rsrc,
     settings::{GameHelpDialog, Settings},
     AssetList, GameAssets, GameVersion,
 };
 use byteorder::{ReadBytesExt, LE};
 use flate2::bufread::ZlibDecoder;
-use rayon::prelude::{IntoParallelRefIterator, ParallelIterator};
 use std::{
     fmt::{self, Display},
     io::{self, Read, Seek, SeekFrom},
@@ -16,26 +16,136 @@ use std::{
 #[derive(Debug)]
 pub enum ReaderError {
     AssetError(Error),
+    InvalidAsset(InvalidAsset),
     InvalidExeHeader,
     IO(io::Error),
     PartialUPXPacking,
     UnknownFormat,
+    UnsupportedVersion(u32),
 }
 impl std::error::Error for ReaderError {}
 impl Display for ReaderError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "{}", match self {
             ReaderError::AssetError(err) => format!("asset data error: {}", err),
+            ReaderError::InvalidAsset(invalid) => invalid.to_string(),
             ReaderError::InvalidExeHeader => "invalid exe header".into(),
             ReaderError::IO(err) => format!("io error: {}", err),
             ReaderError::PartialUPXPacking => {
                 "looks upx protected, can't locate headers".into()
             },
             ReaderError::UnknownFormat => "unknown format, could not identify file".into(),
+            ReaderError::UnsupportedVersion(version) => format!(
+                "made with GameMaker {}.{} (gamedata version {}), only games made with 8.0 or 8.1 can be read",
+                version / 100,
+                version / 10 % 10,
+                version
+            ),
         })
     }
 }
 
+/// The kinds of assets in a game, in the order they're stored.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AssetKind {
+    Extension,
+    Trigger,
+    Sound,
+    Sprite,
+    Background,
+    Path,
+    Script,
+    Font,
+    Timeline,
+    Object,
+    Room,
+    IncludedFile,
+}
+impl Display for AssetKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", match self {
+            AssetKind::Extension => "extension",
+            AssetKind::Trigger => "trigger",
+            AssetKind::Sound => "sound",
+            AssetKind::Sprite => "sprite",
+            AssetKind::Background => "background",
+            AssetKind::Path => "path",
+            AssetKind::Script => "script",
+            AssetKind::Font => "font",
+            AssetKind::Timeline => "timeline",
+            AssetKind::Object => "object",
+            AssetKind::Room => "room",
+            AssetKind::IncludedFile => "included file",
+        })
+    }
+}
+
+/// Where an asset which couldn't be read is in the game.
+#[derive(Debug)]
+pub struct AssetLocation {
+    pub kind: AssetKind,
+
+    /// The asset's index among those of its kind, which is its ID in GML.
+    pub index: usize,
+
+    /// The asset's name, if that much of it could be read.
+    pub name: Option<PascalString>,
+
+    /// Where the asset starts in the gamedata, once decrypted (and unpacked, if UPX was used).
+    pub offset: usize,
+
+    /// How far into the asset's data reading got before failing, after decompression if it's compressed.
+    pub position: u64,
+}
+impl Display for AssetLocation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {}", self.kind, self.index)?;
+        if let Some(name) = &self.name {
+            write!(f, " ('{}')", name)?;
+        }
+        write!(f, " at offset {:#X}, byte {}", self.offset, self.position)
+    }
+}
+
+/// An asset which couldn't be read. In tolerant mode, it's left out of [`GameAssets`] and listed in its
+/// `diagnostics` instead.
+#[derive(Debug)]
+pub struct InvalidAsset {
+    pub location: AssetLocation,
+    pub error: Error,
+}
+impl Display for InvalidAsset {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: asset data error: {}", self.location, self.error)
+    }
+}
+
+/// Something that happened while reading a game, for following along with [`from_exe`].
+#[derive(Debug)]
+pub enum ReaderEvent<'a> {
+    /// A line of the human-readable log, which goes into more detail than the other events.
+    /// It's only formatted once it's displayed, so ignoring these costs next to nothing.
+    Message(fmt::Arguments<'a>),
+
+    /// Something which had to be removed before the gamedata could be read.
+    ProtectionDetected(Protection),
+
+    /// The detector which found the gamedata.
+    Detected(&'a Detector),
+
+    /// The gamedata has been found, and was made with this version of GameMaker.
+    VersionDetected(GameVersion),
+
+    SettingsLoaded(&'a Settings),
+
+    /// A list of assets is about to be read. The count includes deleted assets.
+    ChunkStart { kind: AssetKind, count: usize },
+
+    /// An asset has been read, or found to be deleted or (in tolerant mode) broken. When reading with multiple
+    /// threads, these come from all of them and not in order.
+    AssetDone { kind: AssetKind, index: usize },
+}
+
 macro_rules! from_err {
     ($t: ident, $e: ty, $variant: ident) => {
         impl From<$e> for $t {
@@ -48,13 +158,73 @@ macro_rules! from_err {
 
 from_err!(ReaderError, Error, AssetError);
 from_err!(ReaderError, io::Error, IO);
+from_err!(ReaderError, InvalidAsset, InvalidAsset);
+
+/// Caps on how much reading a game may allocate, for games from untrusted sources.
+///
+/// Lengths read from a game are checked against the data actually in it either way, and malformed games are
+/// reported as errors rather than panicking. These cap data which expands: what's compressed or UPX packed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Limits {
+    /// The most bytes the settings, the help dialog, an asset or an extension file may inflate to.
+    pub max_inflated_len: u64,
+
+    /// The most bytes a UPX packed runner may unpack to.
+    pub max_unpacked_len: usize,
+}
+
+impl Limits {
+    /// No caps beyond what the game says about itself.
+    pub const NONE: Self = Limits { max_inflated_len: u64::MAX, max_unpacked_len: usize::MAX };
 
-/// Helper function for inflating zlib data.
-pub(crate) fn inflate<I>(data: &I) -> ZlibDecoder<&[u8]>
+    /// Caps well above what any real game needs, but low enough to read games from strangers.
+    pub const UNTRUSTED: Self = Limits { max_inflated_len: 256 << 20, max_unpacked_len: 64 << 20 };
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Limits::NONE
+    }
+}
+
+/// Zlib data being inflated, which fails once it's inflated past a limit.
+pub(crate) struct Inflater<'a> {
+    decoder: ZlibDecoder<&'a [u8]>,
+    limit: u64,
+}
+
+impl Inflater<'_> {
+    /// How many bytes have been inflated so far.
+    pub fn position(&self) -> u64 {
+        self.decoder.total_out()
+    }
+}
+
+impl Read for Inflater<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        // asking for one byte past the limit tells data that's too long from data that ends right at it
+        let left = self.limit.saturating_sub(self.decoder.total_out()).saturating_add(1);
+        let len = buf.len().min(usize::try_from(left).unwrap_or(usize::MAX));
+        let read = self.decoder.read(&mut buf[..len])?;
+        if self.decoder.total_out() > self.limit {
+            let message = format!("data inflates to more than the limit of {} bytes", self.limit);
+            return Err(io::Error::new(io::ErrorKind::InvalidData, message))
+        }
+        Ok(read)
+    }
+}
+
+/// Helper function for inflating zlib data, up to `limit` bytes of it.
+pub(crate) fn inflate<I>(data: &I, limit: u64) -> Inflater<'_>
 where
     I: AsRef<[u8]> + ?Sized,
 {
-    ZlibDecoder::new(data.as_ref())
+    Inflater { decoder: ZlibDecoder::new(data.as_ref()), limit }
+}
+
+/// How many bytes are left after the cursor, for capping capacities which come from the data.
+fn remaining<T: AsRef<[u8]>>(cursor: &io::Cursor<T>) -> usize {
+    cursor.get_ref().as_ref().len().saturating_sub(cursor.position() as usize)
 }
 
 /// A windows PE Section header
@@ -66,13 +236,22 @@ pub struct PESection {
     pub disk_address: u32,
 }
 
-pub fn from_exe<I, F>(mut exe: I, logger: Option<F>, strict: bool, multithread: bool) -> Result<GameAssets, ReaderError>
+/// Indexes a game without decoding its assets, which are decoded one at a time from the [`LazyAssets`] instead.
+/// This is much faster and lighter than [`from_exe`] for looking at part of a big game. The gamedata is decrypted in
+/// place, so `exe` is left changed and borrowed by the result.
+///
+/// `events`, `strict`, `limits` and `detectors` are as for [`from_exe`]. Only the extensions are read in full, and
+/// one which can't be read still fails.
+pub fn from_exe_lazy<'a, F>(
+    exe: &'a mut [u8],
+    events: Option<F>,
+    strict: bool,
+    limits: Limits,
+    detectors: &[Detector],
+) -> Result<LazyAssets<'a>, ReaderError>
 where
-    F: Copy + Fn(&str),
-    I: AsRef<[u8]> + AsMut<[u8]>,
+    F: Copy + Fn(ReaderEvent),
 {
-    let exe = exe.as_mut();
-
     // comfy wrapper for byteorder I/O
     let mut exe = io::Cursor::new(exe);
 
@@ -85,7 +264,7 @@ where
     exe.set_position(0x3C);
     let pe_header_loc = exe.read_u32::<LE>()? as usize;
     // PE header must begin with PE\0\0, then 0x14C which means i386.
-    match exe.get_ref().get(pe_header_loc..(pe_header_loc + 6)) {
+    match exe.get_ref().get(pe_header_loc..pe_header_loc.saturating_add(6)) {
         Some(b"PE\0\0\x4C\x01") => (),
         _ => return Err(ReaderError::InvalidExeHeader),
     }
@@ -120,16 +299,16 @@ where
             [0x55, 0x50, 0x58, 0x30, 0x00, 0x00, 0x00, 0x00] => {
                 // UPX0 section
                 upx0_virtual_len = Some(virtual_size);
-                log!(logger, "UPX0 section found, virtual len: {}", virtual_size);
+                log!(events, "UPX0 section found, virtual len: {}", virtual_size);
             },
             [0x55, 0x50, 0x58, 0x31, 0x00, 0x00, 0x00, 0x00] => {
                 // UPX1 section
                 upx1_data = Some((virtual_size, disk_address));
-                log!(logger, "UPX1 section found, virtual len: {}", virtual_size);
+                log!(events, "UPX1 section found, virtual len: {}", virtual_size);
             },
             [0x2E, 0x72, 0x73, 0x72, 0x63, 0x00, 0x00, 0x00] => {
                 // .rsrc section
-                log!(logger, "Found .rsrc section beginning at {}", disk_address);
+                log!(events, "Found .rsrc section beginning at {}", disk_address);
                 rsrc_location = Some(disk_address);
             },
             _ => {},
@@ -148,15 +327,39 @@ where
         .transpose()?
         .flatten();
 
+    // these are only nice to have, so a game with a broken version resource or manifest can still be read
+    let (version_info, manifest) = match rsrc_location {
+        Some(x) => {
+            let temp_pos = exe.position();
+            exe.set_position(u64::from(x));
+            let version_info = rsrc::find_version_info(&mut exe, &sections).unwrap_or_else(|err| {
+                log!(events, "Couldn't read version info: {}", err);
+                None
+            });
+            exe.set_position(u64::from(x));
+            let manifest = rsrc::find_manifest(&mut exe, &sections).unwrap_or_else(|err| {
+                log!(events, "Couldn't read manifest: {}", err);
+                None
+            });
+            exe.set_position(temp_pos);
+            (version_info, manifest)
+        },
+        None => (None, None),
+    };
+    if let Some(info) = &version_info {
+        log!(events, "Found version info: {:?}", info);
+    }
+
     // Decide if UPX is in use based on PE section names
     // This is None if there is no UPX, obviously, otherwise it's (max_size, offset_on_disk)
     let upx_data: Option<(u32, u32)> = match upx0_virtual_len {
-        Some(len0) => upx1_data.map(|(len1, offset)| (len0 + len1, offset)),
+        Some(len0) => upx1_data.map(|(len1, offset)| (len0.saturating_add(len1), offset)),
         None => None,
     };
 
     // Identify the game version in use and locate the gamedata header
-    let game_ver = gamedata::find(&mut exe, logger, upx_data)?;
+    let game_ver = gamedata::find(&mut exe, events, upx_data, limits.max_unpacked_len, detectors)?;
+    event!(events, ReaderEvent::VersionDetected(game_ver));
 
     // little helper thing
     macro_rules! assert_ver {
@@ -179,20 +382,17 @@ where
     let settings_len = exe.read_u32::<LE>()? as usize;
     let pos = exe.position() as usize;
     exe.seek(SeekFrom::Current(settings_len as i64))?;
-    let mut cfg = inflate(&exe.get_ref()[pos..pos + settings_len]);
+    let settings_data = exe.get_ref().get(pos..pos.saturating_add(settings_len));
+    let settings_data = settings_data.ok_or(io::Error::from(io::ErrorKind::UnexpectedEof))?;
+    let mut cfg = inflate(settings_data, limits.max_inflated_len);
 
-    log!(logger, "Reading settings chunk...");
+    log!(events, "Reading settings chunk...");
 
     let settings = {
         fn read_data_maybe(cfg: &mut impl Read) -> Result<Option<Box<[u8]>>, ReaderError> {
             if cfg.read_u32::<LE>()? != 0 {
                 let len = cfg.read_u32::<LE>()? as usize;
-                let mut output = Vec::with_capacity(len);
-                unsafe {
-                    output.set_len(len);
-                }
-                cfg.read_exact(&mut output)?;
-                Ok(Some(output.into_boxed_slice()))
+                Ok(Some(cfg.read_chunk(len)?.into_boxed_slice()))
             } else {
                 Ok(None)
             }
@@ -243,32 +443,32 @@ where
             Err(_) => false,
         };
 
-        log!(logger, " + Loaded settings structure");
-        log!(logger, "   - Start in full-screen mode: {}", fullscreen);
+        log!(events, " + Loaded settings structure");
+        log!(events, "   - Start in full-screen mode: {}", fullscreen);
 
-        log!(logger, "   - Interpolate colors between pixels: {}", interpolate_pixels);
+        log!(events, "   - Interpolate colors between pixels: {}", interpolate_pixels);
 
-        log!(logger, "   - Don't draw a border in windowed mode: {}", dont_draw_border);
+        log!(events, "   - Don't draw a border in windowed mode: {}", dont_draw_border);
 
-        log!(logger, "   - Display the cursor: {}", display_cursor);
+        log!(events, "   - Display the cursor: {}", display_cursor);
 
-        log!(logger, "   - Scaling: {}", scaling);
+        log!(events, "   - Scaling: {}", scaling);
 
-        log!(logger, "   - Allow the player to resize the game window: {}", allow_resize);
+        log!(events, "   - Allow the player to resize the game window: {}", allow_resize);
 
-        log!(logger, "   - Let the game window always stay on top: {}", window_on_top);
+        log!(events, "   - Let the game window always stay on top: {}", window_on_top);
 
-        log!(logger, "   - Colour outside the room region (RGBA): #{:0>8X}", clear_colour);
+        log!(events, "   - Colour outside the room region (RGBA): #{:0>8X}", clear_colour);
 
-        log!(logger, "   - Set the resolution of the screen: {}", set_resolution);
+        log!(events, "   - Set the resolution of the screen: {}", set_resolution);
 
-        log!(logger, "   -   -> Color Depth: {}", match colour_depth {
+        log!(events, "   -   -> Color Depth: {}", match colour_depth {
             0 => "No Change",
             1 => "16-Bit",
             _ => "32-Bit",
         });
 
-        log!(logger, "   -   -> Resolution: {}", match resolution {
+        log!(events, "   -   -> Resolution: {}", match resolution {
             0 => "No Change",
             1 => "320x240",
             2 => "640x480",
@@ -278,7 +478,7 @@ where
             _ => "1600x1200",
         });
 
-        log!(logger, "   -   -> Frequency: {}", match frequency {
+        log!(events, "   -   -> Frequency: {}", match frequency {
             0 => "No Change",
             1 => "60Hz",
             2 => "70Hz",
@@ -287,56 +487,56 @@ where
             _ => "120Hz",
         });
 
-        log!(logger, "   - Don't show the buttons in the window captions: {}", dont_show_buttons);
+        log!(events, "   - Don't show the buttons in the window captions: {}", dont_show_buttons);
 
-        log!(logger, "   - Use synchronization to avoid tearing: {}", vsync);
+        log!(events, "   - Use synchronization to avoid tearing: {}", vsync);
 
-        log!(logger, "   - Disable screensavers and power saving actions: {}", disable_screensaver);
+        log!(events, "   - Disable screensavers and power saving actions: {}", disable_screensaver);
 
-        log!(logger, "   - Let <Esc> end the game: {}", esc_close_game);
+        log!(events, "   - Let <Esc> end the game: {}", esc_close_game);
 
-        log!(logger, "   - Treat the close button as the <Esc> key: {}", treat_close_as_esc);
+        log!(events, "   - Treat the close button as the <Esc> key: {}", treat_close_as_esc);
 
-        log!(logger, "   - Let <F1> show the game information: {}", f1_help_menu);
+        log!(events, "   - Let <F1> show the game information: {}", f1_help_menu);
 
-        log!(logger, "   - Let <F4> switch between screen modes: {}", f4_fullscreen_toggle);
+        log!(events, "   - Let <F4> switch between screen modes: {}", f4_fullscreen_toggle);
 
-        log!(logger, "   - Let <F5> save the game and <F6> load a game: {}", f5_save_f6_load);
+        log!(events, "   - Let <F5> save the game and <F6> load a game: {}", f5_save_f6_load);
 
-        log!(logger, "   - Let <F9> take a screenshot of the game: {}", f9_screenshot);
+        log!(events, "   - Let <F9> take a screenshot of the game: {}", f9_screenshot);
 
-        log!(logger, "   - Game Process Priority: {}", match priority {
+        log!(events, "   - Game Process Priority: {}", match priority {
             0 => "Normal",
             1 => "High",
             _ => "Highest",
         });
 
-        log!(logger, "   - Freeze the game window when the window loses focus: {}", freeze_on_lose_focus);
+        log!(events, "   - Freeze the game window when the window loses focus: {}", freeze_on_lose_focus);
 
-        log!(logger, "   - Loading bar: {}", match loading_bar {
+        log!(events, "   - Loading bar: {}", match loading_bar {
             0 => "No loading progress bar",
             1 => "Default loading progress bar",
             _ => "Own loading progress bar",
         });
 
-        log!(logger, "   - Show your own image while loading: {}", custom_load_image.is_some());
+        log!(events, "   - Show your own image while loading: {}", custom_load_image.is_some());
 
-        log!(logger, "   -   -> Make image partially translucent: {}", transparent);
+        log!(events, "   -   -> Make image partially translucent: {}", transparent);
 
-        log!(logger, "   -   -> Make translucent with alpha value: {}", translucency);
+        log!(events, "   -   -> Make translucent with alpha value: {}", translucency);
 
-        log!(logger, "   - Scale progress bar image: {}", scale_progress_bar);
+        log!(events, "   - Scale progress bar image: {}", scale_progress_bar);
 
-        log!(logger, "   - Display error messages: {}", show_error_messages);
+        log!(events, "   - Display error messages: {}", show_error_messages);
 
-        log!(logger, "   - Write error messages to file game_errors.log: {}", log_errors);
+        log!(events, "   - Write error messages to file game_errors.log: {}", log_errors);
 
-        log!(logger, "   - Abort on all error messages: {}", always_abort);
+        log!(events, "   - Abort on all error messages: {}", always_abort);
 
-        log!(logger, "   - Treat uninitialized variables as value 0: {}", zero_uninitialized_vars);
+        log!(events, "   - Treat uninitialized variables as value 0: {}", zero_uninitialized_vars);
 
         log!(
-            logger,
+            events,
             "   - Throw an error when arguments aren't initialized correctly: {}",
             error_on_uninitialized_args
         );
@@ -381,105 +581,74 @@ where
             swap_creation_events,
         }
     };
+    event!(events, ReaderEvent::SettingsLoaded(&settings));
 
     // Embedded DirectX DLL
     // we obviously don't need this, so we skip over it
     // if we're verbose logging, read the dll name (usually D3DX8.dll, but...)
-    if logger.is_some() {
+    if events.is_some() {
         let dllname = exe.read_pas_string()?;
-        log!(logger, "Skipping embedded DLL '{}'", dllname);
+        log!(events, "Skipping embedded DLL '{}'", dllname);
     } else {
         // otherwise, skip dll name string
         let dllname_len = exe.read_u32::<LE>()? as i64;
         exe.seek(SeekFrom::Current(dllname_len))?;
     }
 
-    // skip or dump embedded dll data chunk
-    let dll_len = exe.read_u32::<LE>()? as i64;
-    let mut dx_dll = vec![0u8; dll_len as usize];
-    exe.read_exact(&mut dx_dll)?;
+    // embedded dll data chunk, which is kept where it is
+    let dll_len = exe.read_u32::<LE>()? as usize;
+    let dll_pos = exe.position() as usize;
+    if dll_pos.checked_add(dll_len).map_or(true, |end| end > exe.get_ref().len()) {
+        return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into())
+    }
+    exe.seek(SeekFrom::Current(dll_len as i64))?;
 
     // yeah
-    gm80::decrypt(&mut exe, logger)?;
+    gm80::decrypt(&mut exe, events)?;
 
     // Garbage field - random bytes
     let garbage_dwords = exe.read_u32::<LE>()?;
-    exe.seek(SeekFrom::Current((garbage_dwords * 4) as i64))?;
-    log!(logger, "Skipped {} garbage DWORDs", garbage_dwords);
+    exe.seek(SeekFrom::Current(i64::from(garbage_dwords) * 4))?;
+    log!(events, "Skipped {} garbage DWORDs", garbage_dwords);
 
     // GM8 Pro flag, game ID
     let pro_flag: bool = exe.read_u32::<LE>()? != 0;
     let game_id = exe.read_u32::<LE>()?;
-    log!(logger, "Pro flag: {}", pro_flag);
-    log!(logger, "Game ID: {}", game_id);
+    log!(events, "Pro flag: {}", pro_flag);
+    log!(events, "Game ID: {}", game_id);
 
     // 16 random bytes...
     let guid = [exe.read_u32::<LE>()?, exe.read_u32::<LE>()?, exe.read_u32::<LE>()?, exe.read_u32::<LE>()?];
 
-    fn get_asset_refs<'a>(src: &mut io::Cursor<&'a [u8]>) -> io::Result<Vec<&'a [u8]>> {
+    /// Returns each asset's compressed data along with where it starts.
+    fn get_asset_refs<'a>(src: &mut io::Cursor<&'a [u8]>) -> io::Result<Vec<(usize, &'a [u8])>> {
         let count = src.read_u32::<LE>()? as usize;
-        let mut refs = Vec::with_capacity(count);
+        let mut refs = Vec::with_capacity(count.min(remaining(src) / 4));
         for _ in 0..count {
             let len = src.read_u32::<LE>()? as usize;
             let pos = src.position() as usize;
             src.seek(SeekFrom::Current(len as i64))?;
             let data = src.get_ref();
-            refs.push(&data[pos..pos + len]);
+            refs.push((pos, data.get(pos..pos.saturating_add(len)).ok_or(io::ErrorKind::UnexpectedEof)?));
         }
         Ok(refs)
     }
 
-    fn get_assets<T, F>(
-        src: &mut io::Cursor<&[u8]>,
-        deserializer: F,
-        multithread: bool,
-    ) -> Result<AssetList<T>, ReaderError>
-    where
-        T: Send,
-        F: Fn(ZlibDecoder<&[u8]>) -> Result<T, Error> + Sync,
-    {
-        let to_asset = |data: &[u8]| {
-            // Skip block if it's just a deflated `00 00 00 00` (normal compression level, as GM8 does).
-            // This will short circuit on length, but it checks against this literal to make sure.
-            if data == [0x78, 0x9C, 0x63, 0x60, 0x60, 0x60, 0x00, 0x00, 0x00, 0x04, 0x00, 0x01] {
-                return Ok(None)
-            }
-            let mut data = inflate(data);
-
-            // If the first u32 is 0 then it's a deleted asset, and is None.
-            match data.read_u32::<LE>() {
-                Ok(0) => Ok(None),
-                Ok(_) => Ok(Some(Box::new(deserializer(data)?))),
-                Err(_) => Err(ReaderError::AssetError(Error::MalformedData)),
-            }
-        };
-
-        if multithread {
-            get_asset_refs(src)?.par_iter().copied().map(to_asset).collect::<Result<Vec<_>, ReaderError>>()
-        } else {
-            get_asset_refs(src)?.iter().copied().map(to_asset).collect::<Result<Vec<_>, ReaderError>>()
-        }
-    }
-
-    #[inline]
-    fn get_assets_ex<T>(
-        src: &mut io::Cursor<&[u8]>,
-        version: GameVersion,
-        strict: bool,
-        multithread: bool,
-    ) -> Result<AssetList<T>, ReaderError>
-    where
-        T: Asset + Send,
-    {
-        get_assets(src, |data| <T as Asset>::deserialize_exe(data, version, strict), multithread)
-    }
-
     assert_ver!("extensions header", 700, exe.read_u32::<LE>()?)?;
     let extension_count = exe.read_u32::<LE>()? as usize;
-    let mut extensions = Vec::with_capacity(extension_count);
-    for _ in 0..extension_count {
-        let ext = Extension::read(&mut exe, strict)?;
-        log!(logger, "+ Added extension '{}' (files: {})", ext.name, ext.files.len());
+    let mut extensions = Vec::with_capacity(extension_count.min(remaining(&exe) / 4));
+    event!(events, ReaderEvent::ChunkStart { kind: AssetKind::Extension, count: extension_count });
+    for index in 0..extension_count {
+        let offset = exe.position() as usize;
+        let ext = Extension::read(&mut exe, strict, limits.max_inflated_len).map_err(|error| {
+            // extensions aren't compressed, so their name is right after the version
+            let name = exe.get_ref().get(offset.saturating_add(4)..).and_then(asset::read_name);
+            let position = exe.position() - offset as u64;
+            let location = AssetLocation { kind: AssetKind::Extension, index, name, offset, position };
+            InvalidAsset { location, error }
+        })?;
+        log!(events, "+ Added extension '{}' (files: {})", ext.name, ext.files.len());
+        event!(events, ReaderEvent::AssetDone { kind: AssetKind::Extension, index });
         extensions.push(ext);
     }
 
@@ -488,45 +657,207 @@ where
     let mut exe = io::Cursor::new(exe.into_inner() as &[u8]);
     exe.set_position(prev_pos);
 
+    let dx_dll = &exe.get_ref()[dll_pos..dll_pos + dll_len];
+
+    macro_rules! read_assets {
+        ($kind: ident) => {
+            get_asset_refs(&mut exe)
+                .map(|refs| LazyList::new(AssetKind::$kind, game_ver, strict, limits.max_inflated_len, refs))
+        };
+    }
+
     // Triggers
     assert_ver!("triggers header", 800, exe.read_u32::<LE>()?)?;
-    let triggers: AssetList<Trigger> = get_assets_ex(&mut exe, game_ver, strict, multithread)?;
-    if logger.is_some() {
-        triggers.iter().flatten().for_each(|trigger| {
-            log!(
-                logger,
-                " + Added trigger '{}' (moment: {}, condition: {})",
-                trigger.name,
-                trigger.moment,
-                trigger.condition
-            );
-        });
-    }
+    let triggers = read_assets!(Trigger)?;
 
     // Constants
     assert_ver!("constants header", 800, exe.read_u32::<LE>()?)?;
     let constant_count = exe.read_u32::<LE>()? as usize;
-    let mut constants = Vec::with_capacity(constant_count);
+    let mut constants = Vec::with_capacity(constant_count.min(remaining(&exe) / 8));
     for _ in 0..constant_count {
         let name = exe.read_pas_string()?;
         let expression = exe.read_pas_string()?;
-        log!(logger, " + Added constant '{}' (expression: {})", name, expression);
+        log!(events, " + Added constant '{}' (expression: {})", name, expression);
         constants.push(Constant { name, expression });
     }
 
     // Sounds
     assert_ver!("sounds header", 800, exe.read_u32::<LE>()?)?;
-    let sounds: AssetList<Sound> = get_assets_ex(&mut exe, game_ver, strict, multithread)?;
-    if logger.is_some() {
+    let sounds = read_assets!(Sound)?;
+
+    // Sprites
+    assert_ver!("sprites header", 800, exe.read_u32::<LE>()?)?;
+    let sprites = read_assets!(Sprite)?;
+
+    // Backgrounds
+    assert_ver!("backgrounds header", 800, exe.read_u32::<LE>()?)?;
+    let backgrounds = read_assets!(Background)?;
+
+    // Paths
+    assert_ver!("paths header", 800, exe.read_u32::<LE>()?)?;
+    let paths = read_assets!(Path)?;
+
+    // Scripts
+    assert_ver!("scripts header", 800, exe.read_u32::<LE>()?)?;
+    let scripts = read_assets!(Script)?;
+
+    // Fonts
+    assert_ver!("fonts header", 800, exe.read_u32::<LE>()?)?;
+    let fonts = read_assets!(Font)?;
+
+    // Timelines
+    assert_ver!("timelines header", 800, exe.read_u32::<LE>()?)?;
+    let timelines = read_assets!(Timeline)?;
+
+    // Objects
+    assert_ver!("objects header", 800, exe.read_u32::<LE>()?)?;
+    let objects = read_assets!(Object)?;
+
+    // Rooms
+    assert_ver!("rooms header", 800, exe.read_u32::<LE>()?)?;
+    let rooms = read_assets!(Room)?;
+
+    let last_instance_id = exe.read_i32::<LE>()?;
+    let last_tile_id = exe.read_i32::<LE>()?;
+
+    // Included Files
+    assert_ver!("included files header", 800, exe.read_u32::<LE>()?)?;
+    let included_files = read_assets!(IncludedFile)?;
+
+    // Help Dialog
+    assert_ver!("help dialog", 800, exe.read_u32::<LE>()?)?;
+    let help_dialog = {
+        let len = exe.read_u32::<LE>()? as usize;
+        let pos = exe.position() as usize;
+        let data = exe.get_ref().get(pos..pos.saturating_add(len)).unwrap_or(&[]);
+        let mut data = inflate(data, limits.max_inflated_len);
+        let hdg = GameHelpDialog {
+            bg_colour: data.read_u32::<LE>()?.into(),
+            new_window: data.read_u32::<LE>()? != 0,
+            caption: data.read_pas_string()?,
+            left: data.read_i32::<LE>()?,
+            top: data.read_i32::<LE>()?,
+            width: data.read_u32::<LE>()?,
+            height: data.read_u32::<LE>()?,
+            border: data.read_u32::<LE>()? != 0,
+            resizable: data.read_u32::<LE>()? != 0,
+            window_on_top: data.read_u32::<LE>()? != 0,
+            freeze_game: data.read_u32::<LE>()? != 0,
+            info: data.read_pas_string()?,
+        };
+        log!(events, " + Help Dialog: {:#?}", hdg);
+        exe.seek(SeekFrom::Current(len as i64))?;
+        hdg
+    };
+
+    // Action library initialization code. These are GML strings which get run at game start, in order.
+    assert_ver!("action library initialization code header", 500, exe.read_u32::<LE>()?)?;
+    let str_count = exe.read_u32::<LE>()? as usize;
+    let mut library_init_strings = Vec::with_capacity(str_count.min(remaining(&exe) / 4));
+    for _ in 0..str_count {
+        library_init_strings.push(exe.read_pas_string()?);
+    }
+    log!(events, " + Read {} action library initialization strings", str_count);
+
+    // Room Order
+    assert_ver!("room order lookup", 700, exe.read_u32::<LE>()?)?;
+    let room_order = {
+        let ro_count = exe.read_u32::<LE>()? as usize;
+        let mut room_order = Vec::with_capacity(ro_count.min(remaining(&exe) / 4));
+        for _ in 0..ro_count {
+            room_order.push(exe.read_i32::<LE>()?);
+        }
+        log!(events, " + Added Room Order LUT: {:?}", room_order);
+
+        room_order
+    };
+
+    Ok(LazyAssets {
+        extensions,
+        sprites,
+        sounds,
+        backgrounds,
+        paths,
+        scripts,
+        fonts,
+        timelines,
+        objects,
+        triggers,
+        constants,
+        rooms,
+        included_files,
+
+        dx_dll,
+        ico_file_raw,
+        version_info,
+        manifest,
+        version: game_ver,
+        help_dialog,
+        last_instance_id,
+        last_tile_id,
+        library_init_strings,
+        room_order,
+
+        settings,
+        game_id,
+        guid,
+    })
+}
+
+/// Reads a game. In `tolerant` mode, assets which can't be read are left out (as `None` where they're referred to
+/// by index) and listed in [`GameAssets::diagnostics`] instead of failing, so as much as possible can be salvaged
+/// from a broken game. An extension which can't be read still fails, since the rest can't be found without it.
+/// See [`Limits`] for reading games which might have been made to use up all memory.
+///
+/// `events` is told about progress as it's made, see [`ReaderEvent`]. Printing its `Message`s gives a verbose log.
+///
+/// `detectors` are tried in order to find the gamedata, normally [`gamedata::DETECTORS`].
+///
+/// This decodes every asset, see [`from_exe_lazy`] for only decoding those which are needed.
+pub fn from_exe<I, F>(
+    mut exe: I,
+    events: Option<F>,
+    strict: bool,
+    multithread: bool,
+    tolerant: bool,
+    limits: Limits,
+    detectors: &[Detector],
+) -> Result<GameAssets, ReaderError>
+where
+    F: Copy + Sync + Fn(ReaderEvent),
+    I: AsRef<[u8]> + AsMut<[u8]>,
+{
+    let lazy = from_exe_lazy(exe.as_mut(), events, strict, limits, detectors)?;
+
+    let mut diagnostics = Vec::new();
+    macro_rules! read_assets {
+        ($list: expr) => {
+            $list.get_all(multithread, if tolerant { Some(&mut diagnostics) } else { None }, events)
+        };
+    }
+
+    let triggers: AssetList<Trigger> = read_assets!(lazy.triggers)?;
+    if events.is_some() {
+        triggers.iter().flatten().for_each(|trigger| {
+            log!(
+                events,
+                " + Added trigger '{}' (moment: {}, condition: {})",
+                trigger.name,
+                trigger.moment,
+                trigger.condition
+            );
+        });
+    }
+
+    let sounds: AssetList<Sound> = read_assets!(lazy.sounds)?;
+    if events.is_some() {
         sounds.iter().flatten().for_each(|sound| {
-            log!(logger, " + Added sound '{}' ({})", sound.name, sound.source);
+            log!(events, " + Added sound '{}' ({})", sound.name, sound.source);
         });
     }
 
-    // Sprites
-    assert_ver!("sprites header", 800, exe.read_u32::<LE>()?)?;
-    let sprites: AssetList<Sprite> = get_assets_ex(&mut exe, game_ver, strict, multithread)?;
-    if logger.is_some() {
+    let sprites: AssetList<Sprite> = read_assets!(lazy.sprites)?;
+    if events.is_some() {
         sprites.iter().flatten().for_each(|sprite| {
             let framecount = sprite.frames.len();
             let (width, height) = match sprite.frames.first() {
@@ -534,7 +865,7 @@ where
                 None => (0, 0),
             };
             log!(
-                logger,
+                events,
                 " + Added sprite '{}' ({}x{}, {} frame{})",
                 sprite.name,
                 width,
@@ -545,24 +876,20 @@ where
         });
     }
 
-    // Backgrounds
-    assert_ver!("backgrounds header", 800, exe.read_u32::<LE>()?)?;
-    let backgrounds: AssetList<Background> = get_assets_ex(&mut exe, game_ver, strict, multithread)?;
-    if logger.is_some() {
+    let backgrounds: AssetList<Background> = read_assets!(lazy.backgrounds)?;
+    if events.is_some() {
         backgrounds.iter().flatten().for_each(|background| {
-            log!(logger, " + Added background '{}' ({}x{})", background.name, background.width, background.height);
+            log!(events, " + Added background '{}' ({}x{})", background.name, background.width, background.height);
         });
     }
 
-    // Paths
-    assert_ver!("paths header", 800, exe.read_u32::<LE>()?)?;
-    let paths: AssetList<Path> = get_assets_ex(&mut exe, game_ver, strict, multithread)?;
-    if logger.is_some() {
+    let paths: AssetList<Path> = read_assets!(lazy.paths)?;
+    if events.is_some() {
         use crate::asset::path::ConnectionKind;
 
         paths.iter().flatten().for_each(|path| {
             log!(
-                logger,
+                events,
                 " + Added path '{}' ({}, {}, {} point{}, precision: {})",
                 path.name,
                 match path.connection {
@@ -577,22 +904,18 @@ where
         });
     }
 
-    // Scripts
-    assert_ver!("scripts header", 800, exe.read_u32::<LE>()?)?;
-    let scripts: AssetList<Script> = get_assets_ex(&mut exe, game_ver, strict, multithread)?;
-    if logger.is_some() {
+    let scripts: AssetList<Script> = read_assets!(lazy.scripts)?;
+    if events.is_some() {
         scripts.iter().flatten().for_each(|script| {
-            log!(logger, " + Added script '{}'", script.name);
+            log!(events, " + Added script '{}'", script.name);
         });
     }
 
-    // Fonts
-    assert_ver!("fonts header", 800, exe.read_u32::<LE>()?)?;
-    let fonts: AssetList<Font> = get_assets_ex(&mut exe, game_ver, strict, multithread)?;
-    if logger.is_some() {
+    let fonts: AssetList<Font> = read_assets!(lazy.fonts)?;
+    if events.is_some() {
         fonts.iter().flatten().for_each(|font| {
             log!(
-                logger,
+                events,
                 " + Added font '{}' ({}, {}px{}{})",
                 font.name,
                 font.sys_name,
@@ -603,22 +926,18 @@ where
         });
     }
 
-    // Timelines
-    assert_ver!("timelines header", 800, exe.read_u32::<LE>()?)?;
-    let timelines: AssetList<Timeline> = get_assets_ex(&mut exe, game_ver, strict, multithread)?;
-    if logger.is_some() {
+    let timelines: AssetList<Timeline> = read_assets!(lazy.timelines)?;
+    if events.is_some() {
         timelines.iter().flatten().for_each(|timeline| {
-            log!(logger, " + Added timeline '{}' (moments: {})", timeline.name, timeline.moments.len());
+            log!(events, " + Added timeline '{}' (moments: {})", timeline.name, timeline.moments.len());
         });
     }
 
-    // Objects
-    assert_ver!("objects header", 800, exe.read_u32::<LE>()?)?;
-    let objects: AssetList<Object> = get_assets_ex(&mut exe, game_ver, strict, multithread)?;
-    if logger.is_some() {
+    let objects: AssetList<Object> = read_assets!(lazy.objects)?;
+    if events.is_some() {
         objects.iter().flatten().for_each(|object| {
             log!(
-                logger,
+                events,
                 " + Added object {} ({}{}{}depth {})",
                 object.name,
                 if object.solid { "solid; " } else { "" },
@@ -629,13 +948,11 @@ where
         });
     }
 
-    // Rooms
-    assert_ver!("rooms header", 800, exe.read_u32::<LE>()?)?;
-    let rooms: AssetList<Room> = get_assets_ex(&mut exe, game_ver, strict, multithread)?;
-    if logger.is_some() {
+    let rooms: AssetList<Room> = read_assets!(lazy.rooms)?;
+    if events.is_some() {
         rooms.iter().flatten().for_each(|room| {
             log!(
-                logger,
+                events,
                 " + Added room '{}' ({}x{}, {}FPS{})",
                 room.name,
                 room.width,
@@ -646,25 +963,14 @@ where
         });
     }
 
-    let last_instance_id = exe.read_i32::<LE>()?;
-    let last_tile_id = exe.read_i32::<LE>()?;
-
-    // Included Files
-    assert_ver!("included files header", 800, exe.read_u32::<LE>()?)?;
-    // TODO: how was this different from the others? why is it not using get_assets?
-    let included_files = get_asset_refs(&mut exe)?
-        .iter()
-        .map(|chunk| {
-            // AssetDataError -> ReaderError
-            let data = inflate(chunk);
-            IncludedFile::deserialize_exe(data, game_ver, strict).map_err(ReaderError::from)
-        })
-        .collect::<Result<Vec<_>, _>>()?;
-    if logger.is_some() {
+    // nothing refers to included files by index, so a broken one is just left out
+    let included_files: Vec<IncludedFile> =
+        read_assets!(lazy.included_files)?.into_iter().flatten().map(|file| *file).collect();
+    if events.is_some() {
         use crate::asset::included_file::ExportSetting;
         for file in &included_files {
             log!(
-                logger,
+                events,
                 " + Added included file '{}' (len: {}, export mode: {})",
                 file.file_name,
                 file.source_length,
@@ -678,55 +984,9 @@ where
         }
     }
 
-    // Help Dialog
-    assert_ver!("help dialog", 800, exe.read_u32::<LE>()?)?;
-    let help_dialog = {
-        let len = exe.read_u32::<LE>()? as usize;
-        let pos = exe.position() as usize;
-        let mut data = inflate(exe.get_ref().get(pos..pos + len).unwrap_or(&[]));
-        let hdg = GameHelpDialog {
-            bg_colour: data.read_u32::<LE>()?.into(),
-            new_window: data.read_u32::<LE>()? != 0,
-            caption: data.read_pas_string()?,
-            left: data.read_i32::<LE>()?,
-            top: data.read_i32::<LE>()?,
-            width: data.read_u32::<LE>()?,
-            height: data.read_u32::<LE>()?,
-            border: data.read_u32::<LE>()? != 0,
-            resizable: data.read_u32::<LE>()? != 0,
-            window_on_top: data.read_u32::<LE>()? != 0,
-            freeze_game: data.read_u32::<LE>()? != 0,
-            info: data.read_pas_string()?,
-        };
-        log!(logger, " + Help Dialog: {:#?}", hdg);
-        exe.seek(SeekFrom::Current(len as i64))?;
-        hdg
-    };
-
-    // Action library initialization code. These are GML strings which get run at game start, in order.
-    assert_ver!("action library initialization code header", 500, exe.read_u32::<LE>()?)?;
-    let str_count = exe.read_u32::<LE>()? as usize;
-    let mut library_init_strings = Vec::with_capacity(str_count);
-    for _ in 0..str_count {
-        library_init_strings.push(exe.read_pas_string()?);
-    }
-    log!(logger, " + Read {} action library initialization strings", str_count);
-
-    // Room Order
-    assert_ver!("room order lookup", 700, exe.read_u32::<LE>()?)?;
-    let room_order = {
-        let ro_count = exe.read_u32::<LE>()? as usize;
-        let mut room_order = Vec::with_capacity(ro_count);
-        for _ in 0..ro_count {
-            room_order.push(exe.read_i32::<LE>()?);
-        }
-        log!(logger, " + Added Room Order LUT: {:?}", room_order);
-
-        room_order
-    };
 
     Ok(GameAssets {
-        extensions,
+        extensions: lazy.extensions,
         sprites,
         sounds,
         backgrounds,
@@ -736,21 +996,25 @@ where
         timelines,
         objects,
         triggers,
-        constants,
+        constants: lazy.constants,
         rooms,
         included_files,
 
-        dx_dll,
-        ico_file_raw,
-        version: game_ver,
-        help_dialog,
-        last_instance_id,
-        last_tile_id,
-        library_init_strings,
-        room_order,
-
-        settings,
-        game_id,
-        guid,
+        dx_dll: lazy.dx_dll.to_vec(),
+        ico_file_raw: lazy.ico_file_raw,
+        version_info: lazy.version_info,
+        manifest: lazy.manifest,
+        version: lazy.version,
+        help_dialog: lazy.help_dialog,
+        last_instance_id: lazy.last_instance_id,
+        last_tile_id: lazy.last_tile_id,
+        library_init_strings: lazy.library_init_strings,
+        room_order: lazy.room_order,
+
+        settings: lazy.settings,
+        game_id: lazy.game_id,
+        guid: lazy.guid,
+
+        diagnostics,
     })
 }
@@ -1,6 +1,38 @@
 use crate::reader::PESection;
 use byteorder::{ReadBytesExt, WriteBytesExt, LE};
-use std::io::{self, Seek, SeekFrom};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::{
+    io::{self, Seek, SeekFrom},
+    ops::Range,
+};
+
+const RT_VERSION: u32 = 16;
+const RT_MANIFEST: u32 = 24;
+
+/// What the exe says about itself in its version resource (VS_VERSIONINFO). GameMaker fills this in from the
+/// game's settings, so it usually has the real title of the game. Strings left empty are `None`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct VersionInfo {
+    /// The file version from VS_FIXEDFILEINFO: major, minor, release, build.
+    pub version: [u16; 4],
+
+    /// `ProductName`, which GameMaker sets to the game's "Product".
+    pub product_name: Option<String>,
+
+    /// `FileDescription`, which GameMaker sets to the game's "Description".
+    pub file_description: Option<String>,
+
+    /// `CompanyName`, which GameMaker sets to the game's "Company".
+    pub company_name: Option<String>,
+
+    /// `FileVersion`, the version as written out rather than as numbers.
+    pub file_version: Option<String>,
+
+    /// `LegalCopyright`, which GameMaker sets to the game's "Copyright".
+    pub legal_copyright: Option<String>,
+}
 
 /*
 /// A windows icon from the .rsrc header
@@ -67,7 +99,7 @@ pub fn find_icons(data: &mut io::Cursor<&mut [u8]>, pe_sections: &[PESection]) -
         } else if id == 14 {
             // 14 = RT_GROUP_ICON
             data.set_position((offset as u64) + rsrc_base + 12);
-            let leaf_count = data.read_u16::<LE>()? + data.read_u16::<LE>()?;
+            let leaf_count = u32::from(data.read_u16::<LE>()?) + u32::from(data.read_u16::<LE>()?);
             if leaf_count == 0 {
                 // No leaves under RT_GROUP_ICON, so no icon
                 return Ok(None)
@@ -89,15 +121,14 @@ pub fn find_icons(data: &mut io::Cursor<&mut [u8]>, pe_sections: &[PESection]) -
                 ico_header.seek(SeekFrom::Current(4))?;
                 let image_count = usize::from(ico_header.read_u16::<LE>()?);
 
-                let raw_header_size = (6 + (image_count * 16)) as usize;
-                let raw_body_size: u32 = icons.iter().map(|t| t.2).sum();
-                let mut raw_file: Vec<u8> = Vec::with_capacity(raw_header_size + (raw_body_size as usize));
-                let mut raw_file_body: Vec<u8> = Vec::with_capacity(raw_body_size as usize);
+                let raw_header_size = 6 + (image_count * 16);
+                let mut raw_file: Vec<u8> = Vec::with_capacity(raw_header_size);
+                let mut raw_file_body: Vec<u8> = Vec::new();
                 raw_file.extend_from_slice(&v[0..6]);
                 for _ in 0..image_count {
                     // Copy data to raw file header
                     let pos = ico_header.position() as usize;
-                    raw_file.extend_from_slice(&v[pos..pos + 12]);
+                    raw_file.extend_from_slice(v.get(pos..pos + 12).ok_or(io::ErrorKind::UnexpectedEof)?);
                     raw_file.write_u32::<LE>((raw_header_size + raw_file_body.len()) as u32)?;
 
                     // Skip over the ICO file header
@@ -109,6 +140,11 @@ pub fn find_icons(data: &mut io::Cursor<&mut [u8]>, pe_sections: &[PESection]) -
                     for icon in &icons {
                         if icon.0 == ordinal as u32 && icon.2 >= 40 {
                             if let Some(v) = extract_virtual_bytes(data, pe_sections, icon.1, icon.2 as usize)? {
+                                // an icon group which uses the same big icon over and over could otherwise
+                                // take up far more memory than the exe
+                                if raw_file_body.len() + v.len() > data.get_ref().len() {
+                                    return Err(io::Error::new(io::ErrorKind::InvalidData, "icon group is too large"))
+                                }
                                 raw_file_body.extend_from_slice(&v);
                             }
                             break
@@ -133,14 +169,330 @@ fn extract_virtual_bytes(
 ) -> io::Result<Option<Vec<u8>>> {
     for section in pe_sections {
         if rva >= section.virtual_address
-            && ((rva as usize) + size) < ((section.virtual_address + section.virtual_size) as usize)
+            && (u64::from(rva) + size as u64) < u64::from(section.virtual_address) + u64::from(section.virtual_size)
         {
             // data is in this section
             let offset_on_disk = rva - section.virtual_address;
-            let data_location = (section.disk_address + offset_on_disk) as usize;
-            return Ok(data.get_ref().get(data_location..data_location + size).map(|chunk| chunk.to_vec()))
+            let data_location = section.disk_address as usize + offset_on_disk as usize;
+            return Ok(data.get_ref().get(data_location..data_location.saturating_add(size)).map(|chunk| chunk.to_vec()))
         }
     }
 
     Ok(None)
 }
+
+/// Finds the first resource of type `kind`, whatever its name and language, and returns its data.
+/// Expects the cursor to be at the start of the .rsrc section, and leaves it somewhere else.
+fn find_resource(
+    data: &mut io::Cursor<&mut [u8]>,
+    pe_sections: &[PESection],
+    kind: u32,
+) -> io::Result<Option<Vec<u8>>> {
+    let rsrc_base = data.position();
+
+    // the directory tree goes type, then name, then language, and the language's entry points to the data
+    let mut entry = 0;
+    for level in 0..3 {
+        data.set_position(rsrc_base + u64::from(entry & 0x7FFFFFFF) + 12);
+        let name_count = data.read_u16::<LE>()?;
+        let id_count = data.read_u16::<LE>()?;
+        if level == 0 {
+            // types are looked up by ID, which come after any names
+            data.seek(SeekFrom::Current(i64::from(name_count) * 8))?;
+            let mut found = None;
+            for _ in 0..id_count {
+                let id = data.read_u32::<LE>()?;
+                let offset = data.read_u32::<LE>()?;
+                if id == kind {
+                    found = Some(offset);
+                    break
+                }
+            }
+            entry = match found {
+                Some(offset) => offset,
+                None => return Ok(None),
+            };
+        } else {
+            if name_count == 0 && id_count == 0 {
+                return Ok(None)
+            }
+            data.seek(SeekFrom::Current(4))?;
+            entry = data.read_u32::<LE>()?;
+        }
+
+        // the high bit says whether it points to another directory, which it has to until the last level
+        if (entry & 0x80000000 != 0) != (level < 2) {
+            return Ok(None)
+        }
+    }
+
+    data.set_position(rsrc_base + u64::from(entry));
+    let rva = data.read_u32::<LE>()?;
+    let size = data.read_u32::<LE>()?;
+    extract_virtual_bytes(data, pe_sections, rva, size as usize)
+}
+
+/// Finds and parses the version resource, if the exe has one.
+/// Expects the cursor to be at the start of the .rsrc section, and leaves it somewhere else.
+pub fn find_version_info(
+    data: &mut io::Cursor<&mut [u8]>,
+    pe_sections: &[PESection],
+) -> io::Result<Option<VersionInfo>> {
+    Ok(find_resource(data, pe_sections, RT_VERSION)?.and_then(|resource| parse_version_info(&resource)))
+}
+
+/// Finds the application manifest, if the exe has one. It's XML, which is returned as it is.
+/// Expects the cursor to be at the start of the .rsrc section, and leaves it somewhere else.
+pub fn find_manifest(data: &mut io::Cursor<&mut [u8]>, pe_sections: &[PESection]) -> io::Result<Option<String>> {
+    Ok(find_resource(data, pe_sections, RT_MANIFEST)?.map(|manifest| {
+        let manifest = manifest.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(&manifest); // UTF-8 BOM
+        String::from_utf8_lossy(manifest).into_owned()
+    }))
+}
+
+/// One entry in a VS_VERSIONINFO tree, with the ranges of its value and children in the resource.
+struct VersionNode {
+    key: String,
+    value: Range<usize>,
+    children: Range<usize>,
+}
+
+/// Each entry is aligned to 4 bytes from the start of the resource, as are the value and children within it.
+fn align4(pos: usize) -> usize {
+    pos.saturating_add(3) & !3
+}
+
+/// Reads a null-terminated UTF-16 string, returning it and where it ends.
+fn read_utf16z(data: &[u8], range: Range<usize>) -> (String, usize) {
+    let units = data.get(range.clone()).unwrap_or(&[]).chunks_exact(2).map(|c| u16::from_le_bytes([c[0], c[1]]));
+    let units = units.take_while(|&unit| unit != 0).collect::<Vec<_>>();
+    (String::from_utf16_lossy(&units), (range.start + units.len() * 2 + 2).min(range.end))
+}
+
+fn read_version_node(data: &[u8], pos: usize) -> Option<VersionNode> {
+    let read_u16 = |at: usize| data.get(at..at.checked_add(2)?).map(|b| u16::from_le_bytes([b[0], b[1]]));
+    let len = usize::from(read_u16(pos)?);
+    let value_len = usize::from(read_u16(pos + 2)?);
+    let is_text = read_u16(pos + 4)? == 1;
+    let end = pos + len;
+    if len < 6 || end > data.len() {
+        return None
+    }
+    let (key, key_end) = read_utf16z(data, pos + 6..end);
+
+    // text values are measured in UTF-16 units, and tools don't always get this right, so it's kept in bounds
+    let value_start = align4(key_end).min(end);
+    let value_end = (value_start + if is_text { value_len * 2 } else { value_len }).min(end);
+    let children = align4(value_end).min(end)..end;
+    Some(VersionNode { key, value: value_start..value_end, children })
+}
+
+/// Reads the entries one after another in `range`, stopping at the first one which doesn't make sense.
+fn read_version_children(data: &[u8], range: Range<usize>) -> Vec<VersionNode> {
+    let mut nodes = Vec::new();
+    let mut pos = range.start;
+    while pos < range.end {
+        match read_version_node(data, pos) {
+            Some(node) if node.children.end <= range.end => {
+                pos = align4(node.children.end);
+                nodes.push(node);
+            },
+            _ => break,
+        }
+    }
+    nodes
+}
+
+fn parse_version_info(data: &[u8]) -> Option<VersionInfo> {
+    let root = read_version_node(data, 0)?;
+    if root.key != "VS_VERSION_INFO" {
+        return None
+    }
+    let mut info = VersionInfo::default();
+
+    // VS_FIXEDFILEINFO starts with a signature, then the struct version, then the file version
+    let fixed = data.get(root.value.clone()).unwrap_or(&[]);
+    if fixed.len() >= 16 && fixed[..4] == 0xFEEF04BDu32.to_le_bytes() {
+        let read_u16 = |at: usize| u16::from_le_bytes([fixed[at], fixed[at + 1]]);
+        info.version = [read_u16(10), read_u16(8), read_u16(14), read_u16(12)];
+    }
+
+    // StringFileInfo has a table for each language, and GameMaker only writes the one
+    let string_file_info =
+        read_version_children(data, root.children.clone()).into_iter().find(|node| node.key == "StringFileInfo");
+    let table = string_file_info.and_then(|node| read_version_children(data, node.children).into_iter().next());
+    for string in table.map(|table| read_version_children(data, table.children)).unwrap_or_default() {
+        let (value, _) = read_utf16z(data, string.value);
+        let value = if value.trim().is_empty() { None } else { Some(value) };
+        match string.key.as_str() {
+            "ProductName" => info.product_name = value,
+            "FileDescription" => info.file_description = value,
+            "CompanyName" => info.company_name = value,
+            "FileVersion" => info.file_version = value,
+            "LegalCopyright" => info.legal_copyright = value,
+            _ => (),
+        }
+    }
+
+    Some(info)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utf16z(s: &str) -> Vec<u8> {
+        s.encode_utf16().chain([0]).flat_map(u16::to_le_bytes).collect()
+    }
+
+    fn pad4(data: &mut Vec<u8>) {
+        data.resize(align4(data.len()), 0);
+    }
+
+    /// Makes a VS_VERSIONINFO entry. Text values are counted in UTF-16 units, like Windows does.
+    fn version_node(key: &str, value: &[u8], is_text: bool, children: &[Vec<u8>]) -> Vec<u8> {
+        let mut node = vec![0; 6];
+        node.extend(utf16z(key));
+        pad4(&mut node);
+        node.extend_from_slice(value);
+        for child in children {
+            pad4(&mut node);
+            node.extend_from_slice(child);
+        }
+        let (len, value_len) = (node.len(), if is_text { value.len() / 2 } else { value.len() });
+        node[0..2].copy_from_slice(&(len as u16).to_le_bytes());
+        node[2..4].copy_from_slice(&(value_len as u16).to_le_bytes());
+        node[4..6].copy_from_slice(&u16::from(is_text).to_le_bytes());
+        node
+    }
+
+    /// Version 1.2.3.4 of "Needle", with an empty company name.
+    fn version_info() -> Vec<u8> {
+        let mut fixed = Vec::new();
+        for n in [0xFEEF04BD, 0x00010000, 0x00010002, 0x00030004] {
+            fixed.extend_from_slice(&u32::to_le_bytes(n));
+        }
+        fixed.resize(52, 0);
+        let string = |key, value| version_node(key, &utf16z(value), true, &[]);
+        let table = version_node("080904b0", &[], true, &[string("ProductName", "Needle"), string("CompanyName", " ")]);
+        version_node("VS_VERSION_INFO", &fixed, false, &[version_node("StringFileInfo", &[], true, &[table])])
+    }
+
+    fn expected_version_info() -> VersionInfo {
+        VersionInfo { version: [1, 2, 3, 4], product_name: Some("Needle".into()), ..VersionInfo::default() }
+    }
+
+    /// Where the .rsrc section made by [`rsrc`] is loaded.
+    const RSRC_RVA: u32 = 0x1000;
+
+    /// Makes a .rsrc section holding `resources` by type, along with the section header pointing to it.
+    fn rsrc(resources: &[(u32, &[u8])]) -> (Vec<u8>, PESection) {
+        fn directory(data: &mut Vec<u8>, entries: &[(u32, u32)]) {
+            data.extend_from_slice(&[0; 12]);
+            data.extend_from_slice(&0u16.to_le_bytes());
+            data.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+            for (id, offset) in entries {
+                data.extend_from_slice(&id.to_le_bytes());
+                data.extend_from_slice(&offset.to_le_bytes());
+            }
+        }
+
+        // the root, then a name directory, a language directory and a data entry for each resource, then the data
+        let root_len = 16 + 8 * resources.len() as u32;
+        let tree_len = root_len + 64 * resources.len() as u32;
+        let mut data = Vec::new();
+        let entries = (0..resources.len() as u32).map(|i| (resources[i as usize].0, 0x80000000 | (root_len + i * 64)));
+        directory(&mut data, &entries.collect::<Vec<_>>());
+        let mut data_at = tree_len;
+        for (i, (_, resource)) in resources.iter().enumerate() {
+            let at = root_len + i as u32 * 64;
+            directory(&mut data, &[(1, 0x80000000 | (at + 24))]);
+            directory(&mut data, &[(0x409, at + 48)]);
+            for n in [RSRC_RVA + data_at, resource.len() as u32, 0, 0] {
+                data.extend_from_slice(&n.to_le_bytes());
+            }
+            data_at = align4(data_at as usize + resource.len()) as u32;
+        }
+        for (_, resource) in resources {
+            data.extend_from_slice(resource);
+            pad4(&mut data);
+        }
+        let len = data.len() as u32;
+        (data, PESection { virtual_size: len + 0x100, virtual_address: RSRC_RVA, disk_size: len, disk_address: 0 })
+    }
+
+    fn version_info_in(data: &mut [u8], section: &PESection) -> io::Result<Option<VersionInfo>> {
+        find_version_info(&mut io::Cursor::new(data), std::slice::from_ref(section))
+    }
+
+    fn manifest_in(data: &mut [u8], section: &PESection) -> io::Result<Option<String>> {
+        find_manifest(&mut io::Cursor::new(data), std::slice::from_ref(section))
+    }
+
+    const MANIFEST: &[u8] = b"\xEF\xBB\xBF<assembly manifestVersion=\"1.0\"/>";
+
+    #[test]
+    fn parses_version_info() {
+        assert_eq!(parse_version_info(&version_info()), Some(expected_version_info()));
+    }
+
+    #[test]
+    fn finds_resources() {
+        let (mut data, section) = rsrc(&[(RT_VERSION, &version_info()), (RT_MANIFEST, MANIFEST)]);
+        assert_eq!(version_info_in(&mut data, &section).unwrap(), Some(expected_version_info()));
+        assert_eq!(manifest_in(&mut data, &section).unwrap().as_deref(), Some("<assembly manifestVersion=\"1.0\"/>"));
+
+        let (mut data, section) = rsrc(&[(RT_MANIFEST, MANIFEST)]);
+        assert_eq!(version_info_in(&mut data, &section).unwrap(), None);
+    }
+
+    #[test]
+    fn truncated_version_info() {
+        let data = version_info();
+        for len in 0..data.len() {
+            assert_eq!(parse_version_info(&data[..len]), None, "cut off at {}", len);
+        }
+    }
+
+    #[test]
+    fn malformed_version_info() {
+        // lengths which point anywhere, and text values longer than their entries, are kept in bounds
+        let data = version_info();
+        for at in 0..data.len() {
+            for byte in [0x00, 0x01, 0x7F, 0xFF] {
+                let mut data = data.clone();
+                data[at] = byte;
+                parse_version_info(&data);
+            }
+        }
+    }
+
+    #[test]
+    fn truncated_resources() {
+        let version_info = version_info();
+        for resource in [(RT_VERSION, version_info.as_slice()), (RT_MANIFEST, MANIFEST)] {
+            let (data, section) = rsrc(&[resource]);
+            // the resource is last, followed by padding
+            for len in 0..data.len() - (align4(resource.1.len()) - resource.1.len()) {
+                let mut data = data[..len].to_vec();
+                let (info, manifest) = (version_info_in(&mut data, &section), manifest_in(&mut data, &section));
+                assert!(matches!(info, Ok(None) | Err(_)), "cut off at {}: {:?}", len, info);
+                assert!(matches!(manifest, Ok(None) | Err(_)), "cut off at {}: {:?}", len, manifest);
+            }
+        }
+    }
+
+    #[test]
+    fn malformed_resources() {
+        let (data, section) = rsrc(&[(RT_VERSION, &version_info()), (RT_MANIFEST, MANIFEST)]);
+        let tree_len = 16 + 8 * 2 + 64 * 2;
+        for at in (0..tree_len).step_by(2) {
+            for word in [0, 0x7FFF, 0xFFFF] {
+                let mut data = data.clone();
+                data[at..at + 2].copy_from_slice(&u16::to_le_bytes(word));
+                let _ = version_info_in(&mut data, &section);
+                let _ = manifest_in(&mut data, &section);
+            }
+        }
+    }
+}
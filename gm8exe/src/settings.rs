@@ -1,6 +1,11 @@
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
 use crate::{asset::PascalString, colour::Colour};
 
 /// The Settings header for a GM8 game
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Settings {
     /// Start in full-screen mode
     pub fullscreen: bool,
@@ -118,6 +123,7 @@ pub struct Settings {
     pub priority: u32,
 
     /// Show your own image while loading (data)
+    #[cfg_attr(feature = "serde", serde(with = "crate::base64::option"))]
     pub custom_load_image: Option<Box<[u8]>>,
 
     /// Sub-value of `custom_load_image`:
@@ -137,9 +143,11 @@ pub struct Settings {
     pub loading_bar: u32,
 
     /// Loading bar - (Custom) Back Image
+    #[cfg_attr(feature = "serde", serde(with = "crate::base64::option"))]
     pub backdata: Option<Box<[u8]>>,
 
     /// Loading bar - (Custom) Front Image
+    #[cfg_attr(feature = "serde", serde(with = "crate::base64::option"))]
     pub frontdata: Option<Box<[u8]>>,
 
     /// Scale progress bar image
@@ -166,6 +174,7 @@ pub struct Settings {
 
 /// The help dialog box associated with a GM8 game
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct GameHelpDialog {
     pub bg_colour: Colour,
     pub new_window: bool,
@@ -1,148 +1,766 @@
-use crate::reader::ReaderError;
-use byteorder::{ReadBytesExt, LE};
+use crate::reader::{ReaderError, ReaderEvent};
 use std::io;
 
-/// Unpack the bytecode of a UPX-protected exe into a separate buffer
+/// The magic number which starts a packheader.
+const PACKHEADER_MAGIC: &[u8; 4] = b"UPX!";
+
+/// How long a packheader is, for UPX 1.x onwards packing anything but DOS programs.
+const PACKHEADER_LEN: usize = 32;
+
+/// How far into UPX1 the compressed data may start. UPX puts the end of its identification string before it,
+/// which is a different length in each version.
+const MAX_DATA_START: usize = 0x200;
+
+/// Where the compressed data starts in games packed without a packheader, which only ever used NRV2E.
+const LEGACY_DATA_START: usize = 0xD;
+
+/// What the unpacked runner is put after, standing in for the PE headers.
+const HEADERS_LEN: usize = 0x400;
+
+/// How long the original PE signature, file header and optional header are, as UPX keeps them.
+const PE_HEADERS_LEN: usize = 248;
+
+/// The algorithms UPX compresses with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Method {
+    Nrv2b(BitBuffer),
+    Nrv2d(BitBuffer),
+    Nrv2e(BitBuffer),
+    Lzma,
+}
+
+/// How many bits at a time the NRV algorithms read their instruction bits in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BitBuffer {
+    Le32,
+    Le16,
+    Byte,
+}
+
+impl Method {
+    fn from_id(id: u8) -> Option<Self> {
+        Some(match id {
+            2 => Method::Nrv2b(BitBuffer::Le32),
+            3 => Method::Nrv2b(BitBuffer::Byte),
+            4 => Method::Nrv2b(BitBuffer::Le16),
+            5 => Method::Nrv2d(BitBuffer::Le32),
+            6 => Method::Nrv2d(BitBuffer::Byte),
+            7 => Method::Nrv2d(BitBuffer::Le16),
+            8 => Method::Nrv2e(BitBuffer::Le32),
+            9 => Method::Nrv2e(BitBuffer::Byte),
+            10 => Method::Nrv2e(BitBuffer::Le16),
+            14 => Method::Lzma,
+            _ => return None,
+        })
+    }
+}
+
+/// What UPX records about the packed data, next to the PE headers.
+#[derive(Clone, Copy, Debug)]
+pub struct PackHeader {
+    /// The version of the packheader format, which goes up with UPX versions but not for each of them.
+    pub version: u8,
+    pub format: u8,
+    pub method: Method,
+    pub level: u8,
+
+    /// Adler-32 of the unpacked data. UPX's own unpacker checks it before undoing the filter, but it's taken either
+    /// way here, see [`unpack`].
+    pub u_adler: u32,
+
+    /// Adler-32 of the compressed data.
+    pub c_adler: u32,
+    pub u_len: u32,
+    pub c_len: u32,
+
+    /// The filter applied to the data before compressing it, to make x86 code compress better. 0 for none.
+    pub filter: u8,
+
+    /// The byte some filters mark the instructions they changed with.
+    pub filter_cto: u8,
+}
+
+impl PackHeader {
+    /// Finds the first valid packheader in `data`. Returns an error if it's using a method that isn't supported.
+    pub fn find(data: &[u8]) -> io::Result<Option<Self>> {
+        for (pos, window) in data.windows(PACKHEADER_LEN).enumerate() {
+            if &window[..4] != PACKHEADER_MAGIC {
+                continue
+            }
+            let (version, format) = (window[4], window[5]);
+            // older versions lay it out differently, and DOS formats have a shorter one
+            if version < 10 || format <= 3 {
+                continue
+            }
+            let checksum = window[4..PACKHEADER_LEN - 1].iter().map(|&b| u32::from(b)).sum::<u32>() % 251;
+            if checksum != u32::from(window[PACKHEADER_LEN - 1]) {
+                continue
+            }
+            let le32 = |at: usize| u32::from_le_bytes([window[at], window[at + 1], window[at + 2], window[at + 3]]);
+            let method = Method::from_id(window[6]).ok_or_else(|| {
+                let message = format!("packheader at {} uses unsupported UPX method {}", pos, window[6]);
+                io::Error::new(io::ErrorKind::InvalidData, message)
+            })?;
+            return Ok(Some(PackHeader {
+                version,
+                format,
+                method,
+                level: window[7],
+                u_adler: le32(8),
+                c_adler: le32(12),
+                u_len: le32(16),
+                c_len: le32(20),
+                filter: window[28],
+                filter_cto: window[29],
+            }))
+        }
+        Ok(None)
+    }
+}
+
+/// Adler-32, as UPX checksums with.
+pub fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    // 5552 is the most bytes that can be summed before b might overflow
+    for chunk in data.chunks(5552) {
+        for &byte in chunk {
+            a += u32::from(byte);
+            b += a;
+        }
+        a %= 65521;
+        b %= 65521;
+    }
+    (b << 16) | a
+}
+
+/// Finds where `len` bytes with the Adler-32 `adler` start in `data`, trying starts up to `max_start`.
+fn find_by_adler(data: &[u8], len: usize, adler: u32, max_start: usize) -> Option<usize> {
+    const MOD: u64 = 65521;
+    let first = data.get(..len)?;
+    let (mut a, mut b) = (u64::from(adler32(first) & 0xFFFF), u64::from(adler32(first) >> 16));
+    let n = len as u64 % MOD;
+    for start in 0..=max_start {
+        if (b << 16 | a) == u64::from(adler) {
+            return Some(start)
+        }
+        // slide the window along by a byte
+        let (out, into) = (u64::from(data[start]), u64::from(*data.get(start + len)?));
+        a = (a + MOD + into - out) % MOD;
+        b = (b + MOD * MOD - n * out % MOD + a + MOD - 1) % MOD;
+    }
+    None
+}
+
+/// Unpack the bytecode of a UPX-protected exe into a separate buffer.
+/// Fails if it unpacks to much more than `max_size`, which the section headers give, or to more than `max_len` bytes.
 pub fn unpack<F>(
     data: &mut io::Cursor<&mut [u8]>,
     max_size: u32,
     disk_offset: u32,
-    logger: Option<F>,
+    max_len: usize,
+    events: Option<F>,
 ) -> Result<Vec<u8>, ReaderError>
 where
-    F: Copy + Fn(&str),
+    F: Copy + Fn(ReaderEvent),
 {
-    log!(logger, "Unpacking UPX with output size {}, data starting at {}", max_size, disk_offset);
+    log!(events, "Unpacking UPX with output size {}, data starting at {}", max_size, disk_offset);
+    let max_len = max_len.min((max_size as usize).saturating_add(HEADERS_LEN));
+    let exe: &[u8] = data.get_ref();
+    let section = exe.get(disk_offset as usize..).ok_or(io::Error::from(io::ErrorKind::UnexpectedEof))?;
 
     // set up output vector
-    let mut output: Vec<u8> = Vec::with_capacity(max_size as usize);
-    output.extend_from_slice(&[0u8; 0x400]);
-    data.set_position((disk_offset as u64) + 0xD); // yeah it starts 13 bytes into the section
-
-    // helper function to pull a new bit from the mask buffer and pull a new mask if we exhaust the current one
-    fn pull_new_bit(
-        mask_buffer: &mut u32,
-        next_bit_buffer: &mut bool,
-        data: &mut io::Cursor<&mut [u8]>,
-    ) -> Result<(), ReaderError> {
-        let (b, w) = mask_buffer.overflowing_add(*mask_buffer);
-        if b == 0 {
-            let v = data.read_u32::<LE>()?;
-            let (b, w) = v.overflowing_add(v);
-            *mask_buffer = b + 1;
-            *next_bit_buffer = w;
-            Ok(())
-        } else {
-            *mask_buffer = b;
-            *next_bit_buffer = w;
-            Ok(())
+    let mut output: Vec<u8> = Vec::with_capacity(max_len.min(exe.len().saturating_mul(8)));
+    output.extend_from_slice(&[0u8; HEADERS_LEN]);
+
+    // the packheader is normally just before the first section, but it's sometimes at the start of UPX1
+    let search_end = (disk_offset as usize).saturating_add(MAX_DATA_START).min(exe.len());
+    let header = match PackHeader::find(&exe[..search_end])? {
+        Some(header) => header,
+        None => {
+            log!(events, "No UPX packheader found, assuming NRV2E data {} bytes into UPX1", LEGACY_DATA_START);
+            let compressed = section.get(LEGACY_DATA_START..).unwrap_or(&[]);
+            nrv(Method::Nrv2e(BitBuffer::Le32), compressed, &mut output, max_len)?;
+            return Ok(output)
+        },
+    };
+    log!(
+        events,
+        "Found UPX packheader version {} (format {}, method {:?}, level {}, filter 0x{:X}), {} bytes packed to {}",
+        header.version,
+        header.format,
+        header.method,
+        header.level,
+        header.filter,
+        header.u_len,
+        header.c_len
+    );
+    if (header.u_len as usize).saturating_add(HEADERS_LEN) > max_len {
+        return Err(malformed("UPX data unpacks to more than the limit").into())
+    }
+
+    let c_len = header.c_len as usize;
+    let start = find_by_adler(section, c_len, header.c_adler, MAX_DATA_START).ok_or_else(|| {
+        log!(events, "Couldn't find {} bytes in UPX1 matching the packheader's checksum", c_len);
+        ReaderError::PartialUPXPacking
+    })?;
+    log!(events, "Compressed data starts {} bytes into UPX1", start);
+    let compressed = &section[start..start + c_len];
+
+    let max_len = HEADERS_LEN + header.u_len as usize;
+    match header.method {
+        Method::Lzma => lzma(compressed, &mut output, header.u_len)?,
+        method => nrv(method, compressed, &mut output, max_len)?,
+    }
+    if output.len() != max_len {
+        return Err(malformed("UPX data unpacks to a different length than the packheader says").into())
+    }
+
+    // whether the checksum covers the data before or after unfiltering, it has to match one of them
+    let mut checked = adler32(&output[HEADERS_LEN..]) == header.u_adler;
+    if header.filter != 0 {
+        let unpacked = &mut output[HEADERS_LEN..];
+        let filter = Filter::from_id(header.filter)
+            .ok_or_else(|| malformed(&format!("UPX filter 0x{:X} isn't supported", header.filter)))?;
+        let (start, len) = filter_range(unpacked).ok_or_else(|| malformed("can't tell where UPX's filter applies"))?;
+        log!(events, "Undoing UPX filter 0x{:X} on {} bytes starting {} bytes in", header.filter, len, start);
+        filter.undo(&mut unpacked[start..start + len], start as u32, header.filter_cto);
+        checked = checked || adler32(unpacked) == header.u_adler;
+    }
+    if !checked {
+        return Err(malformed("unpacked UPX data doesn't match the packheader's checksum").into())
+    }
+
+    Ok(output)
+}
+
+/// What a UPX filter did to x86 code, which was to replace the relative targets of calls and jumps with absolute
+/// ones, since code tends to call the same places over and over.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Filter {
+    /// Whether it's calls (E8), jumps (E9) or both.
+    calls: bool,
+    jumps: bool,
+
+    /// Whether conditional jumps (0F 80 to 0F 8F) are included too.
+    jcc: bool,
+
+    /// Whether the targets were written big-endian.
+    swapped: bool,
+
+    /// Whether targets were only changed if they landed inside the filtered code, in which case their top byte was
+    /// set to the packheader's `filter_cto`.
+    cto: bool,
+}
+
+impl Filter {
+    /// The 32-bit x86 filters UPX packs Windows programs with.
+    fn from_id(id: u8) -> Option<Self> {
+        let (calls, jumps) = match id & 0xF {
+            1 | 4 => (true, false),
+            2 | 5 => (false, true),
+            3 | 6 | 9 => (true, true),
+            _ => return None,
+        };
+        let (jcc, swapped, cto) = match id {
+            0x11..=0x13 => (false, false, false),
+            0x14..=0x16 => (false, true, false),
+            0x24..=0x26 | 0x46 => (false, true, true),
+            0x49 => (true, true, true),
+            _ => return None,
+        };
+        Some(Filter { calls, jumps, jcc, swapped, cto })
+    }
+
+    /// Puts back the relative targets in `code`, which starts `offset` bytes into the unpacked data.
+    fn undo(self, code: &mut [u8], offset: u32, cto: u8) {
+        // where the last target that was put back ends, so its last byte isn't taken for a conditional jump
+        let mut last = 0;
+        let mut pos = 0;
+        while pos + 5 < code.len() {
+            let opcode = code[pos];
+            let is_jcc = self.jcc && pos != last && code[pos - 1] == 0x0F && (0x80..=0x8F).contains(&opcode);
+            if (self.calls && opcode == 0xE8) || (self.jumps && opcode == 0xE9) || is_jcc {
+                let bytes = [code[pos + 1], code[pos + 2], code[pos + 3], code[pos + 4]];
+                if !self.cto || bytes[0] == cto {
+                    let target = if self.swapped { u32::from_be_bytes(bytes) } else { u32::from_le_bytes(bytes) };
+                    let marker = if self.cto { u32::from(cto) << 24 } else { 0 };
+                    let relative = target.wrapping_sub(marker).wrapping_sub(pos as u32 + 1).wrapping_sub(offset);
+                    code[pos + 1..pos + 5].copy_from_slice(&relative.to_le_bytes());
+                    pos += 5;
+                    last = pos;
+                    continue
+                }
+            }
+            pos += 1;
         }
     }
+}
+
+/// Finds where the code UPX filtered is in `unpacked`, from the original PE headers UPX keeps near the end of it.
+/// The last 4 bytes say where those are, and the code's position comes from its RVA minus the first section's.
+fn filter_range(unpacked: &[u8]) -> Option<(usize, usize)> {
+    let le32 = |at: usize| {
+        let bytes = unpacked.get(at..at.checked_add(4)?)?;
+        Some(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize)
+    };
+    let headers = le32(unpacked.len().checked_sub(4)?)?;
+    if unpacked.get(headers..headers.checked_add(4)?)? != b"PE\0\0" {
+        return None
+    }
+    let code_size = le32(headers + 28)?;
+    let code_base = le32(headers + 44)?;
+    // the section table comes right after the headers, and the first section is the lowest one
+    let first_section = le32(headers + PE_HEADERS_LEN + 12)?;
+    let start = code_base.checked_sub(first_section)?;
+    let len = code_size.min(unpacked.len().checked_sub(start)?);
+    Some((start, len))
+}
+
+fn malformed(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Reads NRV's instruction bits and bytes, which are interleaved in the compressed data.
+struct NrvReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    width: BitBuffer,
+    buffer: u32,
+    bits_left: u32,
+}
 
-    // Data always starts with a bitmask, so let's pull it in and assign our buffers their IVs
-    let v = data.read_u32::<LE>()?;
-    let (b, w) = v.overflowing_add(v);
-    let mut mask_buffer = b + 1;
-    let mut next_bit_buffer = w;
+impl NrvReader<'_> {
+    fn byte(&mut self) -> io::Result<u8> {
+        let byte = *self.data.get(self.pos).ok_or(io::ErrorKind::UnexpectedEof)?;
+        self.pos += 1;
+        Ok(byte)
+    }
 
-    // This value also gets stored between loops
-    let mut u_var12: u32 = 0xFFFFFFFF;
+    fn bit(&mut self) -> io::Result<u32> {
+        match self.width {
+            BitBuffer::Byte => {
+                // the lowest set bit marks where the byte's bits run out
+                self.buffer = if self.buffer & 0x7F != 0 {
+                    self.buffer.wrapping_mul(2)
+                } else {
+                    u32::from(self.byte()?) * 2 + 1
+                };
+                Ok((self.buffer >> 8) & 1)
+            },
+            BitBuffer::Le16 | BitBuffer::Le32 => {
+                if self.bits_left == 0 {
+                    let len = if self.width == BitBuffer::Le16 { 2 } else { 4 };
+                    let bytes = self.data.get(self.pos..self.pos + len).ok_or(io::ErrorKind::UnexpectedEof)?;
+                    self.buffer = bytes.iter().rev().fold(0, |buffer, &b| (buffer << 8) | u32::from(b));
+                    self.bits_left = len as u32 * 8;
+                    self.pos += len;
+                }
+                self.bits_left -= 1;
+                Ok((self.buffer >> self.bits_left) & 1)
+            },
+        }
+    }
+}
 
-    // Main loop
+/// Decompresses NRV2B, NRV2D or NRV2E data onto the end of `output`, up to `max_len` bytes of it in total.
+/// These are the decompressors from UCL, which UPX uses.
+fn nrv(method: Method, data: &[u8], output: &mut Vec<u8>, max_len: usize) -> io::Result<()> {
+    let width = match method {
+        Method::Nrv2b(width) | Method::Nrv2d(width) | Method::Nrv2e(width) => width,
+        Method::Lzma => unreachable!(),
+    };
+    let mut src = NrvReader { data, pos: 0, width, buffer: 0, bits_left: 0 };
+    let start = output.len();
+    let mut last_offset: u32 = 1;
     loop {
-        if next_bit_buffer {
-            // Instruction bit 1 means to copy a byte directly from input to output.
-            output.push(data.read_u8()?);
-            pull_new_bit(&mut mask_buffer, &mut next_bit_buffer, data)?;
-            continue
+        // a 1 means to copy a byte directly from input to output
+        while src.bit()? == 1 {
+            if output.len() >= max_len {
+                return Err(malformed("UPX data unpacks to more than the limit"))
+            }
+            output.push(src.byte()?);
         }
 
-        // We pulled a 0. u_var6 is a value calculated from the instruction bits following a 0.
-        let mut u_var6: u32 = 1;
+        // otherwise it's a back-reference, starting with how far back, in a variable-length code
+        let mut offset: u32 = 1;
         loop {
-            // Pull a bit and push it into u_var6
-            pull_new_bit(&mut mask_buffer, &mut next_bit_buffer, data)?;
-            u_var6 <<= 1;
-            u_var6 |= next_bit_buffer as u32;
-
-            // Next bit is an instruction bit. If it's 1, it means stop reading.
-            pull_new_bit(&mut mask_buffer, &mut next_bit_buffer, data)?;
-            if next_bit_buffer {
+            offset = offset.wrapping_mul(2).wrapping_add(src.bit()?);
+            if src.bit()? == 1 {
                 break
             }
-            // Otherwise, it means pull another bit and push it into u_var6
-            pull_new_bit(&mut mask_buffer, &mut next_bit_buffer, data)?;
-            u_var6 -= 1; // Decrements here, not sure why.
-            u_var6 <<= 1;
-            u_var6 |= next_bit_buffer as u32;
-        }
-
-        // The minimum possible value of u_var6 is 2, since it starts at 1, is immediately shifted, then
-        // has a bit added to it. I guess this check is for whether that's the case (and the bit was 0)?
-        if u_var6 < 3 {
-            // Just grabs a new instruction-bit normally.
-            pull_new_bit(&mut mask_buffer, &mut next_bit_buffer, data)?;
+            if let Method::Nrv2d(_) | Method::Nrv2e(_) = method {
+                offset = offset.wrapping_sub(1).wrapping_mul(2).wrapping_add(src.bit()?);
+            }
+        }
+
+        // 2 means the same offset as last time, anything else has its low byte next
+        let mut len: u32 = 0;
+        if offset == 2 {
+            offset = last_offset;
+            if let Method::Nrv2d(_) | Method::Nrv2e(_) = method {
+                len = src.bit()?;
+            }
         } else {
-            // This is weird because it copies a byte into AL then xors all of EAX, which has a dead value left in it.
-            u_var12 = ((((u_var6 - 3) << 8) & 0xFFFFFF00) + (data.read_u8()? as u32 & 0xFF)) ^ 0xFFFFFFFF;
-            if u_var12 == 0 {
-                break // This is the only exit point
+            offset = offset.wrapping_sub(3).wrapping_mul(256).wrapping_add(u32::from(src.byte()?));
+            if offset == u32::MAX {
+                return Ok(()) // this is the only exit point
+            }
+            if let Method::Nrv2d(_) | Method::Nrv2e(_) = method {
+                // the lowest bit is the first bit of the length
+                len = (offset ^ u32::MAX) & 1;
+                offset >>= 1;
             }
-            // Next instruction bit is pulled from the byte we read above, then shifted out of that byte
-            next_bit_buffer = (u_var12 & 1) != 0;
-            u_var12 = ((u_var12 as i32) >> 1) as u32;
-        }
-
-        // next, we're going to calculate the number of bytes to copy from somewhere else in the output vec.
-        let mut byte_count: u32 = 0;
-        let mut do_push_bit: bool = true;
-        if !next_bit_buffer {
-            // Instruction to start byte_count at 1, then pull bits into it.
-            byte_count = 1;
-            pull_new_bit(&mut mask_buffer, &mut next_bit_buffer, data)?;
-            if !next_bit_buffer {
-                // Loop pulling bits into byte_count
-                loop {
-                    // Pull bit, push it into byte_count
-                    pull_new_bit(&mut mask_buffer, &mut next_bit_buffer, data)?;
-                    byte_count <<= 1;
-                    byte_count += next_bit_buffer as u32;
-                    // Instruction bit - 1 means stop
-                    pull_new_bit(&mut mask_buffer, &mut next_bit_buffer, data)?;
-                    if next_bit_buffer {
-                        break
-                    }
+            offset = offset.wrapping_add(1);
+            last_offset = offset;
+        }
+
+        // then how many bytes to copy, also variable-length
+        fn read_long_len(src: &mut NrvReader, mut len: u32) -> io::Result<u32> {
+            loop {
+                len = len.wrapping_mul(2).wrapping_add(src.bit()?);
+                if src.bit()? == 1 {
+                    break Ok(len)
                 }
-                // Add 2 to the byte count for some reason?
-                byte_count += 2;
-                do_push_bit = false;
             }
         }
-        if do_push_bit {
-            // We didn't do the loop above, so instead we just pull one bit into byte_count
-            pull_new_bit(&mut mask_buffer, &mut next_bit_buffer, data)?;
-            byte_count <<= 1;
-            byte_count += next_bit_buffer as u32;
+        if let Method::Nrv2b(_) = method {
+            len = src.bit()?;
         }
+        let far = match method {
+            Method::Nrv2b(_) => offset > 0xD00,
+            _ => offset > 0x500,
+        };
+        len = match method {
+            Method::Nrv2e(_) if len == 1 => 1 + src.bit()?,
+            Method::Nrv2e(_) if src.bit()? == 1 => 3 + src.bit()?,
+            Method::Nrv2e(_) => read_long_len(&mut src, 1)?.wrapping_add(3),
+            _ => match len.wrapping_mul(2).wrapping_add(src.bit()?) {
+                0 => read_long_len(&mut src, 1)?.wrapping_add(2),
+                len => len,
+            },
+        };
+        // it's one more than that, because there's no point copying just one byte
+        let count = len.wrapping_add(u32::from(far)).wrapping_add(1) as usize;
 
-        // Again, add 2 to the byte count for some reason.
-        byte_count += 2;
-        if u_var12 < 0xfffffb00 {
-            // Add another 1 only if our cursor is more than 1280 bytes behind the head. Not sure why.
-            byte_count += 1;
+        if offset as usize > output.len() - start {
+            return Err(malformed("UPX data refers to bytes it hasn't unpacked"))
         }
+        if output.len().saturating_add(count) > max_len {
+            return Err(malformed("UPX data unpacks to more than the limit"))
+        }
+        // The bytes copied can overlap the ones being written, so it has to go one at a time.
+        let cursor = output.len() - offset as usize;
+        for i in cursor..cursor + count {
+            output.push(output[i]);
+        }
+    }
+}
 
-        // Cursor into the output vector. We're going to read some bytes from here and push them again.
-        let mut cursor = (output.len() as u32).wrapping_add(u_var12) as usize;
-        // Do the byte-copying.
-        for _ in 0..byte_count {
-            output.push(output[cursor]);
-            cursor += 1;
+/// Decompresses UPX's LZMA data onto the end of `output`, which unpacks to exactly `len` bytes.
+fn lzma(data: &[u8], output: &mut Vec<u8>, len: u32) -> io::Result<()> {
+    use lzma_rs::decompress::raw::{LzmaDecoder, LzmaParams, LzmaProperties};
+
+    // UPX's own two byte header, instead of the usual LZMA one
+    let (header, data) = match data {
+        [a, b, data @ ..] => ((*a, *b), data),
+        _ => return Err(malformed("UPX's LZMA data is missing its header")),
+    };
+    let (lc, lp, pb) = (u32::from(header.1 & 15), u32::from(header.1 >> 4), u32::from(header.0 & 7));
+    // the first byte also has lc + lp, which UPX's own decompressor uses to size its tables
+    if lc > 8 || lp > 4 || pb > 4 || u32::from(header.0 >> 3) != lc + lp {
+        return Err(malformed("UPX's LZMA data has invalid properties"))
+    }
+    let properties = LzmaProperties { lc, lp, pb };
+    // it's all unpacked into one buffer, so anything already unpacked can be referred to
+    let params = LzmaParams::new(properties, len.max(4096), Some(u64::from(len)));
+    let mut decoder = LzmaDecoder::new(params, None).map_err(|err| malformed(&format!("{:?}", err)))?;
+    let mut data = data;
+    decoder.decompress(&mut data, output).map_err(|err| match err {
+        lzma_rs::error::Error::IoError(err) | lzma_rs::error::Error::HeaderTooShort(err) => err,
+        err => malformed(&format!("UPX's LZMA data is malformed: {:?}", err)),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Makes a packheader, with its checksum.
+    fn packheader(method: u8, u_adler: u32, c_adler: u32, u_len: u32, c_len: u32, filter: u8, cto: u8) -> Vec<u8> {
+        let mut header = b"UPX!".to_vec();
+        header.extend_from_slice(&[13, 9, method, 8]);
+        for n in [u_adler, c_adler, u_len, c_len, u_len] {
+            header.extend_from_slice(&n.to_le_bytes());
+        }
+        header.extend_from_slice(&[filter, cto, 0]);
+        header.push((header[4..].iter().map(|&b| u32::from(b)).sum::<u32>() % 251) as u8);
+        header
+    }
+
+    /// Compresses `data` as NRV2B with a 32-bit bit buffer, without any back-references.
+    fn nrv2b_literals(data: &[u8]) -> Vec<u8> {
+        let mut out = Vec::new();
+        let (mut word_at, mut used) = (0, 32);
+        let mut bit = |out: &mut Vec<u8>, bit: u32| {
+            if used == 32 {
+                word_at = out.len();
+                out.extend_from_slice(&[0; 4]);
+                used = 0;
+            }
+            used += 1;
+            let word = u32::from_le_bytes(out[word_at..word_at + 4].try_into().unwrap()) | bit << (32 - used);
+            out[word_at..word_at + 4].copy_from_slice(&word.to_le_bytes());
+        };
+        for &byte in data {
+            bit(&mut out, 1);
+            out.push(byte);
+        }
+        // the end is a back-reference 0xFFFFFFFF bytes back, with its offset in the same code as lengths
+        bit(&mut out, 0);
+        let code = 0x1000002u32;
+        for shift in (0..24).rev() {
+            bit(&mut out, (code >> shift) & 1);
+            bit(&mut out, u32::from(shift == 0));
         }
+        out.push(0xFF);
+        out
+    }
 
-        // Finally, pull a new instruction bit and start the loop again.
-        pull_new_bit(&mut mask_buffer, &mut next_bit_buffer, data)?;
+    /// Filters `code` the way UPX does with a cto filter, the opposite of [`Filter::undo`].
+    fn cto_filter(filter: Filter, code: &mut [u8], offset: u32, cto: u8) {
+        let mut last = 0;
+        let mut pos = 0;
+        while pos + 5 < code.len() {
+            let opcode = code[pos];
+            let is_jcc = filter.jcc && pos != last && code[pos - 1] == 0x0F && (0x80..=0x8F).contains(&opcode);
+            if (filter.calls && opcode == 0xE8) || (filter.jumps && opcode == 0xE9) || is_jcc {
+                let relative = u32::from_le_bytes(code[pos + 1..pos + 5].try_into().unwrap());
+                let target = relative.wrapping_add(pos as u32 + 1);
+                if (target as usize) < code.len() {
+                    let value = (target + offset) | u32::from(cto) << 24;
+                    code[pos + 1..pos + 5].copy_from_slice(&value.to_be_bytes());
+                    pos += 5;
+                    last = pos;
+                    continue
+                }
+            }
+            pos += 1;
+        }
     }
 
-    Ok(output)
+    fn unpack_exe(exe: &mut [u8], max_size: u32) -> Result<Vec<u8>, ReaderError> {
+        unpack(&mut io::Cursor::new(exe), max_size, 0x200, usize::MAX, None::<fn(ReaderEvent)>)
+    }
+
+    /// Puts a packheader and compressed data where UPX would: the packheader before the first section, and the
+    /// data a little way into it.
+    fn packed_exe(header: &[u8], compressed: &[u8]) -> Vec<u8> {
+        let mut exe = vec![0u8; 0x200];
+        exe[0x100..0x100 + header.len()].copy_from_slice(header);
+        exe.extend_from_slice(b"3.96\0UPX!\0");
+        exe.extend_from_slice(compressed);
+        exe
+    }
+
+    #[test]
+    fn adler32_matches_zlib() {
+        assert_eq!(adler32(b""), 1);
+        assert_eq!(adler32(b"Wikipedia"), 0x11E6_0398);
+        // long enough to need reducing partway, which is where it could overflow
+        let data = (0..20000u32).map(|n| (n * 7 + n / 13) as u8).collect::<Vec<_>>();
+        let (mut a, mut b) = (1u32, 0u32);
+        for &byte in &data {
+            a = (a + u32::from(byte)) % 65521;
+            b = (b + a) % 65521;
+        }
+        assert_eq!(adler32(&data), b << 16 | a);
+    }
+
+    #[test]
+    fn find_by_adler_slides() {
+        let data = (0..1000u32).map(|n| (n.wrapping_mul(0x9E37_79B9) >> 24) as u8).collect::<Vec<_>>();
+        for start in [0, 1, 77, 200] {
+            let adler = adler32(&data[start..start + 600]);
+            assert_eq!(find_by_adler(&data, 600, adler, 200), Some(start));
+        }
+        let adler = adler32(&data[300..900]);
+        assert_eq!(find_by_adler(&data, 600, adler, 200), None);
+        assert_eq!(find_by_adler(&data, 2000, 1, 200), None);
+    }
+
+    #[test]
+    fn packheader_is_found() {
+        let mut data = vec![0xAA; 64];
+        // one with a broken checksum first, which has to be skipped
+        let mut broken = packheader(8, 1, 2, 3, 4, 0x26, 0x5A);
+        broken[31] ^= 1;
+        data.extend_from_slice(&broken);
+        data.extend_from_slice(&packheader(14, 1, 2, 3, 4, 0x49, 0x5A));
+        let header = PackHeader::find(&data).unwrap().unwrap();
+        assert_eq!(header.method, Method::Lzma);
+        assert_eq!((header.u_adler, header.c_adler, header.u_len, header.c_len), (1, 2, 3, 4));
+        assert_eq!((header.filter, header.filter_cto), (0x49, 0x5A));
+
+        assert!(PackHeader::find(&data[..64]).unwrap().is_none());
+        assert!(PackHeader::find(&packheader(1, 1, 2, 3, 4, 0, 0)).is_err());
+    }
+
+    #[test]
+    fn nrv_fixtures() {
+        let cases: [(Method, &[u8], &[u8]); 5] = [
+            (
+                Method::Nrv2b(BitBuffer::Le32),
+                &[
+                    0xD8, 0xF7, 0xF7, 0xDE, 0x78, 0xCA, 0x01, 0x01, 0x00, 0x9E, 0xBE, 0xAC, 0x04, 0x95, 0x06, 0x00,
+                    0x24, 0xDB, 0x42, 0x10, 0x8F, 0x03, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x12, 0xFF,
+                ],
+                &[
+                    0x78, 0xCA, 0x78, 0xCA, 0x78, 0xCA, 0x78, 0xCA, 0x78, 0xCA, 0xCA, 0xCA, 0xCA, 0xCA, 0x9E, 0xBE,
+                    0xAC, 0xCA, 0xCA, 0x9E, 0xBE, 0x95, 0xBE, 0xAC, 0xCA, 0xCA, 0x9E, 0xBE, 0x95, 0xBE, 0xAC, 0xCA,
+                    0xCA, 0x9E, 0xBE, 0x95, 0xBE, 0xAC, 0xCA, 0xCA, 0x9E, 0xBE, 0x95, 0xBE, 0xAC, 0x95, 0xBE, 0x8F,
+                    0xAC, 0x95, 0xBE, 0x8F, 0xAC, 0x95, 0xBE, 0x8F, 0xAC,
+                ],
+            ),
+            (
+                Method::Nrv2d(BitBuffer::Le32),
+                &[
+                    0x57, 0xEE, 0xDD, 0xBB, 0xC5, 0x00, 0x03, 0x25, 0x0E, 0x09, 0xCC, 0x0E, 0x04, 0x24, 0x09, 0x76,
+                    0x77, 0x06, 0x05, 0x05, 0x32, 0x00, 0x2A, 0x49, 0x92, 0xFF,
+                ],
+                NRV2D_OUTPUT,
+            ),
+            (
+                Method::Nrv2d(BitBuffer::Le16),
+                &[
+                    0xDD, 0xBB, 0xC5, 0x00, 0x03, 0x25, 0x0E, 0x57, 0xEE, 0x09, 0xCC, 0x0E, 0x04, 0x76, 0x77, 0x06,
+                    0x05, 0x05, 0x32, 0x24, 0x09, 0x49, 0x92, 0x00, 0x2A, 0xFF,
+                ],
+                NRV2D_OUTPUT,
+            ),
+            (
+                Method::Nrv2e(BitBuffer::Le32),
+                &[
+                    0xD9, 0xEC, 0x39, 0xBB, 0xC5, 0x00, 0x02, 0x05, 0x0E, 0x68, 0x1B, 0xCC, 0x0E, 0x84, 0x30, 0x6B,
+                    0x8B, 0x07, 0x0A, 0x95, 0x08, 0x05, 0x95, 0x24, 0x49, 0x92, 0xFF,
+                ],
+                NRV2E_OUTPUT,
+            ),
+            (
+                Method::Nrv2e(BitBuffer::Byte),
+                &[
+                    0xBB, 0xC5, 0x00, 0x02, 0x39, 0x05, 0xEC, 0x0E, 0x68, 0x1B, 0xD9, 0xCC, 0x0E, 0x8B, 0x07, 0x6B,
+                    0x0A, 0x95, 0x08, 0x30, 0x05, 0x84, 0x92, 0x49, 0x24, 0x95, 0xFF,
+                ],
+                NRV2E_OUTPUT,
+            ),
+        ];
+        for (method, compressed, expected) in cases {
+            let mut output = vec![0u8; 3];
+            nrv(method, compressed, &mut output, usize::MAX).unwrap();
+            assert_eq!(&output[3..], expected, "{:?}", method);
+            // it stops at the limit, rather than writing past it
+            assert!(nrv(method, compressed, &mut vec![0u8; 3], expected.len()).is_err());
+        }
+    }
+
+    const NRV2D_OUTPUT: &[u8] = &[
+        0xC5, 0xC5, 0xC5, 0xC5, 0xC5, 0xC5, 0xC5, 0x25, 0xC5, 0xC5, 0xC5, 0xC5, 0x25, 0xC5, 0xCC, 0x25, 0xC5, 0xC5,
+        0xC5, 0xC5, 0x25, 0xC5, 0xC5, 0x25, 0xC5, 0xC5, 0xC5, 0x25, 0xC5, 0xC5, 0x25, 0xC5, 0xC5, 0x25, 0xC5, 0xC5,
+    ];
+
+    const NRV2E_OUTPUT: &[u8] = &[
+        0xC5, 0xC5, 0xC5, 0xC5, 0xC5, 0xC5, 0xC5, 0xC5, 0xC5, 0xC5, 0xC5, 0xC5, 0xC5, 0x68, 0xC5, 0xC5, 0xC5, 0xC5,
+        0xC5, 0xC5, 0xCC, 0x68, 0xC5, 0xC5, 0xCC, 0x68, 0xC5, 0xC5, 0xCC, 0x68, 0xC5, 0xC5, 0xCC, 0x68, 0xC5, 0xC5,
+        0xCC, 0x68, 0xCC, 0x68, 0x95, 0xCC, 0x68, 0x95, 0xCC, 0x68, 0x95, 0xCC, 0x68, 0x95, 0xCC,
+    ];
+
+    #[test]
+    fn lzma_fixture() {
+        let compressed = [
+            0x1A, 0x03, 0x00, 0x23, 0x98, 0x49, 0xA6, 0x68, 0xDE, 0x9E, 0xA2, 0xFC, 0xBC, 0xFC, 0x59, 0xE8, 0xE0, 0x82,
+            0xEE, 0x0F, 0x3E, 0x0D, 0x8D, 0xC3, 0x0D, 0xFC, 0x2F, 0xFF, 0xFC, 0xF5, 0x70, 0x00,
+        ];
+        let expected = b"GameMaker GameMaker GameMaker, UPX UPX UPX";
+        let mut output = vec![0u8; 3];
+        lzma(&compressed, &mut output, expected.len() as u32).unwrap();
+        assert_eq!(&output[3..], expected);
+        assert!(lzma(&compressed[..1], &mut Vec::new(), 1).is_err());
+    }
+
+    #[test]
+    fn filters_are_undone() {
+        let ctojr = Filter::from_id(0x49).unwrap();
+        // a call and a conditional jump 16 bytes ahead, in code 0x10 bytes into the unpacked data
+        let mut code = [0x90, 0xE8, 0x5A, 0x00, 0x00, 0x22, 0x0F, 0x85, 0x5A, 0x00, 0x00, 0x28, 0x90, 0x90];
+        ctojr.undo(&mut code, 0x10, 0x5A);
+        assert_eq!(code, [0x90, 0xE8, 0x10, 0x00, 0x00, 0x00, 0x0F, 0x85, 0x10, 0x00, 0x00, 0x00, 0x90, 0x90]);
+
+        // without the marker it's left alone
+        let mut code = [0x90, 0xE8, 0x5B, 0x00, 0x00, 0x22, 0x90, 0x90];
+        Filter::from_id(0x26).unwrap().undo(&mut code, 0x10, 0x5A);
+        assert_eq!(code, [0x90, 0xE8, 0x5B, 0x00, 0x00, 0x22, 0x90, 0x90]);
+
+        let mut code = [0x90, 0xE8, 0x12, 0x00, 0x00, 0x00, 0xE9, 0x12, 0x00, 0x00, 0x00, 0x90];
+        Filter::from_id(0x11).unwrap().undo(&mut code, 0, 0);
+        assert_eq!(code, [0x90, 0xE8, 0x10, 0x00, 0x00, 0x00, 0xE9, 0x12, 0x00, 0x00, 0x00, 0x90]);
+
+        assert_eq!(Filter::from_id(0x50), None);
+        assert_eq!(Filter::from_id(0x03), None);
+    }
+
+    #[test]
+    fn unpacks_filtered_code() {
+        // code at RVA 0x1010, in a section at 0x1000, with the original headers after it
+        let mut original = vec![0xCCu8; 0x10];
+        let code = (0..0x100u32).flat_map(|n| match n % 4 {
+            0 => vec![0xE8, (n % 0x60) as u8, 0, 0, 0],
+            1 => vec![0x0F, 0x84, 0x08, 0, 0, 0],
+            2 => vec![0xE9, 0xF0, 0xFF, 0xFF, 0xFF],
+            _ => vec![0x90, 0x0F],
+        });
+        original.extend(code);
+        let code_size = original.len() as u32 - 0x10;
+        let headers = original.len();
+        let mut pe = vec![0u8; PE_HEADERS_LEN + 40];
+        pe[..4].copy_from_slice(b"PE\0\0");
+        pe[28..32].copy_from_slice(&code_size.to_le_bytes());
+        pe[44..48].copy_from_slice(&0x1010u32.to_le_bytes());
+        pe[PE_HEADERS_LEN + 12..PE_HEADERS_LEN + 16].copy_from_slice(&0x1000u32.to_le_bytes());
+        original.extend(pe);
+        original.extend((headers as u32).to_le_bytes());
+
+        let mut filtered = original.clone();
+        let filter = Filter::from_id(0x49).unwrap();
+        cto_filter(filter, &mut filtered[0x10..0x10 + code_size as usize], 0x10, 0x77);
+        assert_ne!(filtered, original);
+
+        let compressed = nrv2b_literals(&filtered);
+        let u_len = original.len() as u32;
+        for adler in [adler32(&original), adler32(&filtered)] {
+            let header = packheader(2, adler, adler32(&compressed), u_len, compressed.len() as u32, 0x49, 0x77);
+            let output = unpack_exe(&mut packed_exe(&header, &compressed), u_len).unwrap();
+            assert_eq!(&output[HEADERS_LEN..], &original[..]);
+        }
+
+        let header = packheader(2, 1, adler32(&compressed), u_len, compressed.len() as u32, 0x49, 0x77);
+        assert!(unpack_exe(&mut packed_exe(&header, &compressed), u_len).is_err());
+        let header = packheader(2, adler32(&filtered), adler32(&compressed), u_len, compressed.len() as u32, 0x50, 0);
+        assert!(unpack_exe(&mut packed_exe(&header, &compressed), u_len).is_err());
+    }
+
+    #[test]
+    fn unpacks_unfiltered() {
+        let original = b"no headers needed without a filter".repeat(3);
+        let compressed = nrv2b_literals(&original);
+        let (u_len, c_len) = (original.len() as u32, compressed.len() as u32);
+        let header = packheader(2, adler32(&original), adler32(&compressed), u_len, c_len, 0, 0);
+        let output = unpack_exe(&mut packed_exe(&header, &compressed), u_len).unwrap();
+        assert_eq!(&output[..HEADERS_LEN], &[0u8; HEADERS_LEN][..]);
+        assert_eq!(&output[HEADERS_LEN..], &original[..]);
+
+        // a packheader that doesn't match the data means some of it's missing
+        let header = packheader(2, adler32(&original), 1, u_len, c_len, 0, 0);
+        let result = unpack_exe(&mut packed_exe(&header, &compressed), u_len);
+        assert!(matches!(result, Err(ReaderError::PartialUPXPacking)));
+    }
 }
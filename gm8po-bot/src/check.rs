@@ -0,0 +1,185 @@
+//! Walks a few bots through the broadcasts `World_EndStep.gml` depends on.
+
+use crate::{Bot, Server};
+use gm8po_online::protocol::{SavePosition, ServerMessage};
+use std::{
+    io, thread,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// How long to wait for something that should arrive.
+const EXPECT_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// How long to wait before deciding that something which shouldn't arrive didn't.
+const SILENCE_TIMEOUT: Duration = Duration::from_millis(300);
+
+fn io_error(bot: &Bot, err: io::Error) -> String {
+    format!("{}: {}", bot.name, err)
+}
+
+fn expect(bot: &mut Bot, expected: &ServerMessage) -> Result<(), String> {
+    match bot.receive(EXPECT_TIMEOUT).map_err(|err| io_error(bot, err))? {
+        Some(message) if &message == expected => Ok(()),
+        Some(message) => Err(format!("{}: expected {:?}, got {:?}", bot.name, expected, message)),
+        None => Err(format!("{}: expected {:?}, got nothing", bot.name, expected)),
+    }
+}
+
+/// Like [`expect`], for messages whose exact contents (such as race times) can't be known up front.
+fn expect_matching(bot: &mut Bot, what: &str, matches: impl Fn(&ServerMessage) -> bool) -> Result<(), String> {
+    match bot.receive(EXPECT_TIMEOUT).map_err(|err| io_error(bot, err))? {
+        Some(message) if matches(&message) => Ok(()),
+        Some(message) => Err(format!("{}: expected {}, got {:?}", bot.name, what, message)),
+        None => Err(format!("{}: expected {}, got nothing", bot.name, what)),
+    }
+}
+
+fn expect_silence(bot: &mut Bot) -> Result<(), String> {
+    match bot.receive(SILENCE_TIMEOUT).map_err(|err| io_error(bot, err))? {
+        Some(message) => Err(format!("{}: expected nothing, got {:?}", bot.name, message)),
+        None => Ok(()),
+    }
+}
+
+/// Like [`expect_silence`], but counting what a race mode bot would otherwise ignore, to check the server sent
+/// nothing at all.
+fn expect_raw_silence(bot: &mut Bot) -> Result<(), String> {
+    match bot.receive_raw(SILENCE_TIMEOUT).map_err(|err| io_error(bot, err))? {
+        Some(message) => Err(format!("{}: expected nothing, got {:?}", bot.name, message)),
+        None => Ok(()),
+    }
+}
+
+fn expect_moved(bot: &Bot, from: &Bot, x: i32) -> Result<(), String> {
+    // the other bots' first packets, and earlier ones from the same sender, may still be in flight
+    while let Some(moved) = bot.receive_moved(EXPECT_TIMEOUT).map_err(|err| io_error(bot, err))? {
+        if moved.id == from.id && moved.x == x {
+            return Ok(())
+        }
+    }
+    Err(format!("{}: expected movement from {}, got nothing", bot.name, from.id))
+}
+
+/// Runs the whole check in a fresh lobby, calling `log` after each step that passed.
+pub fn run(server: &Server, log: impl Fn(&str)) -> Result<(), String> {
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_nanos()).unwrap_or(0);
+    let game_id = format!("gm8po-bot check {}", nanos);
+    let join = |name: &str, race| Bot::join(server, name, &game_id, race).map_err(|err| format!("{}: {}", name, err));
+    let created = |bot: &Bot| ServerMessage::PlayerCreated { id: bot.id.clone(), name: bot.name.clone() };
+
+    let mut a = join("alice", false)?;
+    a.create().map_err(|err| io_error(&a, err))?;
+    let mut b = join("bob", false)?;
+    let mut c = join("carol", true)?;
+    if a.id == b.id || b.id == c.id || a.id == c.id {
+        return Err(format!("IDs aren't unique: {}, {}, {}", a.id, b.id, c.id))
+    }
+    expect(&mut b, &created(&a))?;
+    expect(&mut c, &created(&a))?;
+    log("joined, late joiners were told about existing players");
+
+    b.create().map_err(|err| io_error(&b, err))?;
+    expect(&mut a, &created(&b))?;
+    expect(&mut c, &created(&b))?;
+    c.create().map_err(|err| io_error(&c, err))?;
+    expect(&mut a, &created(&c))?;
+    expect(&mut b, &created(&c))?;
+    expect_silence(&mut c)?;
+    log("player creation was broadcast to everyone else");
+
+    a.chat("hello").map_err(|err| io_error(&a, err))?;
+    let chat = ServerMessage::Chat { id: a.id.clone(), message: "hello".into() };
+    expect(&mut b, &chat)?;
+    expect(&mut c, &chat)?;
+    expect_silence(&mut a)?;
+    log("chat was fanned out to everyone but the sender");
+
+    // The server only learns where to relay to from each bot's own movement.
+    for bot in [&a, &b, &c] {
+        bot.moved(1, 1, 0, 0).map_err(|err| io_error(bot, err))?;
+    }
+    thread::sleep(SILENCE_TIMEOUT);
+    a.moved(1, 2, 1, 0).map_err(|err| io_error(&a, err))?;
+    expect_moved(&b, &a, 1)?;
+    expect_moved(&c, &a, 1)?;
+    log("movement was relayed to everyone else");
+
+    c.race_start(1).map_err(|err| io_error(&c, err))?;
+    expect(&mut c, &ServerMessage::Leaderboard(Vec::new()))?;
+    let running = |m: &ServerMessage| matches!(m, ServerMessage::RaceRanking(r) if r.len() == 1 && !r[0].finished);
+    for bot in [&mut a, &mut b, &mut c] {
+        expect_matching(bot, "a ranking with carol running", running)?;
+    }
+    c.race_split(2).map_err(|err| io_error(&c, err))?;
+    let split = |m: &ServerMessage| matches!(m, ServerMessage::RaceRanking(r) if r.len() == 1 && r[0].room == 2);
+    for bot in [&mut a, &mut b, &mut c] {
+        expect_matching(bot, "a ranking with carol in room 2", split)?;
+    }
+    log("race start and splits were ranked for the whole lobby");
+
+    let position = SavePosition { gravity: 0, x: 416, y: 311.5, room: 4 };
+    a.save(position.clone()).map_err(|err| io_error(&a, err))?;
+    expect(&mut b, &ServerMessage::Saved { name: a.name.clone(), position: position.clone() })?;
+    expect_raw_silence(&mut c)?;
+    log("saves were shared, but not with racers");
+
+    // a patched game doesn't send saves while racing, the server shouldn't pass them on if one does
+    c.save_raw(position).map_err(|err| io_error(&c, err))?;
+    expect_silence(&mut a)?;
+    expect_silence(&mut b)?;
+    log("saves from racers weren't shared");
+
+    c.race_finish().map_err(|err| io_error(&c, err))?;
+    let finished = |m: &ServerMessage| matches!(m, ServerMessage::RaceRanking(r) if r.len() == 1 && r[0].finished);
+    let leaderboard = |m: &ServerMessage| matches!(m, ServerMessage::Leaderboard(l) if l.len() == 1);
+    for bot in [&mut a, &mut b, &mut c] {
+        expect_matching(bot, "a ranking with carol finished", finished)?;
+        expect_matching(bot, "a leaderboard with carol's time", leaderboard)?;
+    }
+    log("the finish was timed and made it onto the leaderboard");
+
+    b.destroy().map_err(|err| io_error(&b, err))?;
+    let destroyed = ServerMessage::PlayerDestroyed { id: b.id.clone() };
+    expect(&mut a, &destroyed)?;
+    expect(&mut c, &destroyed)?;
+    log("player destruction was broadcast");
+
+    let destroyed = ServerMessage::PlayerDestroyed { id: c.id.clone() };
+    drop(c);
+    expect(&mut a, &destroyed)?;
+    expect(&mut b, &destroyed)?;
+    log("leaving was broadcast as destruction");
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gm8po_online::{crypto::DEFAULT_NETWORK_KEY, protocol::OLDEST_COMPATIBLE_VERSION};
+    use gm8po_server::server::{self, Config};
+
+    #[test]
+    fn passes_against_the_server() {
+        let server = server::Server::bind(Config {
+            tcp_addr: "127.0.0.1:0".parse().unwrap(),
+            udp_addr: "127.0.0.1:0".parse().unwrap(),
+            oldest_version: OLDEST_COMPATIBLE_VERSION.into(),
+            timeout: Duration::from_secs(60),
+            results: None,
+            network_key: DEFAULT_NETWORK_KEY.into(),
+            max_players: None,
+            saves: None,
+            stats: None,
+            bans: None,
+        })
+        .unwrap();
+        let target = Server {
+            tcp: server.tcp_addr().unwrap(),
+            udp: server.udp_addr().unwrap(),
+            network_key: DEFAULT_NETWORK_KEY.into(),
+        };
+        thread::spawn(move || server.run());
+        assert_eq!(run(&target, |_| ()), Ok(()));
+    }
+}
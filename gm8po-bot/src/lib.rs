@@ -0,0 +1,337 @@
+//! A headless stand-in for a patched game, for testing servers without GameMaker.
+//!
+//! [`Bot`] speaks the protocol the same way `World_Create.gml` and `World_EndStep.gml` do,
+//! [`check`] walks a few bots through the broadcasts a game relies on and [`load`] floods a lobby with movement.
+
+pub mod check;
+pub mod load;
+
+use gm8po_online::{
+    crypto::{self, Direction, Session, UdpHeader},
+    hbuffer::{self, MessageReader},
+    protocol::{ClientMessage, Join, Moved, SavePosition, ServerMessage, UdpMessage, VERSION},
+    GmString,
+};
+use std::{
+    cell::Cell,
+    collections::VecDeque,
+    io::{self, Read},
+    net::{SocketAddr, TcpStream, ToSocketAddrs, UdpSocket},
+    time::{Duration, Instant},
+};
+
+/// How long [`Bot::join`] waits for the server to hand out an ID.
+const JOIN_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Where a server is listening, and the network key its games were patched with.
+#[derive(Clone, Debug)]
+pub struct Server {
+    pub tcp: SocketAddr,
+    pub udp: SocketAddr,
+    pub network_key: String,
+}
+
+impl Server {
+    pub fn resolve(host: &str, tcp_port: u16, udp_port: u16, network_key: &str) -> io::Result<Self> {
+        let resolve = |port| {
+            (host, port)
+                .to_socket_addrs()?
+                .next()
+                .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("couldn't resolve {}", host)))
+        };
+        Ok(Server { tcp: resolve(tcp_port)?, udp: resolve(udp_port)?, network_key: network_key.into() })
+    }
+}
+
+/// A single fake player.
+pub struct Bot {
+    pub id: GmString,
+    pub name: GmString,
+    pub game_id: GmString,
+
+    /// Whether this bot pretends to have answered "yes" to the race mode prompt,
+    /// which means it neither shares its saves nor accepts anyone else's.
+    pub race: bool,
+
+    tcp: TcpStream,
+    reader: MessageReader,
+    queue: VecDeque<ServerMessage>,
+    udp: UdpSocket,
+
+    /// Only `None` during the key exchange.
+    session: Option<Session>,
+    tcp_send_seq: u32,
+    tcp_recv_seq: u32,
+    udp_send_seq: Cell<u32>,
+    udp_recv_seq: Cell<u32>,
+}
+
+impl Bot {
+    /// Connects, exchanges keys and joins a lobby, returning once the server has sent this bot's ID.
+    pub fn join(server: &Server, name: &str, game_id: &str, race: bool) -> io::Result<Self> {
+        let tcp = TcpStream::connect(server.tcp)?;
+        tcp.set_nodelay(true)?;
+        let local: SocketAddr = if server.udp.is_ipv4() { ([0, 0, 0, 0], 0).into() } else { ([0u16; 8], 0).into() };
+        let udp = UdpSocket::bind(local)?;
+        udp.connect(server.udp)?;
+
+        let mut bot = Bot {
+            id: GmString::default(),
+            name: name.into(),
+            game_id: game_id.into(),
+            race,
+            tcp,
+            reader: MessageReader::new(),
+            queue: VecDeque::new(),
+            udp,
+            session: None,
+            tcp_send_seq: 0,
+            tcp_recv_seq: 0,
+            udp_send_seq: Cell::new(0),
+            udp_recv_seq: Cell::new(0),
+        };
+
+        let incompatible = |oldest_version| {
+            io::Error::other(format!("server requires version {} (we are {})", oldest_version, VERSION))
+        };
+        let deadline = Instant::now() + JOIN_TIMEOUT;
+        let nonce = crypto::nonce();
+        bot.send(&ClientMessage::KeyExchange { version: VERSION.into(), nonce: nonce.clone() })?;
+        match bot.receive(deadline.saturating_duration_since(Instant::now()))? {
+            Some(ServerMessage::KeyExchange { nonce: server_nonce }) => {
+                bot.session = Some(Session::new(server.network_key.as_bytes(), &nonce, &server_nonce));
+            },
+            Some(ServerMessage::IncompatibleVersion { oldest_version }) => return Err(incompatible(oldest_version)),
+            Some(message) => {
+                let message = format!("expected a key exchange, got {:?}", message);
+                return Err(io::Error::new(io::ErrorKind::InvalidData, message))
+            },
+            None => return Err(io::Error::new(io::ErrorKind::TimedOut, "server didn't answer the key exchange")),
+        }
+
+        bot.send(&ClientMessage::Join(Join {
+            name: bot.name.clone(),
+            game_id: bot.game_id.clone(),
+            game_name: "gm8po-bot".into(),
+            version: VERSION.into(),
+            has_password: false,
+        }))?;
+        bot.udp.send(&UdpMessage::Hello.encode())?;
+
+        // Anything arriving before the ID (there shouldn't be anything) is kept for later.
+        let mut early = VecDeque::new();
+        loop {
+            let timeout = deadline.saturating_duration_since(Instant::now());
+            match bot.receive(timeout)? {
+                Some(ServerMessage::SelfId(id)) => {
+                    bot.id = id;
+                    early.append(&mut bot.queue);
+                    bot.queue = early;
+                    return Ok(bot)
+                },
+                Some(ServerMessage::IncompatibleVersion { oldest_version }) => return Err(incompatible(oldest_version)),
+                Some(message) => early.push_back(message),
+                None => return Err(io::Error::new(io::ErrorKind::TimedOut, "server didn't assign an ID")),
+            }
+        }
+    }
+
+    fn send(&mut self, message: &ClientMessage) -> io::Result<()> {
+        let mut data = message.encode();
+        if let Some(session) = &self.session {
+            data = session.seal(Direction::ToServer, self.tcp_send_seq, &[], &data);
+            self.tcp_send_seq = self.tcp_send_seq.wrapping_add(1);
+        }
+        hbuffer::write_message(&mut self.tcp, &data)
+    }
+
+    /// Tells the lobby the player object now exists.
+    pub fn create(&mut self) -> io::Result<()> {
+        self.send(&ClientMessage::PlayerCreated)
+    }
+
+    /// Tells the lobby the player object is gone.
+    pub fn destroy(&mut self) -> io::Result<()> {
+        self.send(&ClientMessage::PlayerDestroyed)
+    }
+
+    pub fn heartbeat(&mut self) -> io::Result<()> {
+        self.send(&ClientMessage::Heartbeat)
+    }
+
+    pub fn chat(&mut self, message: &str) -> io::Result<()> {
+        self.send(&ClientMessage::Chat(message.into()))
+    }
+
+    /// Shares a save, unless in race mode where the save scripts don't send anything.
+    /// Returns whether a message was sent.
+    pub fn save(&mut self, position: SavePosition) -> io::Result<bool> {
+        if self.race {
+            return Ok(false)
+        }
+        self.save_raw(position)?;
+        Ok(true)
+    }
+
+    /// Shares a save even in race mode, which a patched game never does, to see what the server makes of it.
+    pub fn save_raw(&mut self, position: SavePosition) -> io::Result<()> {
+        self.send(&ClientMessage::Saved(position))
+    }
+
+    /// Starts this bot's race clock on the server, like entering the first room after the title screen.
+    pub fn race_start(&mut self, room: u16) -> io::Result<()> {
+        self.send(&ClientMessage::RaceStart { room })
+    }
+
+    /// Reports reaching another room during a race.
+    pub fn race_split(&mut self, room: u16) -> io::Result<()> {
+        self.send(&ClientMessage::RaceSplit { room })
+    }
+
+    /// Reports reaching the finish room.
+    pub fn race_finish(&mut self) -> io::Result<()> {
+        self.send(&ClientMessage::RaceFinish)
+    }
+
+    /// Sends a movement packet. Only `room`, `time`, `x` and `y` vary, the rest looks like a standing kid.
+    pub fn moved(&self, room: u16, time: u64, x: i32, y: i32) -> io::Result<()> {
+        let moved = Moved {
+            id: self.id.clone(),
+            game_id: self.game_id.clone(),
+            room,
+            time,
+            x,
+            y,
+            sprite_index: 0,
+            image_speed: 0.2,
+            image_xscale: 1.0,
+            image_yscale: 1.0,
+            image_angle: 0.0,
+            name: self.name.clone(),
+            form: 0,
+        };
+        let session = self.session.as_ref().expect("bots only exist once keys are exchanged");
+        let seq = self.udp_send_seq.replace(self.udp_send_seq.get().wrapping_add(1));
+        self.udp.send(&session.seal_udp(Direction::ToServer, seq, Some(&self.id), &UdpMessage::Moved(moved).encode()))?;
+        Ok(())
+    }
+
+    /// Waits up to `timeout` for a TCP message. Saves are dropped in race mode, like `World_EndStep.gml` does.
+    pub fn receive(&mut self, timeout: Duration) -> io::Result<Option<ServerMessage>> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            match self.receive_raw(deadline.saturating_duration_since(Instant::now()))? {
+                Some(ServerMessage::Saved { .. }) if self.race => continue,
+                message => return Ok(message),
+            }
+        }
+    }
+
+    /// Waits up to `timeout` for a TCP message, keeping everything the server sent even in race mode.
+    pub fn receive_raw(&mut self, timeout: Duration) -> io::Result<Option<ServerMessage>> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            if let Some(message) = self.queue.pop_front() {
+                return Ok(Some(message))
+            }
+            while let Some(mut data) = self.reader.next_message()? {
+                if let Some(session) = &self.session {
+                    data = session
+                        .open(Direction::ToClient, self.tcp_recv_seq, &data, 0)
+                        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+                    self.tcp_recv_seq = self.tcp_recv_seq.wrapping_add(1);
+                }
+                let message =
+                    ServerMessage::decode(&data).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+                self.queue.push_back(message);
+            }
+            if !self.queue.is_empty() {
+                continue
+            }
+
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Ok(None)
+            }
+            self.tcp.set_read_timeout(Some(remaining))?;
+            let mut buf = [0u8; 4096];
+            match self.tcp.read(&mut buf) {
+                Ok(0) => return Err(io::Error::new(io::ErrorKind::ConnectionAborted, "connection closed")),
+                Ok(len) => self.reader.push(&buf[..len]),
+                Err(err) if matches!(err.kind(), io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut) => {
+                    return Ok(None)
+                },
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    /// Waits up to `timeout` for someone else's movement.
+    pub fn receive_moved(&self, timeout: Duration) -> io::Result<Option<Moved>> {
+        let session = self.session.as_ref().expect("bots only exist once keys are exchanged");
+        receive_moved(&self.udp, session, &self.udp_recv_seq, timeout)
+    }
+
+    /// A handle for receiving movement on another thread.
+    pub fn moved_receiver(&self) -> io::Result<MovedReceiver> {
+        Ok(MovedReceiver {
+            udp: self.udp.try_clone()?,
+            session: self.session.clone().expect("bots only exist once keys are exchanged"),
+            next_seq: Cell::new(self.udp_recv_seq.get()),
+        })
+    }
+}
+
+pub struct MovedReceiver {
+    udp: UdpSocket,
+    session: Session,
+    next_seq: Cell<u32>,
+}
+
+impl MovedReceiver {
+    pub fn receive(&self, timeout: Duration) -> io::Result<Option<Moved>> {
+        receive_moved(&self.udp, &self.session, &self.next_seq, timeout)
+    }
+}
+
+/// Packets older than `next_seq` are dropped like the game drops them, they can only be replays or late.
+fn receive_moved(
+    udp: &UdpSocket,
+    session: &Session,
+    next_seq: &Cell<u32>,
+    timeout: Duration,
+) -> io::Result<Option<Moved>> {
+    let deadline = Instant::now() + timeout;
+    let mut buf = vec![0u8; 0x10000];
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Ok(None)
+        }
+        udp.set_read_timeout(Some(remaining))?;
+        let len = match udp.recv(&mut buf) {
+            Ok(len) => len,
+            Err(err) if matches!(err.kind(), io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut) => return Ok(None),
+            // an ICMP error from an earlier send, the game would give up here but we can keep listening
+            Err(err) if err.kind() == io::ErrorKind::ConnectionRefused => continue,
+            Err(err) => return Err(err),
+        };
+        let packet = &buf[..len];
+        let header = UdpHeader::read(packet, false)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "received an unsealed UDP packet"))?;
+        if header.seq < next_seq.get() {
+            continue
+        }
+        let payload = session
+            .open(Direction::ToClient, header.seq, packet, header.len)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        next_seq.set(header.seq.wrapping_add(1));
+        match UdpMessage::decode(&payload) {
+            Ok(UdpMessage::Moved(moved)) => return Ok(Some(moved)),
+            Ok(message) => {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, format!("unexpected UDP message {:?}", message)))
+            },
+            Err(err) => return Err(io::Error::new(io::ErrorKind::InvalidData, err)),
+        }
+    }
+}
@@ -0,0 +1,190 @@
+//! Fills a lobby with bots sending movement at a fixed rate and measures what comes back.
+
+use crate::{Bot, Server};
+use std::{
+    fmt::{self, Display},
+    io,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+/// Games send a heartbeat every 3 seconds, so do the same to avoid being timed out.
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(3);
+
+/// How long to keep listening after the last packet was sent.
+const GRACE_PERIOD: Duration = Duration::from_secs(1);
+
+pub struct Config {
+    pub bots: usize,
+
+    /// Movement packets per second, per bot. Games send one per frame while moving, so usually 50.
+    pub rate: f64,
+
+    pub duration: Duration,
+    pub game_id: String,
+}
+
+#[derive(Debug, Default)]
+pub struct Report {
+    /// Packets sent by all bots together.
+    pub sent: u64,
+
+    /// Packets that should have arrived, since each one is relayed to every other bot.
+    pub expected: u64,
+
+    pub received: u64,
+
+    /// Time between sending and receiving each packet, sorted.
+    pub latencies: Vec<Duration>,
+}
+
+impl Report {
+    /// Fraction of expected packets that never arrived.
+    pub fn loss(&self) -> f64 {
+        if self.expected == 0 {
+            return 0.0
+        }
+        1.0 - (self.received.min(self.expected) as f64 / self.expected as f64)
+    }
+
+    /// Latency at the given percentile (0 to 100).
+    pub fn percentile(&self, p: f64) -> Option<Duration> {
+        if self.latencies.is_empty() {
+            return None
+        }
+        let index = ((p / 100.0) * (self.latencies.len() - 1) as f64).round() as usize;
+        self.latencies.get(index.min(self.latencies.len() - 1)).copied()
+    }
+
+    pub fn mean(&self) -> Option<Duration> {
+        let total: Duration = self.latencies.iter().sum();
+        Some(total / u32::try_from(self.latencies.len()).ok().filter(|&n| n > 0)?)
+    }
+}
+
+impl Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "sent:      {}", self.sent)?;
+        writeln!(f, "received:  {} of {} ({:.2}% loss)", self.received, self.expected, self.loss() * 100.0)?;
+        match (
+            self.percentile(0.0),
+            self.mean(),
+            self.percentile(50.0),
+            self.percentile(95.0),
+            self.percentile(100.0),
+        ) {
+            (Some(min), Some(mean), Some(median), Some(p95), Some(max)) => write!(
+                f,
+                "latency:   min {:.1?}, mean {:.1?}, median {:.1?}, p95 {:.1?}, max {:.1?}",
+                min, mean, median, p95, max
+            ),
+            _ => write!(f, "latency:   no packets received"),
+        }
+    }
+}
+
+/// Runs the load test and returns once everything has been sent and the stragglers had time to arrive.
+pub fn run(server: &Server, config: &Config) -> io::Result<Report> {
+    let mut bots = (0..config.bots)
+        .map(|i| Bot::join(server, &format!("bot{}", i), &config.game_id, false))
+        .collect::<io::Result<Vec<_>>>()?;
+    for bot in &mut bots {
+        bot.create()?;
+    }
+
+    // The packets carry microseconds since `epoch` instead of `current_time`, so receivers can compute latency.
+    // Movement with x = 0 only serves to let the server learn everyone's address and isn't counted.
+    let epoch = Instant::now();
+    let micros = || epoch.elapsed().as_micros() as u64;
+    for bot in &bots {
+        bot.moved(1, micros(), 0, 0)?;
+    }
+    thread::sleep(Duration::from_millis(500));
+
+    let stop = Arc::new(AtomicBool::new(false));
+    let receivers = bots
+        .iter()
+        .map(|bot| {
+            let receiver = bot.moved_receiver()?;
+            let stop = stop.clone();
+            Ok(thread::spawn(move || -> io::Result<Vec<Duration>> {
+                let mut latencies = Vec::new();
+                while !stop.load(Ordering::Relaxed) {
+                    if let Some(moved) = receiver.receive(Duration::from_millis(100))? {
+                        if moved.x > 0 {
+                            let sent = Duration::from_micros(moved.time);
+                            latencies.push(epoch.elapsed().saturating_sub(sent));
+                        }
+                    }
+                }
+                Ok(latencies)
+            }))
+        })
+        .collect::<io::Result<Vec<_>>>()?;
+
+    let interval = Duration::from_secs_f64(1.0 / config.rate.max(0.001));
+    let start = Instant::now();
+    let mut last_heartbeat = start;
+    let mut sent = 0u64;
+    let mut tick = 1u32;
+    while start.elapsed() < config.duration {
+        for (i, bot) in bots.iter().enumerate() {
+            bot.moved(1, micros(), tick as i32, i as i32)?;
+            sent += 1;
+        }
+        if last_heartbeat.elapsed() >= HEARTBEAT_INTERVAL {
+            for bot in &mut bots {
+                bot.heartbeat()?;
+            }
+            last_heartbeat = Instant::now();
+        }
+        tick += 1;
+        if let Some(wait) = (start + interval * tick).checked_duration_since(Instant::now()) {
+            thread::sleep(wait);
+        }
+    }
+
+    thread::sleep(GRACE_PERIOD);
+    stop.store(true, Ordering::Relaxed);
+    let mut latencies = Vec::new();
+    for receiver in receivers {
+        latencies.extend(receiver.join().expect("receiver thread panicked")?);
+    }
+    latencies.sort_unstable();
+
+    Ok(Report {
+        sent,
+        expected: sent * (config.bots.saturating_sub(1) as u64),
+        received: latencies.len() as u64,
+        latencies,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn statistics() {
+        let report = Report {
+            sent: 10,
+            expected: 20,
+            received: 15,
+            latencies: (1..=15).map(Duration::from_millis).collect(),
+        };
+        assert_eq!(report.loss(), 0.25);
+        assert_eq!(report.percentile(0.0), Some(Duration::from_millis(1)));
+        assert_eq!(report.percentile(50.0), Some(Duration::from_millis(8)));
+        assert_eq!(report.percentile(100.0), Some(Duration::from_millis(15)));
+        assert_eq!(report.mean(), Some(Duration::from_millis(8)));
+
+        let empty = Report::default();
+        assert_eq!(empty.loss(), 0.0);
+        assert_eq!(empty.percentile(50.0), None);
+        assert_eq!(empty.mean(), None);
+    }
+}
@@ -0,0 +1,115 @@
+use gm8po_bot::{check, load, Server};
+use gm8po_online::{
+    crypto::DEFAULT_NETWORK_KEY,
+    protocol::{TCP_PORT, UDP_PORT},
+};
+use std::{env, process, time::Duration};
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    assert!(!args.is_empty());
+    let process_path = args[0].as_str();
+
+    let mut opts = getopts::Options::new();
+    opts.optflag("h", "help", "print this help message")
+        .optopt("s", "server", "server to connect to (default=127.0.0.1)", "HOST")
+        .optopt("", "tcp-port", "TCP port (default=8002)", "PORT")
+        .optopt("", "udp-port", "UDP port (default=8003)", "PORT")
+        .optopt("k", "key", "network key the server's games were patched with", "KEY")
+        .optopt("b", "bots", "number of bots for load (default=8)", "N")
+        .optopt("r", "rate", "movement packets per second per bot for load (default=50)", "HZ")
+        .optopt("d", "duration", "seconds to run load for (default=10)", "SECS")
+        .optopt("g", "game-id", "lobby to join for load (default=gm8po-bot load)", "ID");
+
+    let matches = match opts.parse(&args[1..]) {
+        Ok(matches) => matches,
+        Err(err) => {
+            eprintln!("{}", err);
+            process::exit(1);
+        },
+    };
+
+    if matches.opt_present("h") || matches.free.len() != 1 {
+        println!(
+            "Usage: {} check|load [options]
+
+Modes:
+    check                      verify that chat, saves, creation and movement are broadcast like games expect
+    load                       send movement from many bots and report latency and packet loss
+
+Options:
+    -h, --help                 print this help message
+    -s, --server <host>        server to connect to (defaults to 127.0.0.1)
+    --tcp-port <port>          TCP port (defaults to {})
+    --udp-port <port>          UDP port (defaults to {})
+    -k, --key <key>            network key the server's games were patched with (defaults to the converter's default)
+    -b, --bots <n>             number of bots for load (defaults to 8)
+    -r, --rate <hz>            movement packets per second per bot for load (defaults to 50)
+    -d, --duration <secs>      seconds to run load for (defaults to 10)
+    -g, --game-id <id>         lobby to join for load (defaults to \"gm8po-bot load\")",
+            process_path, TCP_PORT, UDP_PORT,
+        );
+        process::exit(if matches.opt_present("h") { 0 } else { 1 });
+    }
+
+    fn parse_opt<T: std::str::FromStr>(matches: &getopts::Matches, name: &str, default: T) -> T {
+        match matches.opt_str(name) {
+            Some(value) => value.parse().unwrap_or_else(|_| {
+                eprintln!("Invalid value for --{}: {}", name, value);
+                process::exit(1);
+            }),
+            None => default,
+        }
+    }
+    let host = matches.opt_str("server").unwrap_or_else(|| "127.0.0.1".into());
+    let tcp_port = parse_opt(&matches, "tcp-port", TCP_PORT);
+    let udp_port = parse_opt(&matches, "udp-port", UDP_PORT);
+    let network_key = matches.opt_str("key").unwrap_or_else(|| DEFAULT_NETWORK_KEY.into());
+    let server = match Server::resolve(&host, tcp_port, udp_port, &network_key) {
+        Ok(server) => server,
+        Err(err) => {
+            eprintln!("{}", err);
+            process::exit(1);
+        },
+    };
+
+    match matches.free[0].as_str() {
+        "check" => match check::run(&server, |step| println!("ok: {}", step)) {
+            Ok(()) => println!("All checks passed."),
+            Err(err) => {
+                eprintln!("FAILED: {}", err);
+                process::exit(1);
+            },
+        },
+        "load" => {
+            let config = load::Config {
+                bots: parse_opt(&matches, "bots", 8),
+                rate: parse_opt(&matches, "rate", 50.0),
+                // negative, NaN and absurdly long durations can't be turned into a Duration
+                duration: match Duration::try_from_secs_f64(parse_opt(&matches, "duration", 10.0)) {
+                    Ok(duration) => duration,
+                    Err(_) => {
+                        eprintln!("Invalid value for --duration: {}", matches.opt_str("duration").unwrap_or_default());
+                        process::exit(1);
+                    },
+                },
+                game_id: matches.opt_str("game-id").unwrap_or_else(|| "gm8po-bot load".into()),
+            };
+            println!(
+                "Running {} bots at {} packets/s for {:?} against {}...",
+                config.bots, config.rate, config.duration, server.tcp
+            );
+            match load::run(&server, &config) {
+                Ok(report) => println!("{}", report),
+                Err(err) => {
+                    eprintln!("Load test failed: {}", err);
+                    process::exit(1);
+                },
+            }
+        },
+        mode => {
+            eprintln!("Unknown mode: {} (valid modes are check/load)", mode);
+            process::exit(1);
+        },
+    }
+}
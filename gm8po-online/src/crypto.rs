@@ -0,0 +1,252 @@
+//! Session keys, encryption and authentication, built from what http_dll 2.3 offers GML:
+//! RC4 (`hbuffer_rc4_crypt`) and MD5 (`hmd5_*`).
+//!
+//! The game opens with a key exchange (message 9 in both directions, the only messages sent in clear),
+//! after which both sides know the session key, `md5(network key + client nonce + server nonce)` in lowercase hex.
+//! Every later message is sealed: its payload is RC4-encrypted with `md5(session key + direction + sequence)`,
+//! and it ends in a 16 byte MAC, `md5(session key + md5(session key + direction + sequence + everything before))`.
+//! The direction is `C` towards the server and `S` towards the client, and the sequence number counts up from 0
+//! in decimal, separately for each direction of each socket.
+//!
+//! Sealed TCP messages are the payload and the MAC, the order being implied by TCP.
+//! Sealed UDP packets start with [`UDP_SEALED`], the sender's ID (towards the server only) and the sequence number
+//! as a u32, so that the server knows whose key to check with and both sides can drop replays.
+//!
+//! The network key is baked into every game patched for a server, so this keeps out whoever doesn't have one
+//! of those games and stops players from speaking for each other, but not from reading their own traffic.
+
+use crate::{
+    hbuffer::{ReadHString, WriteHString},
+    GmString,
+};
+use byteorder::{ReadBytesExt, WriteBytesExt, LE};
+use std::{
+    collections::hash_map::RandomState,
+    fmt::{self, Display},
+    hash::{BuildHasher, Hasher},
+    io,
+    sync::atomic::{AtomicU64, Ordering},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// The network key games are patched with unless `--server-key` says otherwise.
+pub const DEFAULT_NETWORK_KEY: &str = "gm8po-online";
+
+/// The first byte of a sealed UDP packet.
+pub const UDP_SEALED: u8 = 2;
+
+pub const MAC_LEN: usize = 16;
+
+#[derive(Debug, PartialEq)]
+pub enum Error {
+    Truncated,
+    BadMac,
+}
+impl std::error::Error for Error {}
+impl Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Truncated => write!(f, "sealed message is too short"),
+            Error::BadMac => write!(f, "message failed authentication"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    ToServer,
+    ToClient,
+}
+
+impl Direction {
+    fn tag(self) -> &'static str {
+        match self {
+            Direction::ToServer => "C",
+            Direction::ToClient => "S",
+        }
+    }
+}
+
+fn md5_hex(data: &[u8]) -> String {
+    format!("{:x}", md5::compute(data))
+}
+
+/// Encrypts or decrypts `data` in place, with a fresh keystream for `key` like `hbuffer_rc4_crypt`.
+pub fn rc4(key: &[u8], data: &mut [u8]) {
+    if key.is_empty() {
+        return
+    }
+    let mut s: [u8; 256] = std::array::from_fn(|i| i as u8);
+    let mut j = 0u8;
+    for i in 0..256 {
+        j = j.wrapping_add(s[i]).wrapping_add(key[i % key.len()]);
+        s.swap(i, j.into());
+    }
+    let (mut i, mut j) = (0u8, 0u8);
+    for byte in data {
+        i = i.wrapping_add(1);
+        j = j.wrapping_add(s[usize::from(i)]);
+        s.swap(i.into(), j.into());
+        *byte ^= s[usize::from(s[usize::from(i)].wrapping_add(s[usize::from(j)]))];
+    }
+}
+
+/// A fresh nonce for the key exchange, as hex like the games send theirs.
+pub fn nonce() -> GmString {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    // RandomState is seeded from the OS, which is all that's needed to make nonces unpredictable
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u64(COUNTER.fetch_add(1, Ordering::Relaxed));
+    hasher.write_u128(SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_nanos()).unwrap_or(0));
+    let mut seed = hasher.finish().to_le_bytes().to_vec();
+    seed.extend(RandomState::new().build_hasher().finish().to_le_bytes());
+    md5_hex(&seed).as_str().into()
+}
+
+/// The keys for one connection.
+#[derive(Clone, Debug)]
+pub struct Session {
+    key: String,
+}
+
+impl Session {
+    pub fn new(network_key: &[u8], client_nonce: &GmString, server_nonce: &GmString) -> Self {
+        let input = [network_key, client_nonce.0.as_ref(), server_nonce.0.as_ref()].concat();
+        Session { key: md5_hex(&input) }
+    }
+
+    fn prefix(&self, direction: Direction, seq: u32) -> String {
+        format!("{}{}{}", self.key, direction.tag(), seq)
+    }
+
+    fn mac(&self, prefix: &str, data: &[u8]) -> [u8; MAC_LEN] {
+        let inner = md5_hex(&[prefix.as_bytes(), data].concat());
+        md5::compute(format!("{}{}", self.key, inner)).0
+    }
+
+    /// Appends the encrypted `payload` and a MAC over everything to `header`.
+    pub fn seal(&self, direction: Direction, seq: u32, header: &[u8], payload: &[u8]) -> Vec<u8> {
+        let prefix = self.prefix(direction, seq);
+        let mut out = header.to_vec();
+        let start = out.len();
+        out.extend_from_slice(payload);
+        rc4(md5_hex(prefix.as_bytes()).as_bytes(), &mut out[start..]);
+        let mac = self.mac(&prefix, &out);
+        out.extend_from_slice(&mac);
+        out
+    }
+
+    /// Checks the MAC of a sealed message with a `header_len` byte header and returns the decrypted payload.
+    pub fn open(&self, direction: Direction, seq: u32, sealed: &[u8], header_len: usize) -> Result<Vec<u8>, Error> {
+        let end = sealed.len().checked_sub(MAC_LEN).filter(|&end| end >= header_len).ok_or(Error::Truncated)?;
+        let prefix = self.prefix(direction, seq);
+        if self.mac(&prefix, &sealed[..end]) != sealed[end..] {
+            return Err(Error::BadMac)
+        }
+        let mut payload = sealed[header_len..end].to_vec();
+        rc4(md5_hex(prefix.as_bytes()).as_bytes(), &mut payload);
+        Ok(payload)
+    }
+
+    /// Seals a UDP packet, which only names its sender when going to the server.
+    pub fn seal_udp(&self, direction: Direction, seq: u32, sender: Option<&GmString>, payload: &[u8]) -> Vec<u8> {
+        let mut header = vec![UDP_SEALED];
+        if let Some(id) = sender {
+            header.write_hstring(id).expect("writing to a Vec can't fail");
+        }
+        header.write_u32::<LE>(seq).expect("writing to a Vec can't fail");
+        self.seal(direction, seq, &header, payload)
+    }
+}
+
+/// The clear part of a sealed UDP packet.
+#[derive(Debug, PartialEq)]
+pub struct UdpHeader {
+    pub sender: Option<GmString>,
+    pub seq: u32,
+    /// Where the encrypted payload starts.
+    pub len: usize,
+}
+
+impl UdpHeader {
+    /// Reads the header of a sealed packet, if it is one. Packets to the server name their sender.
+    pub fn read(packet: &[u8], has_sender: bool) -> Option<Self> {
+        let mut data = io::Cursor::new(packet);
+        if data.read_u8().ok()? != UDP_SEALED {
+            return None
+        }
+        let sender = if has_sender { Some(data.read_hstring().ok()?) } else { None };
+        let seq = data.read_u32::<LE>().ok()?;
+        Some(UdpHeader { sender, seq, len: data.position() as usize })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session() -> Session {
+        Session::new(DEFAULT_NETWORK_KEY.as_bytes(), &"a".repeat(32).as_str().into(), &"b".repeat(32).as_str().into())
+    }
+
+    #[test]
+    fn rc4_test_vectors() {
+        let mut data = *b"Plaintext";
+        rc4(b"Key", &mut data);
+        assert_eq!(data, [0xBB, 0xF3, 0x16, 0xE8, 0xD9, 0x40, 0xAF, 0x0A, 0xD3]);
+        let mut data = *b"Attack at dawn";
+        rc4(b"Secret", &mut data);
+        assert_eq!(data, [0x45, 0xA0, 0x1F, 0x64, 0x5F, 0xC3, 0x5B, 0x38, 0x35, 0x52, 0x54, 0x4B, 0x9B, 0xF5]);
+    }
+
+    #[test]
+    fn session_key() {
+        let key = md5_hex(format!("{}{}{}", DEFAULT_NETWORK_KEY, "a".repeat(32), "b".repeat(32)).as_bytes());
+        assert_eq!(session().key, key);
+        assert_eq!(key.len(), 32);
+    }
+
+    #[test]
+    fn seal_and_open() {
+        let session = session();
+        let sealed = session.seal(Direction::ToServer, 7, &[], b"\x04hello\0");
+        assert_eq!(sealed.len(), 7 + MAC_LEN);
+        assert_ne!(&sealed[..7], b"\x04hello\0");
+        assert_eq!(session.open(Direction::ToServer, 7, &sealed, 0).unwrap(), b"\x04hello\0");
+
+        // wrong sequence number, direction or key, or tampering
+        assert_eq!(session.open(Direction::ToServer, 8, &sealed, 0), Err(Error::BadMac));
+        assert_eq!(session.open(Direction::ToClient, 7, &sealed, 0), Err(Error::BadMac));
+        let other = Session::new(b"other", &"a".into(), &"b".into());
+        assert_eq!(other.open(Direction::ToServer, 7, &sealed, 0), Err(Error::BadMac));
+        let mut tampered = sealed.clone();
+        tampered[0] ^= 1;
+        assert_eq!(session.open(Direction::ToServer, 7, &tampered, 0), Err(Error::BadMac));
+        assert_eq!(session.open(Direction::ToServer, 7, &sealed[..MAC_LEN - 1], 0), Err(Error::Truncated));
+    }
+
+    #[test]
+    fn udp_header() {
+        let session = session();
+        let sealed = session.seal_udp(Direction::ToServer, 3, Some(&"12".into()), b"\x01");
+        let header = UdpHeader::read(&sealed, true).unwrap();
+        assert_eq!(header, UdpHeader { sender: Some("12".into()), seq: 3, len: 8 });
+        assert_eq!(session.open(Direction::ToServer, header.seq, &sealed, header.len).unwrap(), b"\x01");
+
+        // the header is covered by the MAC too
+        let mut forged = sealed.clone();
+        forged[1] = b'3';
+        assert_eq!(session.open(Direction::ToServer, 3, &forged, header.len), Err(Error::BadMac));
+
+        let sealed = session.seal_udp(Direction::ToClient, 4, None, b"\x01");
+        assert_eq!(UdpHeader::read(&sealed, false).unwrap(), UdpHeader { sender: None, seq: 4, len: 5 });
+        assert_eq!(UdpHeader::read(&[0], false), None);
+    }
+
+    #[test]
+    fn nonces_differ() {
+        let (a, b) = (nonce(), nonce());
+        assert_ne!(a, b);
+        assert_eq!(a.0.len(), 32);
+    }
+}
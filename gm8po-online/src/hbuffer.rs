@@ -0,0 +1,139 @@
+//! Primitives of the http_dll 2.3 buffer format.
+//!
+//! Numbers are plain little-endian (use byteorder's `ReadBytesExt`/`WriteBytesExt` with `LE` for those),
+//! strings are null-terminated, and TCP messages are prefixed with their length as a u32.
+//! UDP datagrams carry a single buffer with no framing.
+
+use crate::GmString;
+use byteorder::{ReadBytesExt, WriteBytesExt, LE};
+use std::io::{self, BufRead};
+
+/// The largest TCP message we accept, anything bigger is treated as a broken stream.
+pub const MAX_MESSAGE_LEN: usize = 0x10000;
+
+// null-terminated string extension, as written by hbuffer_write_string
+pub trait ReadHString: BufRead {
+    fn read_hstring(&mut self) -> io::Result<GmString> {
+        let mut buf = Vec::new();
+        self.read_until(0, &mut buf)?;
+        match buf.pop() {
+            Some(0) => Ok(GmString(buf.into_boxed_slice())),
+            _ => Err(io::Error::new(io::ErrorKind::UnexpectedEof, "unterminated string")),
+        }
+    }
+}
+impl<R> ReadHString for R where R: BufRead {}
+
+pub trait WriteHString: io::Write {
+    fn write_hstring(&mut self, s: &GmString) -> io::Result<()> {
+        // http_dll stops at the first null byte, so anything after it would never make it over the wire
+        let len = s.0.iter().position(|&b| b == 0).unwrap_or(s.0.len());
+        self.write_all(&s.0[..len])?;
+        self.write_all(&[0])?;
+        Ok(())
+    }
+}
+impl<W> WriteHString for W where W: io::Write {}
+
+/// Writes a buffer as a single TCP message, as hsocket_write_message does.
+pub fn write_message(mut writer: impl io::Write, payload: &[u8]) -> io::Result<()> {
+    writer.write_u32::<LE>(payload.len() as u32)?;
+    writer.write_all(payload)?;
+    Ok(())
+}
+
+/// Reads a single TCP message, blocking until all of it has arrived.
+pub fn read_message(mut reader: impl io::Read) -> io::Result<Vec<u8>> {
+    let len = reader.read_u32::<LE>()? as usize;
+    if len > MAX_MESSAGE_LEN {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "message too long"))
+    }
+    let mut payload = vec![0u8; len];
+    reader.read_exact(&mut payload)?;
+    Ok(payload)
+}
+
+/// Splits a TCP byte stream into messages as data trickles in, like hsocket_read_message does.
+#[derive(Default)]
+pub struct MessageReader {
+    pending: Vec<u8>,
+}
+
+impl MessageReader {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queues data received from the socket.
+    pub fn push(&mut self, data: &[u8]) {
+        self.pending.extend_from_slice(data);
+    }
+
+    /// Takes the next complete message if there is one.
+    /// Returns an error if the stream claims a message longer than [`MAX_MESSAGE_LEN`].
+    pub fn next_message(&mut self) -> io::Result<Option<Vec<u8>>> {
+        let len = match self.pending.get(..4) {
+            Some(&[a, b, c, d]) => u32::from_le_bytes([a, b, c, d]) as usize,
+            _ => return Ok(None),
+        };
+        if len > MAX_MESSAGE_LEN {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "message too long"))
+        }
+        if self.pending.len() < 4 + len {
+            return Ok(None)
+        }
+        let payload = self.pending[4..4 + len].to_vec();
+        self.pending.drain(..4 + len);
+        Ok(Some(payload))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn string_is_null_terminated() {
+        let mut buf = Vec::new();
+        buf.write_hstring(&"abc".into()).unwrap();
+        assert_eq!(buf, b"abc\0");
+        assert_eq!(io::Cursor::new(&buf).read_hstring().unwrap(), "abc".into());
+    }
+
+    #[test]
+    fn string_stops_at_null() {
+        let mut buf = Vec::new();
+        buf.write_hstring(&GmString(b"ab\0cd".as_slice().into())).unwrap();
+        assert_eq!(buf, b"ab\0");
+    }
+
+    #[test]
+    fn unterminated_string() {
+        assert!(io::Cursor::new(b"abc").read_hstring().is_err());
+    }
+
+    #[test]
+    fn message_framing() {
+        let mut stream = Vec::new();
+        write_message(&mut stream, &[4, b'h', b'i', 0]).unwrap();
+        write_message(&mut stream, &[2]).unwrap();
+        assert_eq!(&stream[..8], &[4, 0, 0, 0, 4, b'h', b'i', 0]);
+
+        let mut reader = MessageReader::new();
+        reader.push(&stream[..6]);
+        assert_eq!(reader.next_message().unwrap(), None);
+        reader.push(&stream[6..]);
+        assert_eq!(reader.next_message().unwrap(), Some(vec![4, b'h', b'i', 0]));
+        assert_eq!(reader.next_message().unwrap(), Some(vec![2]));
+        assert_eq!(reader.next_message().unwrap(), None);
+
+        assert_eq!(read_message(io::Cursor::new(&stream)).unwrap(), vec![4, b'h', b'i', 0]);
+    }
+
+    #[test]
+    fn oversized_message() {
+        let mut reader = MessageReader::new();
+        reader.push(&u32::MAX.to_le_bytes());
+        assert!(reader.next_message().is_err());
+    }
+}
@@ -0,0 +1,34 @@
+//! Shared code for talking to games patched by gm8poconverter.
+//!
+//! The wire format is whatever http_dll 2.3 produces for the `hbuffer_*` and `hsocket_*` calls
+//! made by the injected GML, see [`hbuffer`] for the primitives, [`protocol`] for the messages
+//! and [`crypto`] for how they're sealed.
+
+pub mod crypto;
+pub mod hbuffer;
+pub mod protocol;
+pub mod recording;
+
+use std::fmt::{self, Display};
+
+/// A GameMaker string. These are plain bytes in the game's ANSI codepage, so they aren't guaranteed to be UTF-8.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct GmString(pub Box<[u8]>);
+
+impl Display for GmString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        String::from_utf8_lossy(self.0.as_ref()).fmt(f)
+    }
+}
+
+impl From<&str> for GmString {
+    fn from(s: &str) -> Self {
+        GmString(s.as_bytes().into())
+    }
+}
+
+impl From<&[u8]> for GmString {
+    fn from(s: &[u8]) -> Self {
+        GmString(s.into())
+    }
+}
@@ -0,0 +1,565 @@
+//! The messages exchanged between the injected GML (`World_Create.gml`/`World_EndStep.gml`) and the server.
+//!
+//! TCP messages have different meanings depending on direction, so there's one enum for each.
+//! UDP is only used for movement, which the server relays to the rest of the lobby.
+//! Apart from the key exchange everything travels sealed, see [`crate::crypto`].
+
+use crate::{
+    hbuffer::{ReadHString, WriteHString},
+    GmString,
+};
+use byteorder::{ReadBytesExt, WriteBytesExt, LE};
+use std::{
+    fmt::{self, Display},
+    io,
+};
+
+/// The TCP port the templates connect to.
+pub const TCP_PORT: u16 = 8002;
+
+/// The UDP port the templates send movement to.
+pub const UDP_PORT: u16 = 8003;
+
+/// The version string sent by the current templates in [`Join`].
+pub const VERSION: &str = "1.4.0";
+
+/// The oldest version which still speaks this protocol. Games patched before 1.3.0 don't seal their messages.
+pub const OLDEST_COMPATIBLE_VERSION: &str = "1.3.0";
+
+/// The most entries a ranking or leaderboard can have, since the count is sent as a u8.
+pub const MAX_RANKING_LEN: usize = 255;
+
+/// Who [`ServerMessage::Chat`] is from when it's the server talking rather than a player, whose IDs start at 1.
+pub const SYSTEM_ID: &str = "0";
+
+#[derive(Debug)]
+pub enum Error {
+    IO(io::Error),
+    UnknownMessage(u8),
+}
+impl std::error::Error for Error {}
+impl Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::IO(err) => write!(f, "malformed message: {}", err),
+            Error::UnknownMessage(id) => write!(f, "unknown message id {}", id),
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Error::IO(err)
+    }
+}
+
+/// Sent over TCP when a player connects (message 3).
+#[derive(Clone, Debug, PartialEq)]
+pub struct Join {
+    pub name: GmString,
+    /// The MD5 of the game executable with the lobby password appended (hashed again since 1.3.0),
+    /// used to group players.
+    pub game_id: GmString,
+    pub game_name: GmString,
+    pub version: GmString,
+    pub has_password: bool,
+}
+
+/// A save position shared with the rest of the lobby (message 5).
+#[derive(Clone, Debug, PartialEq)]
+pub struct SavePosition {
+    /// `global.grav` on Verve, 0/1 for which player object saved on Renex and Yuuutu,
+    /// 0/1 for upright/upside down on Kayin and Seph.
+    pub gravity: u8,
+    pub x: i32,
+    pub y: f64,
+    pub room: i16,
+}
+
+/// Messages sent by the game over TCP.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ClientMessage {
+    /// The player object came into existence (0).
+    PlayerCreated,
+    /// The player object stopped existing (1).
+    PlayerDestroyed,
+    /// Sent every 3 seconds to keep the connection alive (2).
+    Heartbeat,
+    Join(Join),
+    /// The player said something (4).
+    Chat(GmString),
+    /// The player saved (5), only sent outside of race mode.
+    Saved(SavePosition),
+    /// The player first appeared in race mode (6).
+    RaceStart { room: u16 },
+    /// The player entered a room other than the finish during a race (7).
+    RaceSplit { room: u16 },
+    /// The player entered the finish room (8).
+    RaceFinish,
+    /// The first message on a connection (9), sent in clear. The server answers with its own nonce.
+    KeyExchange { version: GmString, nonce: GmString },
+}
+
+/// Messages sent by the server over TCP.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ServerMessage {
+    /// Another player's object came into existence (0).
+    PlayerCreated { id: GmString, name: GmString },
+    /// Another player's object stopped existing, or they left (1).
+    PlayerDestroyed { id: GmString },
+    /// The client is too old, the game shows a message and quits (2).
+    IncompatibleVersion { oldest_version: GmString },
+    /// Another player said something (4).
+    Chat { id: GmString, message: GmString },
+    /// Another player saved (5).
+    Saved { name: GmString, position: SavePosition },
+    /// The ID the server assigned to this client, to be used in UDP messages (6).
+    SelfId(GmString),
+    /// Everyone racing in the lobby, best first (7).
+    RaceRanking(Vec<RankingEntry>),
+    /// The best finished races for this game ID, best first (8).
+    Leaderboard(Vec<LeaderboardEntry>),
+    /// The answer to the client's key exchange (9), sent in clear.
+    KeyExchange { nonce: GmString },
+}
+
+/// A racer's current standing.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RankingEntry {
+    pub id: GmString,
+    pub name: GmString,
+    pub finished: bool,
+    /// Milliseconds since the racer started, measured by the server. Final if `finished`.
+    pub time: u32,
+    /// The last room the racer reached.
+    pub room: u16,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct LeaderboardEntry {
+    pub name: GmString,
+    /// Milliseconds from start to finish.
+    pub time: u32,
+}
+
+/// A player's state, sent every few frames (1).
+#[derive(Clone, Debug, PartialEq)]
+pub struct Moved {
+    pub id: GmString,
+    pub game_id: GmString,
+    pub room: u16,
+    /// The sender's `current_time`, used to drop out-of-order packets.
+    pub time: u64,
+    pub x: i32,
+    pub y: i32,
+    pub sprite_index: i32,
+    pub image_speed: f32,
+    pub image_xscale: f32,
+    pub image_yscale: f32,
+    pub image_angle: f32,
+    pub name: GmString,
+    /// Which player object the sender is using: 0 for the usual one, 1 for the engine's second player object.
+    /// Games patched before 1.4.0 don't send it, so it's read as 0 when missing.
+    pub form: u8,
+}
+
+/// Messages sent over UDP, in both directions. Only the hello is sent in clear, movement is sealed.
+#[derive(Clone, Debug, PartialEq)]
+pub enum UdpMessage {
+    /// Sent once by the game right after creating its UDP socket (0).
+    Hello,
+    Moved(Moved),
+}
+
+impl SavePosition {
+    fn read(data: &mut impl io::Read) -> io::Result<Self> {
+        Ok(SavePosition {
+            gravity: data.read_u8()?,
+            x: data.read_i32::<LE>()?,
+            y: data.read_f64::<LE>()?,
+            room: data.read_i16::<LE>()?,
+        })
+    }
+
+    fn write(&self, out: &mut impl io::Write) -> io::Result<()> {
+        out.write_u8(self.gravity)?;
+        out.write_i32::<LE>(self.x)?;
+        out.write_f64::<LE>(self.y)?;
+        out.write_i16::<LE>(self.room)?;
+        Ok(())
+    }
+}
+
+impl ClientMessage {
+    pub fn decode(data: &[u8]) -> Result<Self, Error> {
+        let mut data = io::Cursor::new(data);
+        Ok(match data.read_u8()? {
+            0 => ClientMessage::PlayerCreated,
+            1 => ClientMessage::PlayerDestroyed,
+            2 => ClientMessage::Heartbeat,
+            3 => ClientMessage::Join(Join {
+                name: data.read_hstring()?,
+                game_id: data.read_hstring()?,
+                game_name: data.read_hstring()?,
+                version: data.read_hstring()?,
+                has_password: data.read_u8()? != 0,
+            }),
+            4 => ClientMessage::Chat(data.read_hstring()?),
+            5 => ClientMessage::Saved(SavePosition::read(&mut data)?),
+            6 => ClientMessage::RaceStart { room: data.read_u16::<LE>()? },
+            7 => ClientMessage::RaceSplit { room: data.read_u16::<LE>()? },
+            8 => ClientMessage::RaceFinish,
+            9 => ClientMessage::KeyExchange { version: data.read_hstring()?, nonce: data.read_hstring()? },
+            id => return Err(Error::UnknownMessage(id)),
+        })
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        self.write(&mut out).expect("writing to a Vec can't fail");
+        out
+    }
+
+    fn write(&self, out: &mut Vec<u8>) -> io::Result<()> {
+        match self {
+            ClientMessage::PlayerCreated => out.write_u8(0)?,
+            ClientMessage::PlayerDestroyed => out.write_u8(1)?,
+            ClientMessage::Heartbeat => out.write_u8(2)?,
+            ClientMessage::Join(join) => {
+                out.write_u8(3)?;
+                out.write_hstring(&join.name)?;
+                out.write_hstring(&join.game_id)?;
+                out.write_hstring(&join.game_name)?;
+                out.write_hstring(&join.version)?;
+                out.write_u8(join.has_password.into())?;
+            },
+            ClientMessage::Chat(message) => {
+                out.write_u8(4)?;
+                out.write_hstring(message)?;
+            },
+            ClientMessage::Saved(position) => {
+                out.write_u8(5)?;
+                position.write(out)?;
+            },
+            ClientMessage::RaceStart { room } => {
+                out.write_u8(6)?;
+                out.write_u16::<LE>(*room)?;
+            },
+            ClientMessage::RaceSplit { room } => {
+                out.write_u8(7)?;
+                out.write_u16::<LE>(*room)?;
+            },
+            ClientMessage::RaceFinish => out.write_u8(8)?,
+            ClientMessage::KeyExchange { version, nonce } => {
+                out.write_u8(9)?;
+                out.write_hstring(version)?;
+                out.write_hstring(nonce)?;
+            },
+        }
+        Ok(())
+    }
+}
+
+impl ServerMessage {
+    pub fn decode(data: &[u8]) -> Result<Self, Error> {
+        let mut data = io::Cursor::new(data);
+        Ok(match data.read_u8()? {
+            0 => ServerMessage::PlayerCreated { id: data.read_hstring()?, name: data.read_hstring()? },
+            1 => ServerMessage::PlayerDestroyed { id: data.read_hstring()? },
+            2 => ServerMessage::IncompatibleVersion { oldest_version: data.read_hstring()? },
+            4 => ServerMessage::Chat { id: data.read_hstring()?, message: data.read_hstring()? },
+            5 => {
+                // the name sits between the gravity and the position here, unlike in the client's message
+                let gravity = data.read_u8()?;
+                let name = data.read_hstring()?;
+                let x = data.read_i32::<LE>()?;
+                let y = data.read_f64::<LE>()?;
+                let room = data.read_i16::<LE>()?;
+                ServerMessage::Saved { name, position: SavePosition { gravity, x, y, room } }
+            },
+            6 => ServerMessage::SelfId(data.read_hstring()?),
+            7 => {
+                let count = data.read_u8()?;
+                let mut entries = Vec::with_capacity(count.into());
+                for _ in 0..count {
+                    entries.push(RankingEntry {
+                        id: data.read_hstring()?,
+                        name: data.read_hstring()?,
+                        finished: data.read_u8()? != 0,
+                        time: data.read_u32::<LE>()?,
+                        room: data.read_u16::<LE>()?,
+                    });
+                }
+                ServerMessage::RaceRanking(entries)
+            },
+            8 => {
+                let count = data.read_u8()?;
+                let mut entries = Vec::with_capacity(count.into());
+                for _ in 0..count {
+                    entries.push(LeaderboardEntry { name: data.read_hstring()?, time: data.read_u32::<LE>()? });
+                }
+                ServerMessage::Leaderboard(entries)
+            },
+            9 => ServerMessage::KeyExchange { nonce: data.read_hstring()? },
+            id => return Err(Error::UnknownMessage(id)),
+        })
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        self.write(&mut out).expect("writing to a Vec can't fail");
+        out
+    }
+
+    fn write(&self, out: &mut Vec<u8>) -> io::Result<()> {
+        match self {
+            ServerMessage::PlayerCreated { id, name } => {
+                out.write_u8(0)?;
+                out.write_hstring(id)?;
+                out.write_hstring(name)?;
+            },
+            ServerMessage::PlayerDestroyed { id } => {
+                out.write_u8(1)?;
+                out.write_hstring(id)?;
+            },
+            ServerMessage::IncompatibleVersion { oldest_version } => {
+                out.write_u8(2)?;
+                out.write_hstring(oldest_version)?;
+            },
+            ServerMessage::Chat { id, message } => {
+                out.write_u8(4)?;
+                out.write_hstring(id)?;
+                out.write_hstring(message)?;
+            },
+            ServerMessage::Saved { name, position } => {
+                out.write_u8(5)?;
+                out.write_u8(position.gravity)?;
+                out.write_hstring(name)?;
+                out.write_i32::<LE>(position.x)?;
+                out.write_f64::<LE>(position.y)?;
+                out.write_i16::<LE>(position.room)?;
+            },
+            ServerMessage::SelfId(id) => {
+                out.write_u8(6)?;
+                out.write_hstring(id)?;
+            },
+            // anything past MAX_RANKING_LEN is dropped
+            ServerMessage::RaceRanking(entries) => {
+                out.write_u8(7)?;
+                let entries = &entries[..entries.len().min(MAX_RANKING_LEN)];
+                out.write_u8(entries.len() as u8)?;
+                for entry in entries {
+                    out.write_hstring(&entry.id)?;
+                    out.write_hstring(&entry.name)?;
+                    out.write_u8(entry.finished.into())?;
+                    out.write_u32::<LE>(entry.time)?;
+                    out.write_u16::<LE>(entry.room)?;
+                }
+            },
+            ServerMessage::Leaderboard(entries) => {
+                out.write_u8(8)?;
+                let entries = &entries[..entries.len().min(MAX_RANKING_LEN)];
+                out.write_u8(entries.len() as u8)?;
+                for entry in entries {
+                    out.write_hstring(&entry.name)?;
+                    out.write_u32::<LE>(entry.time)?;
+                }
+            },
+            ServerMessage::KeyExchange { nonce } => {
+                out.write_u8(9)?;
+                out.write_hstring(nonce)?;
+            },
+        }
+        Ok(())
+    }
+}
+
+impl UdpMessage {
+    pub fn decode(data: &[u8]) -> Result<Self, Error> {
+        let mut data = io::Cursor::new(data);
+        Ok(match data.read_u8()? {
+            0 => UdpMessage::Hello,
+            1 => UdpMessage::Moved(Moved {
+                id: data.read_hstring()?,
+                game_id: data.read_hstring()?,
+                room: data.read_u16::<LE>()?,
+                time: data.read_u64::<LE>()?,
+                x: data.read_i32::<LE>()?,
+                y: data.read_i32::<LE>()?,
+                sprite_index: data.read_i32::<LE>()?,
+                image_speed: data.read_f32::<LE>()?,
+                image_xscale: data.read_f32::<LE>()?,
+                image_yscale: data.read_f32::<LE>()?,
+                image_angle: data.read_f32::<LE>()?,
+                name: data.read_hstring()?,
+                form: data.read_u8().unwrap_or(0),
+            }),
+            id => return Err(Error::UnknownMessage(id)),
+        })
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        self.write(&mut out).expect("writing to a Vec can't fail");
+        out
+    }
+
+    fn write(&self, out: &mut Vec<u8>) -> io::Result<()> {
+        match self {
+            UdpMessage::Hello => out.write_u8(0)?,
+            UdpMessage::Moved(moved) => {
+                out.write_u8(1)?;
+                out.write_hstring(&moved.id)?;
+                out.write_hstring(&moved.game_id)?;
+                out.write_u16::<LE>(moved.room)?;
+                out.write_u64::<LE>(moved.time)?;
+                out.write_i32::<LE>(moved.x)?;
+                out.write_i32::<LE>(moved.y)?;
+                out.write_i32::<LE>(moved.sprite_index)?;
+                out.write_f32::<LE>(moved.image_speed)?;
+                out.write_f32::<LE>(moved.image_xscale)?;
+                out.write_f32::<LE>(moved.image_yscale)?;
+                out.write_f32::<LE>(moved.image_angle)?;
+                out.write_hstring(&moved.name)?;
+                out.write_u8(moved.form)?;
+            },
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn position() -> SavePosition {
+        SavePosition { gravity: 1, x: -32, y: 407.5, room: 12 }
+    }
+
+    fn moved() -> Moved {
+        Moved {
+            id: "17".into(),
+            game_id: "d41d8cd98f00b204e9800998ecf8427epassword".into(),
+            room: 3,
+            time: 123456789,
+            x: 400,
+            y: -8,
+            sprite_index: 5,
+            image_speed: 0.2,
+            image_xscale: -1.0,
+            image_yscale: 1.0,
+            image_angle: 90.0,
+            name: GmString(b"\xc4\xe3\xba\xc3".as_slice().into()), // not UTF-8
+            form: 1,
+        }
+    }
+
+    #[test]
+    fn client_round_trip() {
+        let messages = [
+            ClientMessage::PlayerCreated,
+            ClientMessage::PlayerDestroyed,
+            ClientMessage::Heartbeat,
+            ClientMessage::Join(Join {
+                name: "Kid".into(),
+                game_id: "d41d8cd98f00b204e9800998ecf8427e".into(),
+                game_name: "I wanna be the Test".into(),
+                version: VERSION.into(),
+                has_password: false,
+            }),
+            ClientMessage::Chat("hello".into()),
+            ClientMessage::Saved(position()),
+            ClientMessage::RaceStart { room: 2 },
+            ClientMessage::RaceSplit { room: 3 },
+            ClientMessage::RaceFinish,
+            ClientMessage::KeyExchange { version: VERSION.into(), nonce: "0123456789abcdef".into() },
+        ];
+        for message in messages {
+            assert_eq!(ClientMessage::decode(&message.encode()).unwrap(), message);
+        }
+    }
+
+    #[test]
+    fn server_round_trip() {
+        let messages = [
+            ServerMessage::PlayerCreated { id: "1".into(), name: "Kid".into() },
+            ServerMessage::PlayerDestroyed { id: "1".into() },
+            ServerMessage::IncompatibleVersion { oldest_version: "1.2.0".into() },
+            ServerMessage::Chat { id: "1".into(), message: "hi".into() },
+            ServerMessage::Saved { name: "Kid".into(), position: position() },
+            ServerMessage::SelfId("42".into()),
+            ServerMessage::RaceRanking(vec![
+                RankingEntry { id: "1".into(), name: "Kid".into(), finished: true, time: 83250, room: 20 },
+                RankingEntry { id: "2".into(), name: "Dude".into(), finished: false, time: 90000, room: 7 },
+            ]),
+            ServerMessage::Leaderboard(vec![LeaderboardEntry { name: "Kid".into(), time: 83250 }]),
+            ServerMessage::Leaderboard(vec![]),
+            ServerMessage::KeyExchange { nonce: "fedcba9876543210".into() },
+        ];
+        for message in messages {
+            assert_eq!(ServerMessage::decode(&message.encode()).unwrap(), message);
+        }
+    }
+
+    #[test]
+    fn udp_round_trip() {
+        for message in [UdpMessage::Hello, UdpMessage::Moved(moved())] {
+            assert_eq!(UdpMessage::decode(&message.encode()).unwrap(), message);
+        }
+    }
+
+    #[test]
+    fn join_bytes() {
+        // What World_Create.gml writes for a passworded join
+        let join = ClientMessage::Join(Join {
+            name: "a".into(),
+            game_id: "id".into(),
+            game_name: "g".into(),
+            version: "1".into(),
+            has_password: true,
+        });
+        assert_eq!(join.encode(), b"\x03a\0id\0g\x001\0\x01");
+    }
+
+    #[test]
+    fn saved_bytes() {
+        let client = ClientMessage::Saved(position()).encode();
+        assert_eq!(client[..6], [5, 1, 0xE0, 0xFF, 0xFF, 0xFF]);
+        assert_eq!(client[6..14], 407.5f64.to_le_bytes());
+        assert_eq!(client[14..], [12, 0]);
+
+        let server = ServerMessage::Saved { name: "K".into(), position: position() }.encode();
+        assert_eq!(server[..4], [5, 1, b'K', 0]);
+    }
+
+    #[test]
+    fn ranking_is_capped() {
+        let entry = LeaderboardEntry { name: "Kid".into(), time: 1 };
+        let data = ServerMessage::Leaderboard(vec![entry; 300]).encode();
+        match ServerMessage::decode(&data).unwrap() {
+            ServerMessage::Leaderboard(entries) => assert_eq!(entries.len(), MAX_RANKING_LEN),
+            message => panic!("expected leaderboard, got {:?}", message),
+        }
+    }
+
+    #[test]
+    fn moved_without_form() {
+        // What games patched before 1.4.0 send
+        let data = UdpMessage::Moved(moved()).encode();
+        let expected = UdpMessage::Moved(Moved { form: 0, ..moved() });
+        assert_eq!(UdpMessage::decode(&data[..data.len() - 1]).unwrap(), expected);
+    }
+
+    #[test]
+    fn truncated() {
+        let data = UdpMessage::Moved(moved()).encode();
+        assert!(UdpMessage::decode(&data[..data.len() - 2]).is_err());
+        assert!(ClientMessage::decode(&[]).is_err());
+    }
+
+    #[test]
+    fn unknown_id() {
+        assert!(matches!(ServerMessage::decode(&[3]), Err(Error::UnknownMessage(3))));
+        assert!(matches!(UdpMessage::decode(&[9]), Err(Error::UnknownMessage(9))));
+    }
+}
@@ -0,0 +1,222 @@
+//! Movement recordings, as written by the recorder injected with `--record` and played back by `__ONLINE_ghost`.
+//!
+//! The file is a header (the string `GM8POREC`, a format version byte, the game ID and the player name)
+//! followed by one fixed-size frame per step in which the player existed.
+//! The recorder writes raw `current_time` values, [`Recording::normalize`] makes them start at 0.
+
+use crate::{
+    hbuffer::{ReadHString, WriteHString},
+    GmString,
+};
+use byteorder::{ReadBytesExt, WriteBytesExt, LE};
+use std::{
+    fmt::{self, Display},
+    io::{self, BufRead},
+};
+
+pub const MAGIC: &str = "GM8POREC";
+pub const VERSION: u8 = 1;
+
+/// The size of a single frame on disk.
+pub const FRAME_LEN: usize = 34;
+
+#[derive(Debug)]
+pub enum Error {
+    IO(io::Error),
+    NotARecording,
+    UnsupportedVersion(u8),
+}
+impl std::error::Error for Error {}
+impl Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::IO(err) => write!(f, "io error: {}", err),
+            Error::NotARecording => write!(f, "not a recording"),
+            Error::UnsupportedVersion(v) => write!(f, "unsupported recording version {} (expected {})", v, VERSION),
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Error::IO(err)
+    }
+}
+
+/// The state of the player in a single step, mirroring what goes into a UDP movement packet.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Frame {
+    /// Milliseconds, see [`Recording::normalize`].
+    pub time: u32,
+    pub room: u16,
+    pub x: i32,
+    pub y: i32,
+    pub sprite_index: i32,
+    pub image_speed: f32,
+    pub image_xscale: f32,
+    pub image_yscale: f32,
+    pub image_angle: f32,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Recording {
+    /// MD5 of the game executable the recording was made in.
+    pub game_id: GmString,
+    pub name: GmString,
+    pub frames: Vec<Frame>,
+}
+
+impl Frame {
+    fn read(reader: &mut impl io::Read) -> io::Result<Self> {
+        Ok(Frame {
+            time: reader.read_u32::<LE>()?,
+            room: reader.read_u16::<LE>()?,
+            x: reader.read_i32::<LE>()?,
+            y: reader.read_i32::<LE>()?,
+            sprite_index: reader.read_i32::<LE>()?,
+            image_speed: reader.read_f32::<LE>()?,
+            image_xscale: reader.read_f32::<LE>()?,
+            image_yscale: reader.read_f32::<LE>()?,
+            image_angle: reader.read_f32::<LE>()?,
+        })
+    }
+
+    fn write(&self, writer: &mut impl io::Write) -> io::Result<()> {
+        writer.write_u32::<LE>(self.time)?;
+        writer.write_u16::<LE>(self.room)?;
+        writer.write_i32::<LE>(self.x)?;
+        writer.write_i32::<LE>(self.y)?;
+        writer.write_i32::<LE>(self.sprite_index)?;
+        writer.write_f32::<LE>(self.image_speed)?;
+        writer.write_f32::<LE>(self.image_xscale)?;
+        writer.write_f32::<LE>(self.image_yscale)?;
+        writer.write_f32::<LE>(self.image_angle)?;
+        Ok(())
+    }
+}
+
+impl Recording {
+    /// Reads a recording. A partial frame at the end, left over if the game crashed while writing, is dropped.
+    pub fn read(mut reader: impl BufRead) -> Result<Self, Error> {
+        let magic = reader.read_hstring().map_err(|_| Error::NotARecording)?;
+        if magic.0.as_ref() != MAGIC.as_bytes() {
+            return Err(Error::NotARecording)
+        }
+        let version = reader.read_u8()?;
+        if version != VERSION {
+            return Err(Error::UnsupportedVersion(version))
+        }
+        let game_id = reader.read_hstring()?;
+        let name = reader.read_hstring()?;
+
+        let mut rest = Vec::new();
+        reader.read_to_end(&mut rest)?;
+        let frames = rest
+            .chunks_exact(FRAME_LEN)
+            .map(|mut chunk| Frame::read(&mut chunk))
+            .collect::<io::Result<Vec<_>>>()?;
+        Ok(Recording { game_id, name, frames })
+    }
+
+    pub fn write(&self, mut writer: impl io::Write) -> io::Result<()> {
+        writer.write_hstring(&MAGIC.into())?;
+        writer.write_u8(VERSION)?;
+        writer.write_hstring(&self.game_id)?;
+        writer.write_hstring(&self.name)?;
+        for frame in &self.frames {
+            frame.write(&mut writer)?;
+        }
+        Ok(())
+    }
+
+    /// Makes frame times relative to the first frame.
+    pub fn normalize(&mut self) {
+        if let Some(start) = self.frames.first().map(|f| f.time) {
+            for frame in &mut self.frames {
+                frame.time = frame.time.wrapping_sub(start);
+            }
+        }
+    }
+
+    /// Keeps only frames with `start <= time < end` and normalizes the result.
+    pub fn trim(&mut self, start: Option<u32>, end: Option<u32>) {
+        self.normalize();
+        self.frames.retain(|f| start.map_or(true, |s| f.time >= s) && end.map_or(true, |e| f.time < e));
+        self.normalize();
+    }
+
+    /// Time of the first frame in `room`, if it was ever entered.
+    pub fn room_entered(&self, room: u16) -> Option<u32> {
+        self.frames.iter().find(|f| f.room == room).map(|f| f.time)
+    }
+
+    /// Time between the first and the last frame in milliseconds.
+    pub fn duration(&self) -> u32 {
+        match (self.frames.first(), self.frames.last()) {
+            (Some(first), Some(last)) => last.time.wrapping_sub(first.time),
+            _ => 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(time: u32, room: u16) -> Frame {
+        Frame {
+            time,
+            room,
+            x: 32,
+            y: -16,
+            sprite_index: 4,
+            image_speed: 0.5,
+            image_xscale: -1.0,
+            image_yscale: 1.0,
+            image_angle: 0.0,
+        }
+    }
+
+    fn recording() -> Recording {
+        Recording {
+            game_id: "d41d8cd98f00b204e9800998ecf8427e".into(),
+            name: "Kid".into(),
+            frames: vec![frame(1000, 2), frame(1020, 2), frame(1040, 3), frame(1060, 3)],
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut data = Vec::new();
+        recording().write(&mut data).unwrap();
+        assert_eq!(&data[..10], b"GM8POREC\0\x01");
+        assert_eq!(data.len(), 10 + 33 + 4 + FRAME_LEN * 4);
+        assert_eq!(Recording::read(data.as_slice()).unwrap(), recording());
+    }
+
+    #[test]
+    fn partial_frame() {
+        let mut data = Vec::new();
+        recording().write(&mut data).unwrap();
+        data.truncate(data.len() - 5);
+        assert_eq!(Recording::read(data.as_slice()).unwrap().frames.len(), 3);
+    }
+
+    #[test]
+    fn not_a_recording() {
+        assert!(matches!(Recording::read(b"tempOnline\0".as_slice()), Err(Error::NotARecording)));
+        assert!(matches!(Recording::read(b"GM8POREC\0\x09".as_slice()), Err(Error::UnsupportedVersion(9))));
+    }
+
+    #[test]
+    fn trimming() {
+        let mut rec = recording();
+        assert_eq!(rec.duration(), 60);
+        rec.normalize();
+        assert_eq!(rec.frames[0].time, 0);
+        assert_eq!(rec.room_entered(3), Some(40));
+
+        rec.trim(Some(20), Some(60));
+        assert_eq!(rec.frames.iter().map(|f| (f.time, f.room)).collect::<Vec<_>>(), [(0, 2), (20, 3)]);
+    }
+}
@@ -0,0 +1,98 @@
+//! Recordings as CSV, one frame per line, so they can be inspected or edited with anything.
+
+use gm8po_online::recording::{Frame, Recording};
+use std::fmt::Write;
+
+const HEADER: &str = "time,room,x,y,sprite_index,image_speed,image_xscale,image_yscale,image_angle";
+
+pub fn write(recording: &Recording) -> String {
+    let mut out = String::new();
+    // writing to a String can't fail
+    let _ = writeln!(out, "# name: {}", recording.name);
+    let _ = writeln!(out, "# game_id: {}", recording.game_id);
+    let _ = writeln!(out, "{}", HEADER);
+    for f in &recording.frames {
+        let _ = writeln!(
+            out,
+            "{},{},{},{},{},{},{},{},{}",
+            f.time, f.room, f.x, f.y, f.sprite_index, f.image_speed, f.image_xscale, f.image_yscale, f.image_angle
+        );
+    }
+    out
+}
+
+pub fn read(text: &str) -> Result<Recording, String> {
+    let mut recording = Recording { game_id: Default::default(), name: Default::default(), frames: Vec::new() };
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if let Some(comment) = line.strip_prefix('#') {
+            if let Some((key, value)) = comment.split_once(':') {
+                match key.trim() {
+                    "name" => recording.name = value.trim().into(),
+                    "game_id" => recording.game_id = value.trim().into(),
+                    _ => (),
+                }
+            }
+            continue
+        }
+        if line.is_empty() || line == HEADER {
+            continue
+        }
+
+        let fields = line.split(',').map(str::trim).collect::<Vec<_>>();
+        let error = |what: &str| format!("line {}: {}", i + 1, what);
+        if fields.len() != 9 {
+            return Err(error(&format!("expected 9 fields, found {}", fields.len())))
+        }
+        macro_rules! field {
+            ($index:expr) => {
+                fields[$index].parse().map_err(|_| error(&format!("invalid value '{}'", fields[$index])))?
+            };
+        }
+        recording.frames.push(Frame {
+            time: field!(0),
+            room: field!(1),
+            x: field!(2),
+            y: field!(3),
+            sprite_index: field!(4),
+            image_speed: field!(5),
+            image_xscale: field!(6),
+            image_yscale: field!(7),
+            image_angle: field!(8),
+        });
+    }
+    Ok(recording)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let recording = Recording {
+            game_id: "d41d8cd98f00b204e9800998ecf8427e".into(),
+            name: "Kid".into(),
+            frames: vec![Frame {
+                time: 20,
+                room: 3,
+                x: 400,
+                y: -8,
+                sprite_index: 5,
+                image_speed: 0.2,
+                image_xscale: -1.0,
+                image_yscale: 1.0,
+                image_angle: 90.0,
+            }],
+        };
+        let text = write(&recording);
+        assert!(text.ends_with("\n20,3,400,-8,5,0.2,-1,1,90\n"));
+        assert_eq!(read(&text).unwrap(), recording);
+    }
+
+    #[test]
+    fn bad_line() {
+        assert_eq!(read("1,2,3").unwrap_err(), "line 1: expected 9 fields, found 3");
+        assert_eq!(read(&format!("{}\n1,2,3,4,5,6,7,8,x", HEADER)).unwrap_err(), "line 2: invalid value 'x'");
+    }
+}
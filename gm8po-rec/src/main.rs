@@ -0,0 +1,154 @@
+mod csv;
+
+use gm8po_online::recording::Recording;
+use std::{
+    env, fs, io,
+    path::{Path, PathBuf},
+    process,
+};
+
+fn is_csv(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("csv"))
+}
+
+fn load(path: &Path) -> Result<Recording, String> {
+    let error = |e: &dyn std::fmt::Display| format!("Failed to read '{}': {}", path.display(), e);
+    if is_csv(path) {
+        csv::read(&fs::read_to_string(path).map_err(|e| error(&e))?).map_err(|e| error(&e))
+    } else {
+        let file = fs::File::open(path).map_err(|e| error(&e))?;
+        Recording::read(io::BufReader::new(file)).map_err(|e| error(&e))
+    }
+}
+
+fn save(path: &Path, recording: &Recording) -> Result<(), String> {
+    let result = if is_csv(path) {
+        fs::write(path, csv::write(recording))
+    } else {
+        let mut data = Vec::new();
+        recording.write(&mut data).and_then(|()| fs::write(path, data))
+    };
+    result.map_err(|e| format!("Failed to write '{}': {}", path.display(), e))
+}
+
+fn seconds(ms: u32) -> f64 {
+    f64::from(ms) / 1000.0
+}
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    assert!(!args.is_empty());
+    let process_path = args[0].as_str();
+
+    let mut opts = getopts::Options::new();
+    opts.optflag("h", "help", "print this help message")
+        .optopt("o", "output", "specify output filename", "FILE")
+        .optopt("", "start", "drop everything before this many seconds in", "SECS")
+        .optopt("", "end", "drop everything from this many seconds in", "SECS")
+        .optopt("", "start-room", "drop everything before the room is first entered", "ROOM")
+        .optopt("", "end-room", "drop everything from when the room is first entered", "ROOM");
+
+    let matches = match opts.parse(&args[1..]) {
+        Ok(matches) => matches,
+        Err(err) => {
+            eprintln!("{}", err);
+            process::exit(1);
+        },
+    };
+
+    if matches.opt_present("h") || matches.free.len() != 2 {
+        println!(
+            "Usage: {} info|trim|convert FILENAME [options]
+
+Modes:
+    info                  print who recorded it, how long it is and which rooms it visits
+    trim                  cut a recording down, making it start at 0
+    convert               turn a recording into CSV, or CSV back into a recording
+
+Files ending in .csv are read and written as CSV, everything else as a recording.
+
+Options:
+    -h, --help            print this help message
+    -o, --output <file>   specify output filename (required for trim and convert)
+    --start <secs>        drop everything before this many seconds in
+    --end <secs>          drop everything from this many seconds in
+    --start-room <room>   drop everything before the room is first entered
+    --end-room <room>     drop everything from when the room is first entered",
+            process_path
+        );
+        process::exit(if matches.opt_present("h") { 0 } else { 1 });
+    }
+
+    if let Err(err) = run(&matches) {
+        eprintln!("{}", err);
+        process::exit(1);
+    }
+}
+
+fn run(matches: &getopts::Matches) -> Result<(), String> {
+    let input = PathBuf::from(&matches.free[1]);
+    let mut recording = load(&input)?;
+    let output = || matches.opt_str("o").map(PathBuf::from).ok_or_else(|| "No output file given (use -o)".to_string());
+
+    match matches.free[0].as_str() {
+        "info" => {
+            println!("Name:     {}", recording.name);
+            println!("Game ID:  {}", recording.game_id);
+            println!("Frames:   {}", recording.frames.len());
+            println!("Duration: {:.2}s", seconds(recording.duration()));
+            recording.normalize();
+            let mut last_room = None;
+            for frame in &recording.frames {
+                if last_room != Some(frame.room) {
+                    println!("  {:>9.2}s  room {}", seconds(frame.time), frame.room);
+                    last_room = Some(frame.room);
+                }
+            }
+        },
+        "trim" => {
+            let output = output()?;
+            recording.normalize();
+            let time = |name: &str| -> Result<Option<u32>, String> {
+                match matches.opt_str(name) {
+                    Some(secs) => match secs.parse::<f64>() {
+                        Ok(secs) if secs >= 0.0 => Ok(Some((secs * 1000.0).round() as u32)),
+                        _ => Err(format!("Invalid value for --{}: {}", name, secs)),
+                    },
+                    None => Ok(None),
+                }
+            };
+            let room = |name: &str| -> Result<Option<u32>, String> {
+                match matches.opt_str(name) {
+                    Some(room) => {
+                        let room = room.parse::<u16>().map_err(|_| format!("Invalid value for --{}: {}", name, room))?;
+                        match recording.room_entered(room) {
+                            Some(time) => Ok(Some(time)),
+                            None => Err(format!("Room {} never appears in the recording", room)),
+                        }
+                    },
+                    None => Ok(None),
+                }
+            };
+            let start = room("start-room")?.or(time("start")?);
+            let end = room("end-room")?.or(time("end")?);
+            let before = recording.frames.len();
+            recording.trim(start, end);
+            println!(
+                "Kept {} of {} frames ({:.2}s)",
+                recording.frames.len(),
+                before,
+                seconds(recording.duration())
+            );
+            save(&output, &recording)?;
+        },
+        "convert" => {
+            let output = output()?;
+            if is_csv(&input) == is_csv(&output) {
+                return Err("Convert either from or to CSV (by giving the file a .csv extension)".into())
+            }
+            save(&output, &recording)?;
+        },
+        mode => return Err(format!("Unknown mode: {} (valid modes are info/trim/convert)", mode)),
+    }
+    Ok(())
+}
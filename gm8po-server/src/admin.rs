@@ -0,0 +1,129 @@
+//! Commands for whoever runs the server, typed into its standard input or sent over a Unix socket,
+//! one per line. Each gets a reply of one or more lines.
+
+use gm8po_online::GmString;
+use std::{
+    io::{self, BufRead, Write},
+    net::IpAddr,
+};
+
+pub const HELP: &str = "Commands:
+    list               show the lobbies in use and who's in them
+    kick <id|address>  disconnect a player, or everyone from an address
+    ban <id|address>   kick and keep out a player's address, or an address
+    unban <address>    let an address back in
+    say <message>      show a message from the server to everyone";
+
+/// A player, by the ID the server gave them, or everyone connecting from an address.
+#[derive(Debug, PartialEq)]
+pub enum Target {
+    Id(GmString),
+    Ip(IpAddr),
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    Help,
+    List,
+    Kick(Target),
+    Ban(Target),
+    Unban(IpAddr),
+    Say(String),
+}
+
+impl Command {
+    pub fn parse(line: &str) -> Result<Self, String> {
+        let line = line.trim();
+        let (name, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let rest = rest.trim();
+        let target = || match rest.parse() {
+            _ if rest.is_empty() || rest.contains(char::is_whitespace) => Err(format!("usage: {} <id|address>", name)),
+            Ok(ip) => Ok(Target::Ip(ip)),
+            Err(_) => Ok(Target::Id(rest.into())),
+        };
+        match name {
+            "help" => Ok(Command::Help),
+            "list" => Ok(Command::List),
+            "kick" => Ok(Command::Kick(target()?)),
+            "ban" => Ok(Command::Ban(target()?)),
+            "unban" => rest.parse().map(Command::Unban).map_err(|_| "usage: unban <address>".into()),
+            "say" if !rest.is_empty() => Ok(Command::Say(rest.into())),
+            "say" => Err("usage: say <message>".into()),
+            _ => Err(format!("unknown command '{}', try 'help'", name)),
+        }
+    }
+}
+
+/// Runs commands from `input` until it ends, writing replies to `output`.
+pub fn serve(input: impl BufRead, mut output: impl Write, execute: impl Fn(Command) -> String) -> io::Result<()> {
+    for line in input.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue
+        }
+        let reply = match Command::parse(&line) {
+            Ok(command) => execute(command),
+            Err(err) => err,
+        };
+        writeln!(output, "{}", reply)?;
+        output.flush()?;
+    }
+    Ok(())
+}
+
+/// Accepts connections on a Unix socket at `path`, running each one's commands like [`serve`].
+#[cfg(unix)]
+pub fn serve_socket(
+    path: &std::path::Path,
+    execute: impl Fn(Command) -> String + Clone + Send + 'static,
+) -> io::Result<()> {
+    use std::os::unix::{fs::FileTypeExt, net::UnixListener};
+
+    // a socket left behind by an earlier run would make binding fail
+    if std::fs::symlink_metadata(path).is_ok_and(|m| m.file_type().is_socket()) {
+        std::fs::remove_file(path)?;
+    }
+    let listener = UnixListener::bind(path)?;
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(err) => {
+                eprintln!("Failed to accept admin connection: {}", err);
+                continue
+            },
+        };
+        let execute = execute.clone();
+        std::thread::spawn(move || {
+            if let Ok(reader) = stream.try_clone() {
+                let _ = serve(io::BufReader::new(reader), stream, execute);
+            }
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        assert_eq!(Command::parse(" list "), Ok(Command::List));
+        assert_eq!(Command::parse("kick 12"), Ok(Command::Kick(Target::Id("12".into()))));
+        assert_eq!(Command::parse("ban 10.0.0.1"), Ok(Command::Ban(Target::Ip("10.0.0.1".parse().unwrap()))));
+        assert_eq!(Command::parse("unban ::1"), Ok(Command::Unban("::1".parse().unwrap())));
+        assert_eq!(Command::parse("say  back in 5  minutes"), Ok(Command::Say("back in 5  minutes".into())));
+        assert!(Command::parse("kick").is_err());
+        assert!(Command::parse("kick 1 2").is_err());
+        assert!(Command::parse("unban 12").is_err());
+        assert!(Command::parse("say").is_err());
+        assert!(Command::parse("shutdown").is_err());
+    }
+
+    #[test]
+    fn replies() {
+        let mut output = Vec::new();
+        serve(&b"list\n\nnope\nsay hi\n"[..], &mut output, |command| format!("{:?}", command)).unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), "List\nunknown command 'nope', try 'help'\nSay(\"hi\")\n");
+    }
+}
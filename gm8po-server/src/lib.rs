@@ -0,0 +1,9 @@
+//! A server for games patched by gm8poconverter, relaying their messages between players in the same lobby.
+//!
+//! [`server::Server`] does the relaying, and [`admin`] parses the commands whoever runs it can type in.
+
+pub mod admin;
+mod lobby;
+mod race;
+pub mod server;
+mod tsv;
@@ -0,0 +1,223 @@
+//! What the server remembers between restarts, besides race results: the last save shared in each lobby,
+//! statistics per game ID and banned addresses. Each is optionally kept in a file, see [`tsv`].
+//!
+//! Rather than rewriting their files on every change, they only remember that they've changed. The server takes a
+//! [`Snapshot`] of them now and then and writes it out without holding up everyone else.
+
+use crate::tsv::{self, Snapshot};
+use gm8po_online::{protocol::SavePosition, GmString};
+use std::{
+    collections::{HashMap, HashSet},
+    io,
+    net::IpAddr,
+    path::PathBuf,
+};
+
+/// The last save shared in each lobby, which players joining later get too.
+///
+/// The file has one `game ID<TAB>name<TAB>gravity<TAB>x<TAB>y<TAB>room` record per lobby.
+pub struct Saves {
+    path: Option<PathBuf>,
+    by_game: HashMap<GmString, (GmString, SavePosition)>,
+    /// Whether anything changed since the last snapshot.
+    dirty: bool,
+}
+
+impl Saves {
+    pub fn load(path: Option<PathBuf>) -> io::Result<Self> {
+        let mut saves = Saves { path: None, by_game: HashMap::new(), dirty: false };
+        if let Some(path) = &path {
+            tsv::read(path, 6, |record| {
+                let position = (|| {
+                    Some(SavePosition {
+                        gravity: tsv::parse(record[2])?,
+                        x: tsv::parse(record[3])?,
+                        y: tsv::parse(record[4])?,
+                        room: tsv::parse(record[5])?,
+                    })
+                })();
+                if let Some(position) = position {
+                    saves.by_game.insert(tsv::unescape(record[0]), (tsv::unescape(record[1]), position));
+                }
+            })?;
+        }
+        saves.path = path;
+        Ok(saves)
+    }
+
+    /// Who saved last in a lobby, and where.
+    pub fn last(&self, game_id: &GmString) -> Option<&(GmString, SavePosition)> {
+        self.by_game.get(game_id)
+    }
+
+    pub fn set(&mut self, game_id: &GmString, name: &GmString, position: &SavePosition) {
+        self.by_game.insert(game_id.clone(), (name.clone(), position.clone()));
+        self.dirty = true;
+    }
+
+    /// The file's new contents, if it's kept in one and anything changed since last time.
+    pub fn snapshot(&mut self) -> Option<Snapshot> {
+        let path = self.path.as_ref().filter(|_| self.dirty)?;
+        self.dirty = false;
+        let records = self.by_game.iter().map(|(game_id, (name, p))| {
+            let numbers = [p.gravity.to_string(), p.x.to_string(), p.y.to_string(), p.room.to_string()];
+            let (game_id, name) = (tsv::escape(game_id), tsv::escape(name));
+            let mut fields = vec![game_id.as_slice(), name.as_slice()];
+            fields.extend(numbers.iter().map(|n| n.as_bytes()));
+            tsv::record(&fields)
+        });
+        Some(Snapshot::rewrite(path, records.collect()))
+    }
+}
+
+/// Running totals for one game ID.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct GameStats {
+    pub joins: u64,
+    /// The most players that were ever in the lobby at once.
+    pub peak_players: u64,
+    pub saves: u64,
+    pub chats: u64,
+    pub finished_races: u64,
+}
+
+/// [`GameStats`] for every game ID that was ever played.
+///
+/// The file has one `game ID<TAB>joins<TAB>peak players<TAB>saves<TAB>chats<TAB>finished races` record per game.
+pub struct Stats {
+    path: Option<PathBuf>,
+    by_game: HashMap<GmString, GameStats>,
+    /// Whether anything changed since the last snapshot.
+    dirty: bool,
+}
+
+impl Stats {
+    pub fn load(path: Option<PathBuf>) -> io::Result<Self> {
+        let mut stats = Stats { path: None, by_game: HashMap::new(), dirty: false };
+        if let Some(path) = &path {
+            tsv::read(path, 6, |record| {
+                let numbers = record[1..].iter().map(|field| tsv::parse(field)).collect::<Option<Vec<u64>>>();
+                if let Some(&[joins, peak_players, saves, chats, finished_races]) = numbers.as_deref() {
+                    let game = GameStats { joins, peak_players, saves, chats, finished_races };
+                    stats.by_game.insert(tsv::unescape(record[0]), game);
+                }
+            })?;
+        }
+        stats.path = path;
+        Ok(stats)
+    }
+
+    pub fn get(&self, game_id: &GmString) -> GameStats {
+        self.by_game.get(game_id).cloned().unwrap_or_default()
+    }
+
+    pub fn update(&mut self, game_id: &GmString, f: impl FnOnce(&mut GameStats)) {
+        f(self.by_game.entry(game_id.clone()).or_default());
+        self.dirty = true;
+    }
+
+    /// The file's new contents, if it's kept in one and anything changed since last time.
+    pub fn snapshot(&mut self) -> Option<Snapshot> {
+        let path = self.path.as_ref().filter(|_| self.dirty)?;
+        self.dirty = false;
+        let records = self.by_game.iter().map(|(game_id, s)| {
+            let numbers = [s.joins, s.peak_players, s.saves, s.chats, s.finished_races].map(|n| n.to_string());
+            let game_id = tsv::escape(game_id);
+            let mut fields = vec![game_id.as_slice()];
+            fields.extend(numbers.iter().map(|n| n.as_bytes()));
+            tsv::record(&fields)
+        });
+        Some(Snapshot::rewrite(path, records.collect()))
+    }
+}
+
+/// Addresses which may not connect. The file has one address per line.
+pub struct Bans {
+    path: Option<PathBuf>,
+    ips: HashSet<IpAddr>,
+    /// Whether anything changed since the last snapshot.
+    dirty: bool,
+}
+
+impl Bans {
+    pub fn load(path: Option<PathBuf>) -> io::Result<Self> {
+        let mut bans = Bans { path: None, ips: HashSet::new(), dirty: false };
+        if let Some(path) = &path {
+            tsv::read(path, 1, |record| bans.ips.extend(tsv::parse::<IpAddr>(record[0])))?;
+        }
+        bans.path = path;
+        Ok(bans)
+    }
+
+    pub fn contains(&self, ip: &IpAddr) -> bool {
+        self.ips.contains(ip)
+    }
+
+    /// Returns whether the address wasn't banned already.
+    pub fn add(&mut self, ip: IpAddr) -> bool {
+        let added = self.ips.insert(ip);
+        self.dirty |= added;
+        added
+    }
+
+    /// Returns whether the address was banned.
+    pub fn remove(&mut self, ip: &IpAddr) -> bool {
+        let removed = self.ips.remove(ip);
+        self.dirty |= removed;
+        removed
+    }
+
+    /// The file's new contents, if it's kept in one and anything changed since last time.
+    pub fn snapshot(&mut self) -> Option<Snapshot> {
+        let path = self.path.as_ref().filter(|_| self.dirty)?;
+        self.dirty = false;
+        let records = self.ips.iter().map(|ip| tsv::record(&[ip.to_string().as_bytes()]));
+        Some(Snapshot::rewrite(path, records.collect()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn files_round_trip() {
+        let dir = std::env::temp_dir();
+        let file = |what| dir.join(format!("gm8po-server-{}-{}.tsv", what, std::process::id()));
+        let (saves_path, stats_path, bans_path) = (file("saves"), file("stats"), file("bans"));
+        let game: GmString = "d41d8cd98f00b204e9800998ecf8427e\tpass%word".into();
+        let position = SavePosition { gravity: 1, x: -32, y: 407.25, room: 12 };
+
+        let mut saves = Saves::load(Some(saves_path.clone())).unwrap();
+        saves.set(&game, &"Kid".into(), &SavePosition { gravity: 0, x: 0, y: 0.0, room: 1 });
+        saves.set(&game, &"Dude".into(), &position);
+        saves.set(&"other".into(), &"Kid".into(), &position);
+        saves.snapshot().unwrap().write().unwrap();
+        assert!(saves.snapshot().is_none());
+        let mut stats = Stats::load(Some(stats_path.clone())).unwrap();
+        stats.update(&game, |s| s.joins += 2);
+        stats.update(&game, |s| s.peak_players = 2);
+        stats.snapshot().unwrap().write().unwrap();
+        let mut bans = Bans::load(Some(bans_path.clone())).unwrap();
+        let (a, b) = ("10.0.0.1".parse().unwrap(), "::1".parse().unwrap());
+        assert!(bans.add(a));
+        assert!(!bans.add(a));
+        assert!(bans.add(b));
+        assert!(bans.remove(&a));
+        bans.snapshot().unwrap().write().unwrap();
+        assert!(bans.snapshot().is_none());
+
+        let saves = Saves::load(Some(saves_path.clone())).unwrap();
+        let stats = Stats::load(Some(stats_path.clone())).unwrap();
+        let bans = Bans::load(Some(bans_path.clone())).unwrap();
+        for path in [saves_path, stats_path, bans_path] {
+            let _ = fs::remove_file(path);
+        }
+        assert_eq!(saves.last(&game), Some(&("Dude".into(), position)));
+        assert_eq!(saves.last(&"nothing".into()), None);
+        assert_eq!(stats.get(&game), GameStats { joins: 2, peak_players: 2, ..GameStats::default() });
+        assert_eq!(stats.get(&"other".into()), GameStats::default());
+        assert!(!bans.contains(&a) && bans.contains(&b));
+    }
+}
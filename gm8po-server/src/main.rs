@@ -0,0 +1,136 @@
+use gm8po_online::{
+    crypto::DEFAULT_NETWORK_KEY,
+    protocol::{OLDEST_COMPATIBLE_VERSION, TCP_PORT, UDP_PORT},
+};
+use gm8po_server::{
+    admin,
+    server::{Config, Server},
+};
+use std::{
+    env, io,
+    net::{IpAddr, SocketAddr},
+    path::PathBuf,
+    process, thread,
+    time::Duration,
+};
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    assert!(!args.is_empty());
+    let process_path = args[0].as_str();
+
+    let mut opts = getopts::Options::new();
+    opts.optflag("h", "help", "print this help message")
+        .optopt("b", "bind", "address to listen on (default=0.0.0.0)", "ADDR")
+        .optopt("", "tcp-port", "TCP port (default=8002)", "PORT")
+        .optopt("", "udp-port", "UDP port (default=8003)", "PORT")
+        .optopt("", "oldest-version", "oldest client version allowed to join", "VERSION")
+        .optopt("t", "timeout", "seconds of silence before a client is dropped (default=60)", "SECS")
+        .optopt("", "results", "file to keep race results in", "FILE")
+        .optopt("k", "key", "network key games were patched with", "KEY")
+        .optopt("m", "max-players", "most players a lobby can hold", "N")
+        .optopt("", "saves", "file to keep each lobby's last shared save in", "FILE")
+        .optopt("", "stats", "file to keep statistics for each game in", "FILE")
+        .optopt("", "bans", "file to keep banned addresses in", "FILE")
+        .optopt("", "admin-socket", "Unix socket to accept admin commands on", "PATH");
+
+    let matches = match opts.parse(&args[1..]) {
+        Ok(matches) => matches,
+        Err(err) => {
+            eprintln!("{}", err);
+            process::exit(1);
+        },
+    };
+
+    if matches.opt_present("h") || !matches.free.is_empty() {
+        println!(
+            "Usage: {} [options]
+
+Options:
+    -h, --help                 print this help message
+    -b, --bind <addr>          address to listen on (defaults to 0.0.0.0)
+    --tcp-port <port>          TCP port (defaults to {})
+    --udp-port <port>          UDP port (defaults to {})
+    --oldest-version <version> oldest client version allowed to join (defaults to {})
+    -t, --timeout <secs>       seconds of silence before a client is dropped (defaults to 60)
+    --results <file>           file to keep race results in, so leaderboards survive restarts
+    -k, --key <key>            network key games were patched with (defaults to the converter's default)
+    -m, --max-players <n>      most players a lobby can hold (defaults to no limit)
+    --saves <file>             file to keep each lobby's last shared save in, which late joiners get too
+    --stats <file>             file to keep statistics for each game in
+    --bans <file>              file to keep banned addresses in
+    --admin-socket <path>      Unix socket to accept admin commands on, besides standard input
+
+Patched games always use TCP {} and UDP {}, so only change the ports when something in between forwards them.
+
+{}",
+            process_path,
+            TCP_PORT,
+            UDP_PORT,
+            OLDEST_COMPATIBLE_VERSION,
+            TCP_PORT,
+            UDP_PORT,
+            admin::HELP,
+        );
+        process::exit(if matches.opt_present("h") { 0 } else { 1 });
+    }
+
+    fn parse_opt<T: std::str::FromStr>(matches: &getopts::Matches, name: &str, default: T) -> T {
+        match matches.opt_str(name) {
+            Some(value) => value.parse().unwrap_or_else(|_| {
+                eprintln!("Invalid value for --{}: {}", name, value);
+                process::exit(1);
+            }),
+            None => default,
+        }
+    }
+    let ip: IpAddr = parse_opt(&matches, "bind", IpAddr::from([0, 0, 0, 0]));
+    let config = Config {
+        tcp_addr: SocketAddr::new(ip, parse_opt(&matches, "tcp-port", TCP_PORT)),
+        udp_addr: SocketAddr::new(ip, parse_opt(&matches, "udp-port", UDP_PORT)),
+        oldest_version: matches
+            .opt_str("oldest-version")
+            .unwrap_or_else(|| OLDEST_COMPATIBLE_VERSION.to_string()),
+        timeout: Duration::from_secs(parse_opt(&matches, "timeout", 60)),
+        results: matches.opt_str("results").map(PathBuf::from),
+        network_key: matches.opt_str("key").unwrap_or_else(|| DEFAULT_NETWORK_KEY.to_string()),
+        max_players: matches.opt_str("max-players").map(|_| parse_opt(&matches, "max-players", 0)),
+        saves: matches.opt_str("saves").map(PathBuf::from),
+        stats: matches.opt_str("stats").map(PathBuf::from),
+        bans: matches.opt_str("bans").map(PathBuf::from),
+    };
+
+    let server = match Server::bind(config) {
+        Ok(server) => server,
+        Err(err) => {
+            eprintln!("Couldn't start the server: {}", err);
+            process::exit(1);
+        },
+    };
+    if let (Ok(tcp), Ok(udp)) = (server.tcp_addr(), server.udp_addr()) {
+        println!("Listening on TCP {} and UDP {}", tcp, udp);
+    }
+
+    let admin = server.admin();
+    thread::spawn(move || admin::serve(io::stdin().lock(), io::stdout(), |command| admin.execute(command)));
+    if let Some(path) = matches.opt_str("admin-socket") {
+        #[cfg(unix)]
+        {
+            let admin = server.admin();
+            thread::spawn(move || {
+                if let Err(err) = admin::serve_socket(path.as_ref(), move |command| admin.execute(command)) {
+                    eprintln!("Admin socket stopped: {}", err);
+                }
+            });
+        }
+        #[cfg(not(unix))]
+        {
+            eprintln!("--admin-socket {} needs Unix sockets, use standard input instead", path);
+            process::exit(1);
+        }
+    }
+    if let Err(err) = server.run() {
+        eprintln!("Server stopped: {}", err);
+        process::exit(1);
+    }
+}
@@ -0,0 +1,202 @@
+//! Race timing, live rankings and finished results.
+//!
+//! Times are measured on the server from the moment a racer's start message arrives,
+//! so they don't depend on the game's clock.
+
+use crate::tsv::{self, Snapshot};
+use gm8po_online::{
+    protocol::{LeaderboardEntry, RankingEntry},
+    GmString,
+};
+use std::{
+    collections::HashMap,
+    io,
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+/// How many results the leaderboard sent to games shows.
+pub const LEADERBOARD_LEN: usize = 10;
+
+pub struct Racer {
+    start: Instant,
+    room: u16,
+    splits: u32,
+    last_split: Duration,
+    finished: Option<Duration>,
+}
+
+impl Racer {
+    pub fn new(room: u16, now: Instant) -> Self {
+        Racer { start: now, room, splits: 0, last_split: Duration::ZERO, finished: None }
+    }
+
+    pub fn split(&mut self, room: u16, now: Instant) {
+        if self.finished.is_none() {
+            self.room = room;
+            self.splits += 1;
+            self.last_split = now.saturating_duration_since(self.start);
+        }
+    }
+
+    /// Stops the timer, returning the final time unless the racer had already finished.
+    pub fn finish(&mut self, now: Instant) -> Option<Duration> {
+        if self.finished.is_some() {
+            return None
+        }
+        let time = now.saturating_duration_since(self.start);
+        self.finished = Some(time);
+        Some(time)
+    }
+
+    fn time(&self, now: Instant) -> Duration {
+        self.finished.unwrap_or_else(|| now.saturating_duration_since(self.start))
+    }
+}
+
+fn millis(duration: Duration) -> u32 {
+    duration.as_millis().try_into().unwrap_or(u32::MAX)
+}
+
+/// Finished racers come first by time, then everyone else by how many rooms they got through and how quickly.
+pub fn ranking<'a>(
+    racers: impl Iterator<Item = (&'a GmString, &'a GmString, &'a Racer)>,
+    now: Instant,
+) -> Vec<RankingEntry> {
+    let mut racers = racers.collect::<Vec<_>>();
+    racers.sort_by(|(_, _, a), (_, _, b)| match (a.finished, b.finished) {
+        (Some(a), Some(b)) => a.cmp(&b),
+        (Some(_), None) => std::cmp::Ordering::Less,
+        (None, Some(_)) => std::cmp::Ordering::Greater,
+        (None, None) => b.splits.cmp(&a.splits).then(a.last_split.cmp(&b.last_split)),
+    });
+    racers
+        .into_iter()
+        .map(|(id, name, racer)| RankingEntry {
+            id: id.clone(),
+            name: name.clone(),
+            finished: racer.finished.is_some(),
+            time: millis(racer.time(now)),
+            room: racer.room,
+        })
+        .collect()
+}
+
+/// Every finished race, grouped by game ID, optionally kept in a file.
+///
+/// The file has one `game ID<TAB>name<TAB>milliseconds` record per result, see [`tsv`].
+pub struct Results {
+    path: Option<PathBuf>,
+    by_game: HashMap<GmString, Vec<LeaderboardEntry>>,
+    /// Records for the results added since the last snapshot.
+    pending: Vec<Vec<u8>>,
+}
+
+impl Results {
+    /// Loads results from `path` if it exists. New results will be appended to it.
+    pub fn load(path: Option<PathBuf>) -> io::Result<Self> {
+        let mut results = Results { path: None, by_game: HashMap::new(), pending: Vec::new() };
+        if let Some(path) = &path {
+            tsv::read(path, 3, |record| {
+                if let Some(time) = tsv::parse(record[2]) {
+                    results.insert(tsv::unescape(record[0]), LeaderboardEntry { name: tsv::unescape(record[1]), time });
+                }
+            })?;
+        }
+        results.path = path;
+        Ok(results)
+    }
+
+    fn insert(&mut self, game_id: GmString, entry: LeaderboardEntry) {
+        self.by_game.entry(game_id).or_default().push(entry);
+    }
+
+    pub fn add(&mut self, game_id: &GmString, name: &GmString, time: Duration) {
+        let entry = LeaderboardEntry { name: name.clone(), time: millis(time) };
+        if self.path.is_some() {
+            let time = entry.time.to_string();
+            self.pending.push(tsv::record(&[&tsv::escape(game_id), &tsv::escape(&entry.name), time.as_bytes()]));
+        }
+        self.insert(game_id.clone(), entry);
+    }
+
+    /// The results to append to the file, if it's kept in one and any were added since last time.
+    pub fn snapshot(&mut self) -> Option<Snapshot> {
+        let path = self.path.as_ref().filter(|_| !self.pending.is_empty())?;
+        Some(Snapshot::append(path, std::mem::take(&mut self.pending)))
+    }
+
+    /// The best [`LEADERBOARD_LEN`] results for a game, counting only each name's best.
+    pub fn leaderboard(&self, game_id: &GmString) -> Vec<LeaderboardEntry> {
+        let mut best: HashMap<&GmString, u32> = HashMap::new();
+        for entry in self.by_game.get(game_id).into_iter().flatten() {
+            let time = best.entry(&entry.name).or_insert(entry.time);
+            *time = (*time).min(entry.time);
+        }
+        let mut entries =
+            best.into_iter().map(|(name, time)| LeaderboardEntry { name: name.clone(), time }).collect::<Vec<_>>();
+        entries.sort_by(|a, b| a.time.cmp(&b.time).then_with(|| a.name.cmp(&b.name)));
+        entries.truncate(LEADERBOARD_LEN);
+        entries
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn ranking_order() {
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+        let (ids, names) = (["1", "2", "3", "4"].map(GmString::from), ["a", "b", "c", "d"].map(GmString::from));
+
+        let mut slow = Racer::new(1, start);
+        slow.finish(at(9000));
+        let mut fast = Racer::new(1, start);
+        fast.finish(at(5000));
+        assert_eq!(fast.finish(at(6000)), None);
+        let mut far = Racer::new(1, start);
+        far.split(2, at(1000));
+        far.split(3, at(2000));
+        let mut near = Racer::new(1, start);
+        near.split(2, at(500));
+
+        let racers = [&near, &slow, &far, &fast];
+        let ranking = ranking(racers.iter().enumerate().map(|(i, r)| (&ids[i], &names[i], *r)), at(10000));
+        let order = ranking.iter().map(|e| (e.name.to_string(), e.finished, e.time, e.room)).collect::<Vec<_>>();
+        assert_eq!(order, [
+            ("d".into(), true, 5000, 1),
+            ("b".into(), true, 9000, 1),
+            ("c".into(), false, 10000, 3),
+            ("a".into(), false, 10000, 2),
+        ]);
+    }
+
+    #[test]
+    fn results_file() {
+        let path = std::env::temp_dir().join(format!("gm8po-server-results-{}.tsv", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let game: GmString = "d41d8cd98f00b204e9800998ecf8427e\tpass%word".into();
+
+        let mut results = Results::load(Some(path.clone())).unwrap();
+        results.add(&game, &"Kid".into(), Duration::from_millis(9000));
+        results.add(&game, &"Kid".into(), Duration::from_millis(7000));
+        results.snapshot().unwrap().write().unwrap();
+        assert!(results.snapshot().is_none());
+        results.add(&game, &"Dude".into(), Duration::from_millis(8000));
+        let older = results.snapshot().unwrap();
+        results.add(&"other".into(), &"Kid".into(), Duration::from_millis(1000));
+        results.snapshot().unwrap().after(older).write().unwrap();
+
+        let loaded = Results::load(Some(path.clone())).unwrap();
+        let _ = fs::remove_file(&path);
+        let board = loaded.leaderboard(&game);
+        assert_eq!(board, [
+            LeaderboardEntry { name: "Kid".into(), time: 7000 },
+            LeaderboardEntry { name: "Dude".into(), time: 8000 },
+        ]);
+        assert!(loaded.leaderboard(&"nothing".into()).is_empty());
+    }
+}
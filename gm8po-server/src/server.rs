@@ -0,0 +1,1003 @@
+use crate::{
+    admin::{self, Command, Target},
+    lobby::{Bans, Saves, Stats},
+    race::{self, Racer, Results},
+    tsv::Snapshot,
+};
+use gm8po_online::{
+    crypto::{self, Direction, Session, UdpHeader},
+    hbuffer,
+    protocol::{ClientMessage, Join, ServerMessage, UdpMessage, SYSTEM_ID},
+    GmString,
+};
+use std::{
+    collections::HashMap,
+    io::{self, BufReader},
+    net::{Shutdown, SocketAddr, TcpListener, TcpStream, UdpSocket},
+    panic::{self, AssertUnwindSafe},
+    path::PathBuf,
+    sync::{mpsc, Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+
+pub struct Config {
+    pub tcp_addr: SocketAddr,
+    pub udp_addr: SocketAddr,
+
+    /// Clients reporting an older version than this get message 2 and are disconnected.
+    pub oldest_version: String,
+
+    /// How long a client may stay silent before it's dropped.
+    /// Games send a heartbeat every 3 seconds, but freeze while a dialog box is open.
+    pub timeout: Duration,
+
+    /// Where finished race times are kept between restarts, if anywhere.
+    pub results: Option<PathBuf>,
+
+    /// Has to match the key games were patched with, see [`crypto`].
+    pub network_key: String,
+
+    /// How many players a lobby can hold, if there's a limit. Anyone else is turned away with a chat message.
+    pub max_players: Option<usize>,
+
+    /// Where the last save shared in each lobby is kept, see [`Saves`].
+    pub saves: Option<PathBuf>,
+
+    /// Where statistics for each game ID are kept, see [`Stats`].
+    pub stats: Option<PathBuf>,
+
+    /// Where banned addresses are kept, see [`Bans`].
+    pub bans: Option<PathBuf>,
+}
+
+struct Player {
+    name: GmString,
+    game_id: GmString,
+    game_name: GmString,
+    tcp_addr: SocketAddr,
+
+    /// Learned from the first movement packet, since the UDP hello doesn't say who it's from.
+    udp_addr: Option<SocketAddr>,
+
+    /// Whether the player object currently exists in the game.
+    exists: bool,
+
+    /// Queue of sealed messages for this client's writer thread.
+    sender: mpsc::Sender<Vec<u8>>,
+
+    session: Session,
+    tcp_seq: u32,
+    udp_seq: u32,
+
+    /// The lowest sequence number a UDP packet from this client may still have, anything else is a replay.
+    udp_next_seq: u32,
+
+    /// Set once the game reports the start of a race.
+    racer: Option<Racer>,
+}
+
+impl Player {
+    fn send(&mut self, message: &ServerMessage) {
+        let data = self.session.seal(Direction::ToClient, self.tcp_seq, &[], &message.encode());
+        self.tcp_seq = self.tcp_seq.wrapping_add(1);
+        let _ = self.sender.send(data);
+    }
+}
+
+fn system_chat(message: &str) -> ServerMessage {
+    ServerMessage::Chat { id: SYSTEM_ID.into(), message: message.into() }
+}
+
+struct State {
+    next_id: u64,
+    players: HashMap<GmString, Player>,
+    max_players: Option<usize>,
+    results: Results,
+    saves: Saves,
+    stats: Stats,
+    bans: Bans,
+}
+
+impl State {
+    /// Sends a message to everyone in a lobby except `except`.
+    fn broadcast(&mut self, game_id: &GmString, except: Option<&GmString>, message: &ServerMessage) {
+        for (id, player) in &mut self.players {
+            if Some(id) != except && &player.game_id == game_id {
+                player.send(message);
+            }
+        }
+    }
+
+    /// Sends the current race ranking to the whole lobby.
+    fn broadcast_ranking(&mut self, game_id: &GmString) {
+        let racers = self
+            .players
+            .iter()
+            .filter(|(_, player)| &player.game_id == game_id)
+            .filter_map(|(id, player)| Some((id, &player.name, player.racer.as_ref()?)));
+        let ranking = race::ranking(racers, Instant::now());
+        self.broadcast(game_id, None, &ServerMessage::RaceRanking(ranking));
+    }
+
+    /// Adds a player to their lobby, unless it's full, in which case they're told so and `None` is returned.
+    fn join(
+        &mut self,
+        join: Join,
+        tcp_addr: SocketAddr,
+        sender: mpsc::Sender<Vec<u8>>,
+        session: Session,
+    ) -> Option<GmString> {
+        let in_lobby = self.players.values().filter(|player| player.game_id == join.game_id).count();
+        if self.max_players.is_some_and(|max| in_lobby >= max) {
+            println!("[{}] turned {} away from full lobby '{}'", tcp_addr, join.name, join.game_name);
+            let full = system_chat("This lobby is full, try again later.").encode();
+            let _ = sender.send(session.seal(Direction::ToClient, 0, &[], &full));
+            return None
+        }
+
+        self.next_id += 1;
+        let id = GmString::from(self.next_id.to_string().as_str());
+        println!("[{}] {} joined '{}' as {}", tcp_addr, join.name, join.game_name, id);
+
+        let mut player = Player {
+            name: join.name,
+            game_id: join.game_id,
+            game_name: join.game_name,
+            tcp_addr,
+            udp_addr: None,
+            exists: false,
+            sender,
+            session,
+            tcp_seq: 0,
+            udp_seq: 0,
+            udp_next_seq: 0,
+            racer: None,
+        };
+        player.send(&ServerMessage::SelfId(id.clone()));
+        for (other_id, other) in &self.players {
+            if other.game_id == player.game_id && other.exists {
+                player.send(&ServerMessage::PlayerCreated { id: other_id.clone(), name: other.name.clone() });
+            }
+        }
+        if let Some((name, position)) = self.saves.last(&player.game_id) {
+            player.send(&ServerMessage::Saved { name: name.clone(), position: position.clone() });
+        }
+        let game_id = player.game_id.clone();
+        self.players.insert(id.clone(), player);
+        let lobby_size = in_lobby as u64 + 1;
+        self.stats.update(&game_id, |stats| {
+            stats.joins += 1;
+            stats.peak_players = stats.peak_players.max(lobby_size);
+        });
+        Some(id)
+    }
+
+    fn leave(&mut self, id: &GmString) {
+        if let Some(player) = self.players.remove(id) {
+            println!("[{}] {} ({}) left", player.tcp_addr, player.name, id);
+            self.broadcast(&player.game_id, Some(id), &ServerMessage::PlayerDestroyed { id: id.clone() });
+            if player.racer.is_some() {
+                self.broadcast_ranking(&player.game_id);
+            }
+        }
+    }
+
+    fn handle(&mut self, id: &GmString, message: ClientMessage) {
+        let player = match self.players.get_mut(id) {
+            Some(player) => player,
+            None => return,
+        };
+        let broadcast = match message {
+            ClientMessage::PlayerCreated => {
+                player.exists = true;
+                ServerMessage::PlayerCreated { id: id.clone(), name: player.name.clone() }
+            },
+            ClientMessage::PlayerDestroyed => {
+                player.exists = false;
+                ServerMessage::PlayerDestroyed { id: id.clone() }
+            },
+            ClientMessage::Heartbeat | ClientMessage::Join(_) | ClientMessage::KeyExchange { .. } => return,
+            ClientMessage::Chat(message) => ServerMessage::Chat { id: id.clone(), message },
+            // racers have to get there themselves, and a patched game doesn't send saves while racing
+            ClientMessage::Saved(_) if player.racer.is_some() => return,
+            ClientMessage::Saved(position) => ServerMessage::Saved { name: player.name.clone(), position },
+            ClientMessage::RaceStart { room } => {
+                let now = Instant::now();
+                // a restart from the title screen doesn't reset the clock
+                if player.racer.is_none() {
+                    println!("[{}] {} ({}) started racing", player.tcp_addr, player.name, id);
+                    player.racer = Some(Racer::new(room, now));
+                }
+                player.send(&ServerMessage::Leaderboard(self.results.leaderboard(&player.game_id)));
+                let game_id = player.game_id.clone();
+                self.broadcast_ranking(&game_id);
+                return
+            },
+            ClientMessage::RaceSplit { room } => {
+                match &mut player.racer {
+                    Some(racer) => racer.split(room, Instant::now()),
+                    None => return,
+                }
+                let game_id = player.game_id.clone();
+                self.broadcast_ranking(&game_id);
+                return
+            },
+            ClientMessage::RaceFinish => {
+                let time = match player.racer.as_mut().and_then(|racer| racer.finish(Instant::now())) {
+                    Some(time) => time,
+                    None => return,
+                };
+                println!("[{}] {} ({}) finished in {:.2}s", player.tcp_addr, player.name, id, time.as_secs_f64());
+                let (game_id, name) = (player.game_id.clone(), player.name.clone());
+                self.results.add(&game_id, &name, time);
+                self.stats.update(&game_id, |stats| stats.finished_races += 1);
+                self.broadcast_ranking(&game_id);
+                let leaderboard = ServerMessage::Leaderboard(self.results.leaderboard(&game_id));
+                self.broadcast(&game_id, None, &leaderboard);
+                return
+            },
+        };
+        let game_id = player.game_id.clone();
+        match &broadcast {
+            ServerMessage::Chat { .. } => self.stats.update(&game_id, |stats| stats.chats += 1),
+            ServerMessage::Saved { name, position } => {
+                self.saves.set(&game_id, name, position);
+                self.stats.update(&game_id, |stats| stats.saves += 1);
+                for (other_id, other) in &mut self.players {
+                    if other_id != id && other.game_id == game_id && other.racer.is_none() {
+                        other.send(&broadcast);
+                    }
+                }
+                return
+            },
+            _ => (),
+        }
+        self.broadcast(&game_id, Some(id), &broadcast);
+    }
+
+    fn execute(&mut self, command: Command) -> String {
+        match command {
+            Command::Help => admin::HELP.into(),
+            Command::List => self.list(),
+            Command::Kick(target) => {
+                let ids = self.find(&target);
+                if ids.is_empty() {
+                    return "no such player".into()
+                }
+                for id in &ids {
+                    self.kick(id, "You were kicked from the server.");
+                }
+                format!("kicked {} player(s)", ids.len())
+            },
+            Command::Ban(target) => {
+                let ip = match target {
+                    Target::Ip(ip) => ip,
+                    Target::Id(id) => match self.players.get(&id) {
+                        Some(player) => player.tcp_addr.ip(),
+                        None => return "no such player".into(),
+                    },
+                };
+                self.bans.add(ip);
+                let ids = self.find(&Target::Ip(ip));
+                for id in &ids {
+                    self.kick(id, "You were banned from the server.");
+                }
+                format!("banned {}, kicked {} player(s)", ip, ids.len())
+            },
+            Command::Unban(ip) => match self.bans.remove(&ip) {
+                true => format!("unbanned {}", ip),
+                false => format!("{} isn't banned", ip),
+            },
+            Command::Say(message) => {
+                let chat = system_chat(&message);
+                for player in self.players.values_mut() {
+                    player.send(&chat);
+                }
+                format!("told {} player(s)", self.players.len())
+            },
+        }
+    }
+
+    fn find(&self, target: &Target) -> Vec<GmString> {
+        let matches = |id: &GmString, player: &Player| match target {
+            Target::Id(target) => id == target,
+            Target::Ip(ip) => player.tcp_addr.ip() == *ip,
+        };
+        self.players.iter().filter(|(id, player)| matches(id, player)).map(|(id, _)| id.clone()).collect()
+    }
+
+    /// Disconnects a player after telling them why. Their writer thread closes the connection once
+    /// the message is out, since the player held its only sender.
+    fn kick(&mut self, id: &GmString, reason: &str) {
+        if let Some(player) = self.players.get_mut(id) {
+            println!("[{}] kicked {} ({})", player.tcp_addr, player.name, id);
+            player.send(&system_chat(reason));
+        }
+        self.leave(id);
+    }
+
+    fn list(&self) -> String {
+        let mut lobbies: HashMap<&GmString, Vec<(&GmString, &Player)>> = HashMap::new();
+        for (id, player) in &self.players {
+            lobbies.entry(&player.game_id).or_default().push((id, player));
+        }
+        if lobbies.is_empty() {
+            return "no one is connected".into()
+        }
+        let mut lobbies = lobbies.into_iter().collect::<Vec<_>>();
+        lobbies.sort_by(|(a_id, a), (b_id, b)| a[0].1.game_name.cmp(&b[0].1.game_name).then(a_id.cmp(b_id)));
+        let mut lines = Vec::new();
+        for (game_id, mut players) in lobbies {
+            // IDs are numbers, so shorter ones come first
+            players.sort_by(|(a, _), (b, _)| a.0.len().cmp(&b.0.len()).then(a.cmp(b)));
+            let count = match self.max_players {
+                Some(max) => format!("{}/{}", players.len(), max),
+                None => players.len().to_string(),
+            };
+            let stats = self.stats.get(game_id);
+            lines.push(format!(
+                "'{}' ({}): {} playing, {} joins, at most {} at once, {} saves, {} chats, {} finished races",
+                players[0].1.game_name,
+                game_id,
+                count,
+                stats.joins,
+                stats.peak_players,
+                stats.saves,
+                stats.chats,
+                stats.finished_races,
+            ));
+            for (id, player) in players {
+                let racing = if player.racer.is_some() { ", racing" } else { "" };
+                lines.push(format!("    {} {} from {}{}", id, player.name, player.tcp_addr.ip(), racing));
+            }
+        }
+        lines.join("\n")
+    }
+}
+
+/// Runs admin commands on a running server.
+#[derive(Clone)]
+pub struct Admin(Arc<Mutex<State>>);
+
+impl Admin {
+    pub fn execute(&self, command: Command) -> String {
+        self.0.lock().unwrap().execute(command)
+    }
+}
+
+pub struct Server {
+    config: Config,
+    tcp: TcpListener,
+    udp: UdpSocket,
+    state: Arc<Mutex<State>>,
+}
+
+impl Server {
+    pub fn bind(config: Config) -> io::Result<Self> {
+        let tcp = TcpListener::bind(config.tcp_addr)?;
+        let udp = UdpSocket::bind(config.udp_addr)?;
+        let state = State {
+            next_id: 0,
+            players: HashMap::new(),
+            max_players: config.max_players,
+            results: Results::load(config.results.clone())?,
+            saves: Saves::load(config.saves.clone())?,
+            stats: Stats::load(config.stats.clone())?,
+            bans: Bans::load(config.bans.clone())?,
+        };
+        Ok(Self { config, tcp, udp, state: Arc::new(Mutex::new(state)) })
+    }
+
+    pub fn tcp_addr(&self) -> io::Result<SocketAddr> {
+        self.tcp.local_addr()
+    }
+
+    pub fn udp_addr(&self) -> io::Result<SocketAddr> {
+        self.udp.local_addr()
+    }
+
+    pub fn admin(&self) -> Admin {
+        Admin(self.state.clone())
+    }
+
+    /// Serves clients until the UDP relay or the TCP listener stops, whether from a socket error or a panic,
+    /// then writes out whatever changed and returns why it stopped.
+    pub fn run(self) -> io::Result<()> {
+        let (stopped, stop) = mpsc::channel();
+        let (udp, state) = (self.udp, self.state.clone());
+        spawn_loop("UDP relay", stopped.clone(), move || relay_udp(udp, state));
+        let (tcp, state, config) = (self.tcp, self.state.clone(), Arc::new(self.config));
+        spawn_loop("TCP listener", stopped, move || accept_clients(tcp, state, config));
+
+        let mut failed = [None, None, None, None];
+        let result = loop {
+            match stop.recv_timeout(FLUSH_INTERVAL) {
+                Ok(result) => break result,
+                Err(mpsc::RecvTimeoutError::Timeout) => flush(&self.state, &mut failed),
+                Err(mpsc::RecvTimeoutError::Disconnected) => unreachable!("both loops report how they stopped"),
+            }
+        };
+        flush(&self.state, &mut failed);
+        result
+    }
+}
+
+/// Runs one of the server's loops on its own thread, sending how it ended to `stopped`, panics included.
+fn spawn_loop(
+    name: &'static str,
+    stopped: mpsc::Sender<io::Result<()>>,
+    f: impl FnOnce() -> io::Result<()> + Send + 'static,
+) {
+    thread::spawn(move || {
+        let result = panic::catch_unwind(AssertUnwindSafe(f)).unwrap_or_else(|_| Err(io::Error::other("panicked")));
+        let _ = stopped.send(result.map_err(|err| io::Error::new(err.kind(), format!("{}: {}", name, err))));
+    });
+}
+
+/// Accepts connections, giving each client a thread. Failing to accept one isn't fatal.
+fn accept_clients(tcp: TcpListener, state: Arc<Mutex<State>>, config: Arc<Config>) -> io::Result<()> {
+    for stream in tcp.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(err) => {
+                eprintln!("Failed to accept connection: {}", err);
+                continue
+            },
+        };
+        let state = state.clone();
+        let config = config.clone();
+        thread::spawn(move || {
+            let peer = stream.peer_addr();
+            if let Err(err) = serve_client(stream, &state, &config) {
+                if let Ok(peer) = peer {
+                    println!("[{}] {}", peer, err);
+                }
+            }
+        });
+    }
+    Ok(())
+}
+
+/// How often changes to results, saves, statistics and bans are written to their files.
+const FLUSH_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Writes out whatever changed, after letting go of the state. Writes which fail are kept in `failed` to be tried
+/// again next time, together with anything newer.
+fn flush(state: &Mutex<State>, failed: &mut [Option<Snapshot>; 4]) {
+    let snapshots = {
+        let mut state = state.lock().unwrap();
+        [state.results.snapshot(), state.saves.snapshot(), state.stats.snapshot(), state.bans.snapshot()]
+    };
+    for (snapshot, failed) in snapshots.into_iter().zip(failed) {
+        let snapshot = match (snapshot, failed.take()) {
+            (Some(snapshot), Some(older)) => Some(snapshot.after(older)),
+            (snapshot, older) => snapshot.or(older),
+        };
+        if let Some(snapshot) = snapshot {
+            if let Err(err) = snapshot.write() {
+                eprintln!("Failed to write {}: {}", snapshot.path().display(), err);
+                *failed = Some(snapshot);
+            }
+        }
+    }
+}
+
+fn serve_client(stream: TcpStream, state: &Mutex<State>, config: &Config) -> io::Result<()> {
+    let peer = stream.peer_addr()?;
+    if state.lock().unwrap().bans.contains(&peer.ip()) {
+        println!("[{}] refused banned address", peer);
+        return Ok(())
+    }
+    stream.set_read_timeout(Some(config.timeout))?;
+    stream.set_nodelay(true)?;
+
+    // Writes go through a channel so a slow client can't stall whoever is broadcasting to it.
+    let (sender, receiver) = mpsc::channel::<Vec<u8>>();
+    let mut writer = stream.try_clone()?;
+    thread::spawn(move || {
+        for data in receiver {
+            if hbuffer::write_message(&mut writer, &data).is_err() {
+                break
+            }
+        }
+        let _ = writer.shutdown(Shutdown::Both);
+    });
+
+    let mut reader = BufReader::new(stream);
+    let mut sender = Some(sender);
+    let mut session: Option<Session> = None;
+    let mut seq = 0u32;
+    let mut id = None;
+    let result = loop {
+        let data = match hbuffer::read_message(&mut reader) {
+            Ok(data) => data,
+            Err(err) if matches!(err.kind(), io::ErrorKind::UnexpectedEof | io::ErrorKind::ConnectionReset) => {
+                break Ok(())
+            },
+            Err(err) => break Err(err),
+        };
+        let data = match &session {
+            Some(session) => match session.open(Direction::ToServer, seq, &data, 0) {
+                Ok(data) => data,
+                Err(err) => break Err(io::Error::new(io::ErrorKind::InvalidData, err)),
+            },
+            None => data,
+        };
+        if session.is_some() {
+            seq = seq.wrapping_add(1);
+        }
+        let message = match ClientMessage::decode(&data) {
+            Ok(message) => message,
+            Err(err) => break Err(io::Error::new(io::ErrorKind::InvalidData, err)),
+        };
+        let oldest_version = config.oldest_version.as_str();
+        let incompatible = || ServerMessage::IncompatibleVersion { oldest_version: oldest_version.into() };
+        match (&session, &id, message) {
+            (None, _, ClientMessage::KeyExchange { version, nonce }) => {
+                let sender = sender.as_ref().expect("sender is only taken on join");
+                if !is_compatible(&version.to_string(), &config.oldest_version) {
+                    println!("[{}] rejected outdated version {}", peer, version);
+                    let _ = sender.send(incompatible().encode());
+                    break Ok(())
+                }
+                let server_nonce = crypto::nonce();
+                let _ = sender.send(ServerMessage::KeyExchange { nonce: server_nonce.clone() }.encode());
+                session = Some(Session::new(config.network_key.as_bytes(), &nonce, &server_nonce));
+            },
+            // games from before messages were sealed start with a join in clear
+            (None, _, ClientMessage::Join(join)) => {
+                println!("[{}] rejected {}: outdated version {}", peer, join.name, join.version);
+                let _ = sender.as_ref().expect("sender is only taken on join").send(incompatible().encode());
+                break Ok(())
+            },
+            (Some(session), None, ClientMessage::Join(join)) => {
+                let sender = sender.take().expect("sender is only taken once");
+                id = state.lock().unwrap().join(join, peer, sender, session.clone());
+                if id.is_none() {
+                    break Ok(())
+                }
+            },
+            (Some(_), Some(id), message) => state.lock().unwrap().handle(id, message),
+            // the game only starts sending other things once it has joined
+            _ => (),
+        }
+    };
+
+    if let Some(id) = id {
+        state.lock().unwrap().leave(&id);
+    }
+    result
+}
+
+fn relay_udp(udp: UdpSocket, state: Arc<Mutex<State>>) -> io::Result<()> {
+    let mut buf = vec![0u8; 0x10000];
+    loop {
+        let (len, src) = match udp.recv_from(&mut buf) {
+            Ok(received) => received,
+            // Windows reports ICMP port unreachable from an earlier send_to as an error here
+            Err(err) if err.kind() == io::ErrorKind::ConnectionReset => continue,
+            Err(err) => return Err(err),
+        };
+        let data = &buf[..len];
+        // hellos and anything else that isn't sealed just get dropped
+        let (sender, header) = match UdpHeader::read(data, true) {
+            Some(UdpHeader { sender: Some(sender), seq, len }) => (sender, (seq, len)),
+            _ => continue,
+        };
+
+        let packets = {
+            let mut state = state.lock().unwrap();
+            let (payload, game_id) = match state.players.get_mut(&sender) {
+                Some(player) if header.0 >= player.udp_next_seq && player.tcp_addr.ip() == src.ip() => {
+                    let payload = match player.session.open(Direction::ToServer, header.0, data, header.1) {
+                        Ok(payload) => payload,
+                        Err(_) => continue,
+                    };
+                    match UdpMessage::decode(&payload) {
+                        Ok(UdpMessage::Moved(moved)) if moved.id == sender && moved.game_id == player.game_id => (),
+                        _ => continue,
+                    }
+                    player.udp_next_seq = header.0.wrapping_add(1);
+                    player.udp_addr = Some(src);
+                    (payload, player.game_id.clone())
+                },
+                _ => continue,
+            };
+            // each recipient gets the movement sealed with their own key
+            let mut packets = Vec::new();
+            for (id, player) in &mut state.players {
+                if let (true, Some(addr)) = (*id != sender && player.game_id == game_id, player.udp_addr) {
+                    packets.push((player.session.seal_udp(Direction::ToClient, player.udp_seq, None, &payload), addr));
+                    player.udp_seq = player.udp_seq.wrapping_add(1);
+                }
+            }
+            packets
+        };
+        for (packet, target) in packets {
+            let _ = udp.send_to(&packet, target);
+        }
+    }
+}
+
+/// Compares dotted version strings numerically, treating missing or non-numeric parts as 0.
+pub fn is_compatible(version: &str, oldest: &str) -> bool {
+    let parse = |v: &str| v.split('.').map(|part| part.trim().parse::<u32>().unwrap_or(0)).collect::<Vec<_>>();
+    let (mut version, mut oldest) = (parse(version), parse(oldest));
+    let len = version.len().max(oldest.len());
+    version.resize(len, 0);
+    oldest.resize(len, 0);
+    version >= oldest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gm8po_online::protocol::{Moved, SavePosition, VERSION};
+    use std::io::Write;
+
+    #[test]
+    fn version_comparison() {
+        assert!(is_compatible("1.1.9", "1.1.9"));
+        assert!(is_compatible("1.1.10", "1.1.9"));
+        assert!(is_compatible("1.2", "1.1.9"));
+        assert!(is_compatible("1.1.9.0", "1.1.9"));
+        assert!(!is_compatible("1.1.8", "1.1.9"));
+        assert!(!is_compatible("1.1", "1.1.9"));
+        assert!(!is_compatible("", "1.1.9"));
+    }
+
+    struct Client {
+        tcp: BufReader<TcpStream>,
+        udp: UdpSocket,
+        session: Option<Session>,
+        seqs: [u32; 4],
+    }
+
+    impl Client {
+        /// Connects and joins, doing the key exchange first unless `version` predates it.
+        fn connect(server: &(SocketAddr, SocketAddr), name: &str, game_id: &str, version: &str) -> Self {
+            let tcp = TcpStream::connect(server.0).unwrap();
+            tcp.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+            let udp = UdpSocket::bind("127.0.0.1:0").unwrap();
+            udp.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+            udp.connect(server.1).unwrap();
+            let mut client = Client { tcp: BufReader::new(tcp), udp, session: None, seqs: [0; 4] };
+            if is_compatible(version, "1.3.0") {
+                let nonce = crypto::nonce();
+                client.send(ClientMessage::KeyExchange { version: version.into(), nonce: nonce.clone() });
+                match client.receive() {
+                    ServerMessage::KeyExchange { nonce: server_nonce } => {
+                        let key = crypto::DEFAULT_NETWORK_KEY.as_bytes();
+                        client.session = Some(Session::new(key, &nonce, &server_nonce));
+                    },
+                    message => {
+                        assert!(matches!(message, ServerMessage::IncompatibleVersion { .. }));
+                        return client
+                    },
+                }
+            }
+            client.send(ClientMessage::Join(Join {
+                name: name.into(),
+                game_id: game_id.into(),
+                game_name: "test".into(),
+                version: version.into(),
+                has_password: false,
+            }));
+            client
+        }
+
+        fn send(&mut self, message: ClientMessage) {
+            let mut data = message.encode();
+            if let Some(session) = &self.session {
+                data = session.seal(Direction::ToServer, self.seqs[0], &[], &data);
+                self.seqs[0] += 1;
+            }
+            let mut stream = self.tcp.get_ref();
+            hbuffer::write_message(&mut stream, &data).unwrap();
+            stream.flush().unwrap();
+        }
+
+        fn receive(&mut self) -> ServerMessage {
+            let mut data = hbuffer::read_message(&mut self.tcp).unwrap();
+            if let Some(session) = &self.session {
+                data = session.open(Direction::ToClient, self.seqs[1], &data, 0).unwrap();
+                self.seqs[1] += 1;
+            }
+            ServerMessage::decode(&data).unwrap()
+        }
+
+        fn self_id(&mut self) -> GmString {
+            match self.receive() {
+                ServerMessage::SelfId(id) => id,
+                message => panic!("expected self ID, got {:?}", message),
+            }
+        }
+
+        fn moved_packet(&mut self, id: &GmString, game_id: &str, x: i32) -> Vec<u8> {
+            let moved = Moved {
+                id: id.clone(),
+                game_id: game_id.into(),
+                room: 1,
+                time: 0,
+                x,
+                y: 0,
+                sprite_index: 0,
+                image_speed: 0.0,
+                image_xscale: 1.0,
+                image_yscale: 1.0,
+                image_angle: 0.0,
+                name: "".into(),
+                form: 0,
+            };
+            let session = self.session.as_ref().unwrap();
+            let payload = UdpMessage::Moved(moved).encode();
+            let packet = session.seal_udp(Direction::ToServer, self.seqs[2], Some(id), &payload);
+            self.seqs[2] += 1;
+            packet
+        }
+
+        fn moved(&mut self, id: &GmString, game_id: &str, x: i32) {
+            let packet = self.moved_packet(id, game_id, x);
+            self.udp.send(&packet).unwrap();
+        }
+
+        fn receive_moved(&mut self) -> Moved {
+            let mut buf = [0u8; 1024];
+            let len = self.udp.recv(&mut buf).unwrap();
+            let header = UdpHeader::read(&buf[..len], false).unwrap();
+            assert!(header.seq >= self.seqs[3]);
+            self.seqs[3] = header.seq + 1;
+            let payload = self.session.as_ref().unwrap().open(Direction::ToClient, header.seq, &buf[..len], header.len);
+            match UdpMessage::decode(&payload.unwrap()).unwrap() {
+                UdpMessage::Moved(moved) => moved,
+                message => panic!("expected movement, got {:?}", message),
+            }
+        }
+
+        fn expect_no_movement(&self) {
+            self.udp.set_read_timeout(Some(Duration::from_millis(100))).unwrap();
+            assert!(self.udp.recv(&mut [0u8; 1024]).is_err());
+            self.udp.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        }
+    }
+
+    fn start_with(configure: impl FnOnce(&mut Config)) -> ((SocketAddr, SocketAddr), Admin) {
+        let mut config = Config {
+            tcp_addr: "127.0.0.1:0".parse().unwrap(),
+            udp_addr: "127.0.0.1:0".parse().unwrap(),
+            oldest_version: VERSION.into(),
+            timeout: Duration::from_secs(60),
+            results: None,
+            network_key: crypto::DEFAULT_NETWORK_KEY.into(),
+            max_players: None,
+            saves: None,
+            stats: None,
+            bans: None,
+        };
+        configure(&mut config);
+        let server = Server::bind(config).unwrap();
+        let addrs = (server.tcp_addr().unwrap(), server.udp_addr().unwrap());
+        let admin = server.admin();
+        thread::spawn(move || server.run());
+        (addrs, admin)
+    }
+
+    fn start() -> (SocketAddr, SocketAddr) {
+        start_with(|_| ()).0
+    }
+
+    fn system_chat(message: &str) -> ServerMessage {
+        ServerMessage::Chat { id: SYSTEM_ID.into(), message: message.into() }
+    }
+
+    #[test]
+    fn outdated_client() {
+        let server = start();
+        let mut client = Client::connect(&server, "old", "game", "1.0.0");
+        assert_eq!(client.receive(), ServerMessage::IncompatibleVersion { oldest_version: VERSION.into() });
+    }
+
+    #[test]
+    fn unsealed_message() {
+        let server = start();
+        let mut client = Client::connect(&server, "a", "game", VERSION);
+        client.self_id();
+        client.session = None;
+        client.send(ClientMessage::Chat("hi".into()));
+        assert!(hbuffer::read_message(&mut client.tcp).is_err());
+    }
+
+    #[test]
+    fn forged_movement() {
+        let server = start();
+        let mut a = Client::connect(&server, "a", "game", VERSION);
+        let a_id = a.self_id();
+        let mut b = Client::connect(&server, "b", "game", VERSION);
+        let b_id = b.self_id();
+        b.moved(&b_id, "game", 0);
+        a.moved(&a_id, "game", 1);
+        assert_eq!(b.receive_moved().x, 1);
+
+        // replayed, tampered with, or claiming to be someone else
+        let packet = a.moved_packet(&a_id, "game", 2);
+        a.udp.send(&packet).unwrap();
+        assert_eq!(b.receive_moved().x, 2);
+        a.udp.send(&packet).unwrap();
+        let mut packet = a.moved_packet(&a_id, "game", 3);
+        let last = packet.len() - 1;
+        packet[last] ^= 1;
+        a.udp.send(&packet).unwrap();
+        let packet = a.moved_packet(&b_id, "game", 4);
+        a.udp.send(&packet).unwrap();
+        let mut moved = UdpMessage::Moved(Moved {
+            id: a_id.clone(),
+            game_id: "game".into(),
+            room: 1,
+            time: 0,
+            x: 5,
+            y: 0,
+            sprite_index: 0,
+            image_speed: 0.0,
+            image_xscale: 1.0,
+            image_yscale: 1.0,
+            image_angle: 0.0,
+            name: "".into(),
+            form: 0,
+        })
+        .encode();
+        a.udp.send(&moved).unwrap();
+        moved.insert(0, crypto::UDP_SEALED);
+        a.udp.send(&moved).unwrap();
+        b.expect_no_movement();
+    }
+
+    #[test]
+    fn lobby() {
+        let server = start();
+        let mut a = Client::connect(&server, "a", "game", VERSION);
+        let a_id = a.self_id();
+        a.send(ClientMessage::PlayerCreated);
+
+        // joining late still shows who's already playing
+        let mut b = Client::connect(&server, "b", "game", VERSION);
+        let b_id = b.self_id();
+        assert_ne!(a_id, b_id);
+        assert_eq!(b.receive(), ServerMessage::PlayerCreated { id: a_id.clone(), name: "a".into() });
+
+        // someone playing another game (or with another password) shouldn't see anything
+        let mut c = Client::connect(&server, "c", "other game", VERSION);
+        let c_id = c.self_id();
+
+        b.send(ClientMessage::PlayerCreated);
+        assert_eq!(a.receive(), ServerMessage::PlayerCreated { id: b_id.clone(), name: "b".into() });
+        b.send(ClientMessage::Chat("hi".into()));
+        assert_eq!(a.receive(), ServerMessage::Chat { id: b_id.clone(), message: "hi".into() });
+        let position = SavePosition { gravity: 0, x: 32, y: 64.4, room: 5 };
+        a.send(ClientMessage::Saved(position.clone()));
+        assert_eq!(b.receive(), ServerMessage::Saved { name: "a".into(), position });
+
+        // UDP addresses are learned from the first movement, then relayed within the lobby
+        a.moved(&a_id, "game", 1);
+        c.moved(&c_id, "other game", 3);
+        b.moved(&b_id, "game", 2);
+        let moved = a.receive_moved();
+        assert_eq!((moved.id, moved.x), (b_id.clone(), 2));
+        a.moved(&a_id, "game", 4);
+        let moved = b.receive_moved();
+        assert_eq!((moved.id, moved.x), (a_id.clone(), 4));
+
+        drop(b);
+        assert_eq!(a.receive(), ServerMessage::PlayerDestroyed { id: b_id });
+
+        // the lobby sees the ranking, the racer also gets the leaderboard
+        a.send(ClientMessage::RaceStart { room: 2 });
+        assert_eq!(a.receive(), ServerMessage::Leaderboard(Vec::new()));
+        assert!(matches!(a.receive(), ServerMessage::RaceRanking(r) if r.len() == 1 && r[0].room == 2));
+        a.send(ClientMessage::RaceSplit { room: 3 });
+        assert!(matches!(a.receive(), ServerMessage::RaceRanking(r) if r[0].room == 3));
+        a.send(ClientMessage::RaceFinish);
+        assert!(matches!(a.receive(), ServerMessage::RaceRanking(r) if r[0].finished && r[0].id == a_id));
+        match a.receive() {
+            ServerMessage::Leaderboard(board) => assert_eq!(board[0].name, "a".into()),
+            message => panic!("expected leaderboard, got {:?}", message),
+        }
+        a.send(ClientMessage::RaceFinish);
+
+        c.expect_no_movement();
+        c.tcp.get_ref().set_read_timeout(Some(Duration::from_millis(100))).unwrap();
+        assert!(hbuffer::read_message(&mut c.tcp).is_err());
+    }
+
+    #[test]
+    fn racers_dont_share_saves() {
+        let server = start();
+        let mut a = Client::connect(&server, "a", "game", VERSION);
+        a.self_id();
+        let mut b = Client::connect(&server, "b", "game", VERSION);
+        b.self_id();
+        b.send(ClientMessage::RaceStart { room: 2 });
+        assert_eq!(b.receive(), ServerMessage::Leaderboard(Vec::new()));
+        assert!(matches!(b.receive(), ServerMessage::RaceRanking(_)));
+        assert!(matches!(a.receive(), ServerMessage::RaceRanking(_)));
+
+        let position = SavePosition { gravity: 0, x: 32, y: 64.4, room: 5 };
+        a.send(ClientMessage::Saved(position.clone()));
+        b.send(ClientMessage::Saved(position));
+        for client in [&mut a, &mut b] {
+            client.tcp.get_ref().set_read_timeout(Some(Duration::from_millis(100))).unwrap();
+            assert!(hbuffer::read_message(&mut client.tcp).is_err());
+        }
+    }
+
+    #[test]
+    fn late_joiner_gets_last_save() {
+        let server = start();
+        let mut a = Client::connect(&server, "a", "game", VERSION);
+        a.self_id();
+        let position = SavePosition { gravity: 0, x: 32, y: 64.4, room: 5 };
+        a.send(ClientMessage::Saved(position.clone()));
+
+        let mut b = Client::connect(&server, "b", "game", VERSION);
+        b.self_id();
+        assert_eq!(b.receive(), ServerMessage::Saved { name: "a".into(), position });
+        let mut c = Client::connect(&server, "c", "other game", VERSION);
+        c.self_id();
+        c.tcp.get_ref().set_read_timeout(Some(Duration::from_millis(100))).unwrap();
+        assert!(hbuffer::read_message(&mut c.tcp).is_err());
+    }
+
+    #[test]
+    fn full_lobby() {
+        let (server, admin) = start_with(|config| config.max_players = Some(1));
+        let mut a = Client::connect(&server, "a", "game", VERSION);
+        a.self_id();
+        let mut b = Client::connect(&server, "b", "game", VERSION);
+        assert_eq!(b.receive(), system_chat("This lobby is full, try again later."));
+        assert!(hbuffer::read_message(&mut b.tcp).is_err());
+        let mut c = Client::connect(&server, "c", "other game", VERSION);
+        c.self_id();
+        assert!(admin.execute(Command::List).contains("1/1 playing, 1 joins"));
+    }
+
+    #[test]
+    fn admin_commands() {
+        let (server, admin) = start_with(|_| ());
+        let mut a = Client::connect(&server, "a", "game", VERSION);
+        let a_id = a.self_id();
+        let mut b = Client::connect(&server, "b", "game", VERSION);
+        let b_id = b.self_id();
+
+        assert_eq!(admin.execute(Command::Say("restarting soon".into())), "told 2 player(s)");
+        assert_eq!(a.receive(), system_chat("restarting soon"));
+        assert_eq!(b.receive(), system_chat("restarting soon"));
+        let list = admin.execute(Command::List);
+        assert!(list.contains(&format!("{} a from 127.0.0.1", a_id)) && list.contains("2 playing"));
+
+        assert_eq!(admin.execute(Command::Kick(Target::Id(b_id.clone()))), "kicked 1 player(s)");
+        assert_eq!(b.receive(), system_chat("You were kicked from the server."));
+        assert!(hbuffer::read_message(&mut b.tcp).is_err());
+        assert_eq!(a.receive(), ServerMessage::PlayerDestroyed { id: b_id.clone() });
+        assert_eq!(admin.execute(Command::Kick(Target::Id(b_id))), "no such player");
+
+        assert_eq!(admin.execute(Command::Ban(Target::Id(a_id))), "banned 127.0.0.1, kicked 1 player(s)");
+        assert_eq!(a.receive(), system_chat("You were banned from the server."));
+        // refused before the key exchange
+        let mut refused = TcpStream::connect(server.0).unwrap();
+        refused.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        assert!(hbuffer::read_message(&mut refused).is_err());
+        assert_eq!(admin.execute(Command::Unban("127.0.0.1".parse().unwrap())), "unbanned 127.0.0.1");
+        let mut c = Client::connect(&server, "c", "game", VERSION);
+        c.self_id();
+    }
+
+    #[test]
+    fn loops_report_stopping() {
+        let (stopped, stop) = mpsc::channel();
+        spawn_loop("failing", stopped.clone(), || Err(io::ErrorKind::AddrInUse.into()));
+        let err = stop.recv().unwrap().unwrap_err();
+        assert_eq!((err.kind(), err.to_string().starts_with("failing: ")), (io::ErrorKind::AddrInUse, true));
+        spawn_loop("panicking", stopped, || panic!("on purpose"));
+        assert_eq!(stop.recv().unwrap().unwrap_err().to_string(), "panicking: panicked");
+    }
+}
@@ -0,0 +1,129 @@
+//! The tab-separated files the server keeps its state in.
+//!
+//! Each record is a line of fields separated by tabs. Fields are game strings, so bytes which would
+//! break a line up are written as `%XX`.
+
+use gm8po_online::GmString;
+use std::{
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+pub fn escape(s: &GmString) -> Vec<u8> {
+    let mut out = Vec::with_capacity(s.0.len());
+    for &b in s.0.iter() {
+        if b < 0x20 || b == b'%' || b == 0x7F {
+            out.extend_from_slice(format!("%{:02X}", b).as_bytes());
+        } else {
+            out.push(b);
+        }
+    }
+    out
+}
+
+pub fn unescape(s: &[u8]) -> GmString {
+    let mut out = Vec::with_capacity(s.len());
+    let mut i = 0;
+    while i < s.len() {
+        let hex = s.get(i + 1..i + 3).and_then(|h| u8::from_str_radix(std::str::from_utf8(h).ok()?, 16).ok());
+        match (s[i], hex) {
+            (b'%', Some(b)) => {
+                out.push(b);
+                i += 3;
+            },
+            (b, _) => {
+                out.push(b);
+                i += 1;
+            },
+        }
+    }
+    GmString(out.into_boxed_slice())
+}
+
+/// Parses a field which was written with `to_string`.
+pub fn parse<T: std::str::FromStr>(field: &[u8]) -> Option<T> {
+    std::str::from_utf8(field).ok()?.parse().ok()
+}
+
+/// Calls `f` with every record that has exactly `fields` fields, skipping anything else.
+/// A missing file has no records.
+pub fn read(path: &Path, fields: usize, mut f: impl FnMut(&[&[u8]])) -> io::Result<()> {
+    let data = match fs::read(path) {
+        Ok(data) => data,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(err) => return Err(err),
+    };
+    for line in data.split(|&b| b == b'\n') {
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        let record = line.split(|&b| b == b'\t').collect::<Vec<_>>();
+        if record.len() == fields {
+            f(&record);
+        }
+    }
+    Ok(())
+}
+
+/// Turns already escaped fields into a record.
+pub fn record(fields: &[&[u8]]) -> Vec<u8> {
+    let mut line = fields.join(&b'\t');
+    line.push(b'\n');
+    line
+}
+
+/// Adds a record to the end of the file.
+pub fn append(path: &Path, record: &[u8]) -> io::Result<()> {
+    fs::OpenOptions::new().create(true).append(true).open(path)?.write_all(record)
+}
+
+/// Replaces the whole file, going through a temporary file so that a crash can't leave half of it behind.
+pub fn rewrite(path: &Path, records: impl IntoIterator<Item = Vec<u8>>) -> io::Result<()> {
+    let mut temp = path.as_os_str().to_owned();
+    temp.push(".tmp");
+    let mut data = Vec::new();
+    for record in records {
+        data.extend(record);
+    }
+    fs::write(&temp, data)?;
+    fs::rename(&temp, path)
+}
+
+/// Records for a file, taken while the state is locked and written after it's released.
+pub struct Snapshot {
+    path: PathBuf,
+    records: Vec<Vec<u8>>,
+    /// Whether the records go after what's in the file already, rather than replacing it.
+    append: bool,
+}
+
+impl Snapshot {
+    /// Everything the file should contain now.
+    pub fn rewrite(path: &Path, records: Vec<Vec<u8>>) -> Self {
+        Snapshot { path: path.to_owned(), records, append: false }
+    }
+
+    /// Records to add to the end of the file.
+    pub fn append(path: &Path, records: Vec<Vec<u8>>) -> Self {
+        Snapshot { path: path.to_owned(), records, append: true }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Combines this with an older snapshot of the same file which couldn't be written.
+    pub fn after(mut self, older: Snapshot) -> Self {
+        if self.append {
+            self.records.splice(0..0, older.records);
+        }
+        self
+    }
+
+    pub fn write(&self) -> io::Result<()> {
+        if self.append {
+            append(&self.path, &self.records.concat())
+        } else {
+            rewrite(&self.path, self.records.iter().cloned())
+        }
+    }
+}
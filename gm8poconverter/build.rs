@@ -1,4 +1,4 @@
-use std::{env, error::Error, process::Command};
+use std::{env, error::Error, fs, path::Path, process::Command};
 use time::OffsetDateTime;
 
 fn main() -> Result<(), Box<dyn Error>> {
@@ -15,6 +15,10 @@ fn main() -> Result<(), Box<dyn Error>> {
     let target_triple = env::var("TARGET")?;
     println!("cargo:rustc-env=TARGET_TRIPLE={}", target_triple);
 
+    // http dll - bundled when there's one in src/res, otherwise left empty so the converter looks elsewhere
+    let http_dll = Path::new(&env::var("OUT_DIR")?).join("http_dll_2_3.dll");
+    fs::write(http_dll, fs::read("src/res/http_dll_2_3.dll").unwrap_or_default())?;
+
     // icon
     #[cfg(target_os = "windows")]
     {
@@ -0,0 +1,291 @@
+//! The fangame engines the online patch knows how to hook into.
+//!
+//! Every engine gets the templates under `gml/online/`. Where engines differ, such as in how the kid faces left
+//! and flips, and which scripts do the saving, the templates have placeholders which each profile fills in.
+
+use gm8exe::GameAssets;
+use std::collections::HashSet;
+
+/// The GML injected for an engine.
+pub struct Templates {
+    pub world_create: &'static str,
+    pub world_end_step: &'static str,
+    pub world_game_end: &'static str,
+    pub world_end_step_record: &'static str,
+    pub online_player_create: &'static str,
+    pub online_player_end_step: &'static str,
+    pub online_player_draw: &'static str,
+    pub chatbox_create: &'static str,
+    pub chatbox_end_step: &'static str,
+    pub chatbox_draw: &'static str,
+    pub player_saved_draw: &'static str,
+    pub player_saved_end_step: &'static str,
+
+    /// Appended to the engine's save script.
+    pub save: &'static str,
+    /// Appended to the engine's load script.
+    pub load: &'static str,
+}
+
+pub static TEMPLATES: Templates = Templates {
+    world_create: include_str!("./gml/online/World_Create.gml"),
+    world_end_step: include_str!("./gml/online/World_EndStep.gml"),
+    world_game_end: include_str!("./gml/online/World_GameEnd.gml"),
+    world_end_step_record: include_str!("./gml/online/World_EndStep_Record.gml"),
+    online_player_create: include_str!("./gml/online/__ONLINE_onlinePlayer_Create.gml"),
+    online_player_end_step: include_str!("./gml/online/__ONLINE_onlinePlayer_EndStep.gml"),
+    online_player_draw: include_str!("./gml/online/__ONLINE_onlinePlayer_Draw.gml"),
+    chatbox_create: include_str!("./gml/online/__ONLINE_chatbox_Create.gml"),
+    chatbox_end_step: include_str!("./gml/online/__ONLINE_chatbox_EndStep.gml"),
+    chatbox_draw: include_str!("./gml/online/__ONLINE_chatbox_Draw.gml"),
+    player_saved_draw: include_str!("./gml/online/__ONLINE_playerSaved_Draw.gml"),
+    player_saved_end_step: include_str!("./gml/online/__ONLINE_playerSaved_EndStep.gml"),
+    save: include_str!("./gml/online/save.gml"),
+    load: include_str!("./gml/online/load.gml"),
+};
+
+/// A second player object which the player can turn into, such as an upside-down kid.
+pub struct SecondPlayer {
+    /// Names the object goes by, in order of preference.
+    pub names: &'static [&'static str],
+
+    /// Whether it's the first player flipped vertically, using the same sprites.
+    /// Its remote copies are then drawn with a negated image_yscale.
+    pub flipped: bool,
+}
+
+pub struct Profile {
+    pub name: &'static str,
+
+    /// Scripts which games made with this engine have, including the save and load scripts.
+    /// Some are common names, so a game has to have every one of them, and the world and player objects too.
+    pub markers: &'static [&'static str],
+
+    /// Anti-aliasing level for the player name font, to match the engine's own text.
+    pub font_aa_level: u32,
+
+    /// What the world and player objects are called, which the templates refer to as `World` and `Player`.
+    pub world_object: &'static str,
+    pub player_object: &'static str,
+
+    /// The engine's save and load scripts, which get the `save` and `load` templates appended.
+    pub save_script: &'static str,
+    pub load_script: &'static str,
+
+    /// `$SAVE_CONDITION`, whether a call to the save script is the player saving, rather than the engine saving
+    /// on the title screen or for some other reason.
+    pub save_condition: &'static str,
+
+    /// `$SAVE_GRAVITY`, the player's gravity as sent with a save, and `$LOAD_GRAVITY`, which sets it again from
+    /// `__ONLINE_sGravity` when someone else's save is loaded.
+    pub save_gravity: &'static str,
+    pub load_gravity: &'static str,
+
+    /// `$XSCALE` and `$YSCALE`, the image scales of the player `__ONLINE_p` as everyone else should draw it.
+    /// Engines which face left or flip the kid without touching image_xscale and image_yscale fold that in here.
+    pub xscale: &'static str,
+    pub yscale: &'static str,
+
+    /// Rooms which usually hold the ending, in order of preference.
+    /// Reaching one of them finishes a race, unless `--finish-room` says otherwise.
+    pub finish_rooms: &'static [&'static str],
+
+    /// The second player object, if the engine has one.
+    pub player2: Option<SecondPlayer>,
+}
+
+impl Profile {
+    /// Whether a game has this engine's marker scripts, and its world and player objects.
+    fn matches(&self, has_script: impl Fn(&str) -> bool, has_object: impl Fn(&str) -> bool) -> bool {
+        self.markers.iter().all(|&name| has_script(name))
+            && has_object(self.world_object)
+            && has_object(self.player_object)
+    }
+
+    /// Fills in this engine's placeholders in one of the templates.
+    pub fn fill(&self, template: &str) -> String {
+        template
+            .replace("$SAVE_CONDITION", self.save_condition)
+            .replace("$SAVE_GRAVITY", self.save_gravity)
+            .replace("$LOAD_GRAVITY", self.load_gravity)
+            .replace("$XSCALE", self.xscale)
+            .replace("$YSCALE", self.yscale)
+    }
+}
+
+/// Rooms the Renex and Verve engines save in before there's a game to save.
+const MENU_ROOMS: &str = "room != rmInit && room != rmTitle && room != rmMenu && room != rmOptions";
+
+pub static VERVE: Profile = Profile {
+    name: "Verve",
+    markers: &["save_save", "save_load", "player_air_jump"],
+    font_aa_level: 3,
+    world_object: "World",
+    player_object: "Player",
+    save_script: "save_save",
+    load_script: "save_load",
+    save_condition: MENU_ROOMS,
+    save_gravity: "global.grav",
+    load_gravity: "global.grav = __ONLINE_sGravity;",
+    // the kid faces left with x_scale and flips with global.grav, neither of which touches the image scales
+    xscale: "__ONLINE_p.image_xscale * __ONLINE_p.x_scale",
+    yscale: "__ONLINE_p.image_yscale * global.grav",
+    finish_rooms: &["rEnd", "rEnding", "rClear"],
+    // gravity flips are folded into yscale instead
+    player2: None,
+};
+
+pub static RENEX: Profile = Profile {
+    name: "Renex",
+    markers: &["custom_sound_properties", "savedata_save", "savedata_load"],
+    font_aa_level: 4,
+    world_object: "World",
+    player_object: "Player",
+    save_script: "savedata_save",
+    load_script: "savedata_load",
+    save_condition: MENU_ROOMS,
+    save_gravity: "__ONLINE_p != Player",
+    load_gravity: "global.grav = __ONLINE_sGravity;",
+    xscale: "__ONLINE_p.image_xscale",
+    yscale: "__ONLINE_p.image_yscale",
+    finish_rooms: &["rmEnd", "rmEnding", "rmClear"],
+    player2: Some(SecondPlayer { names: &["Player2"], flipped: true }),
+};
+
+pub static YUUUTU: Profile = Profile {
+    name: "Yuuutu",
+    markers: &["saveGame", "loadGame"],
+    font_aa_level: 3,
+    world_object: "world",
+    player_object: "player",
+    save_script: "saveGame",
+    load_script: "loadGame",
+    // saveGame also runs on the title screen when starting a new game, but there's no player there to send
+    save_condition: "true",
+    save_gravity: "__ONLINE_p != Player",
+    load_gravity: "// there's no global.grav, the player comes back in whichever form the loaded save has",
+    // the kid faces left with image_xscale and flips by turning into the second player object
+    xscale: "__ONLINE_p.image_xscale",
+    yscale: "__ONLINE_p.image_yscale",
+    finish_rooms: &["rEnd", "rEnding", "rClear"],
+    player2: Some(SecondPlayer { names: &["player2"], flipped: true }),
+};
+
+// Saves to a ds_map, and flips the kid by setting global.grav to -1
+pub static KAYIN: Profile = Profile {
+    name: "Kayin",
+    markers: &["scrSaveGame", "scrLoadGame"],
+    font_aa_level: 3,
+    world_object: "objWorld",
+    player_object: "objPlayer",
+    save_script: "scrSaveGame",
+    load_script: "scrLoadGame",
+    // scrSaveGame(false) only stores things like the death count
+    save_condition: "argument0",
+    save_gravity: "global.grav == -1",
+    load_gravity: "global.grav = 1-2*__ONLINE_sGravity;",
+    // the kid faces left with xScale, and global.grav is -1 when flipped
+    xscale: "__ONLINE_p.image_xscale * __ONLINE_p.xScale",
+    yscale: "__ONLINE_p.image_yscale * global.grav",
+    finish_rooms: &["rEnd", "rEnding", "rClear"],
+    player2: None,
+};
+
+// Saves to an ini file, and flips the kid by setting global.flip
+pub static SEPH: Profile = Profile {
+    name: "Seph",
+    markers: &["scrSaveIni", "scrLoadIni"],
+    font_aa_level: 3,
+    world_object: "oWorld",
+    player_object: "oPlayer",
+    save_script: "scrSaveIni",
+    load_script: "scrLoadIni",
+    // the title screen saves before there's a player, which the template already skips
+    save_condition: "true",
+    save_gravity: "global.flip",
+    load_gravity: "global.flip = __ONLINE_sGravity;",
+    // the kid faces left with its facing variable, and global.flip is 1 when flipped
+    xscale: "__ONLINE_p.image_xscale * __ONLINE_p.facing",
+    yscale: "__ONLINE_p.image_yscale * (1-2*global.flip)",
+    finish_rooms: &["rmEnd", "rmEnding", "rmClear"],
+    player2: None,
+};
+
+pub static PROFILES: &[&Profile] = &[&VERVE, &RENEX, &YUUUTU, &KAYIN, &SEPH];
+
+/// Finds the engine a game was made with, which has to be the only one the game matches.
+pub fn detect(assets: &GameAssets) -> Result<&'static Profile, String> {
+    let scripts = assets.scripts.iter().flatten().map(|s| s.name.to_string()).collect::<HashSet<_>>();
+    let objects = assets.objects.iter().flatten().map(|o| o.name.to_string()).collect::<HashSet<_>>();
+    detect_by(|name| scripts.contains(name), |name| objects.contains(name))
+}
+
+fn detect_by(has_script: impl Fn(&str) -> bool, has_object: impl Fn(&str) -> bool) -> Result<&'static Profile, String> {
+    let found = PROFILES.iter().copied().filter(|p| p.matches(&has_script, &has_object)).collect::<Vec<_>>();
+    match found.as_slice() {
+        [profile] => Ok(profile),
+        [] => Err("Unsupported engine! Please contact Cube.".into()),
+        _ => {
+            let names = found.iter().map(|p| p.name).collect::<Vec<_>>().join(", ");
+            Err(format!("This game could be made with any of these engines: {}. Please contact Cube.", names))
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn detect_in(scripts: &[&str], objects: &[&str]) -> Result<&'static str, String> {
+        detect_by(|name| scripts.contains(&name), |name| objects.contains(&name)).map(|p| p.name)
+    }
+
+    #[test]
+    fn detects_every_profile() {
+        for profile in PROFILES {
+            let objects = [profile.world_object, profile.player_object];
+            assert_eq!(detect_in(profile.markers, &objects), Ok(profile.name));
+        }
+    }
+
+    #[test]
+    fn needs_every_marker() {
+        assert!(detect_in(&["save_save"], &["World", "Player"]).is_err());
+        assert!(detect_in(&["scrSaveIni"], &["oWorld", "oPlayer"]).is_err());
+    }
+
+    #[test]
+    fn needs_the_objects() {
+        // common script names on their own say nothing about the engine
+        assert!(detect_in(&["saveGame", "loadGame"], &["World", "Player"]).is_err());
+        assert!(detect_in(&["scrSaveGame", "scrLoadGame"], &["World", "Player"]).is_err());
+        assert!(detect_in(&["scrSaveIni", "scrLoadIni"], &["objWorld", "objPlayer"]).is_err());
+    }
+
+    #[test]
+    fn rejects_several_matches() {
+        let scripts = [VERVE.markers, RENEX.markers].concat();
+        let error = detect_in(&scripts, &["World", "Player"]).unwrap_err();
+        assert!(error.contains("Verve") && error.contains("Renex"), "{}", error);
+    }
+
+    #[test]
+    fn fills_every_placeholder() {
+        let placeholders = ["$SAVE_CONDITION", "$SAVE_GRAVITY", "$LOAD_GRAVITY", "$XSCALE", "$YSCALE"];
+        let templates = [TEMPLATES.world_end_step, TEMPLATES.world_end_step_record, TEMPLATES.save, TEMPLATES.load];
+        for profile in PROFILES {
+            for template in templates {
+                let filled = profile.fill(template);
+                assert!(placeholders.iter().all(|p| !filled.contains(p)), "{} left a placeholder", profile.name);
+            }
+        }
+    }
+
+    #[test]
+    fn sends_the_protocol_version() {
+        let version = gm8po_online::protocol::VERSION;
+        let create = TEMPLATES.world_create;
+        assert!(create.contains(&format!("__ONLINE_version = \"{}\";", version)), "the template isn't on {}", version);
+        assert!(create.contains(&format!("// {}: The version", version)), "the header isn't on {}", version);
+    }
+}
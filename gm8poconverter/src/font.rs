@@ -0,0 +1,220 @@
+use ab_glyph::{Font as _, FontRef, InvalidFont, ScaleFont};
+use gm8exe::asset::{
+    sprite::{CollisionMap, Frame},
+    Font, Sprite,
+};
+
+/// Horizontal and vertical gap left between glyphs in the pixel map, so filtering doesn't bleed them together.
+const GLYPH_PADDING: u32 = 1;
+
+/// Where a single glyph ended up in the pixel map, plus its metrics.
+struct GlyphCell {
+    x: u32,
+    y: u32,
+    width: u32,
+    offset: i32,
+    distance: i32,
+    alpha: Vec<u8>,
+}
+
+// Rasterises a TrueType font into the font's pixel map and dmap, for every char in range_start..=range_end.
+// The size, range and aa_level are read from the font, everything else related to the glyphs is overwritten.
+pub fn rasterize(font: &mut Font, ttf: &[u8]) -> Result<(), InvalidFont> {
+    let face = FontRef::try_from_slice(ttf)?;
+    // GM8 asks Windows for a point size at 96 DPI, which is what ab_glyph assumes too.
+    let scale = face.pt_to_px_scale(font.size as f32).ok_or(InvalidFont)?;
+    let face = face.as_scaled(scale);
+    let line_height = (face.ascent() - face.descent()).ceil().max(1.0) as u32;
+
+    // Render each glyph on its own first - the dimensions decide how the pixel map gets packed.
+    let range_end = font.range_end.min(0xFF);
+    let mut cells: Vec<(u32, GlyphCell)> = Vec::new();
+    for charcode in font.range_start..=range_end {
+        let glyph_id = face.glyph_id(char::from(charcode as u8));
+        let glyph = glyph_id.with_scale_and_position(scale, ab_glyph::point(0.0, face.ascent()));
+        let distance = face.h_advance(glyph_id).round() as i32;
+        let cell = match face.outline_glyph(glyph) {
+            Some(outline) => {
+                let bounds = outline.px_bounds();
+                let width = bounds.width() as u32;
+                let top = bounds.min.y as i32;
+                let mut alpha = vec![0u8; (width * line_height) as usize];
+                outline.draw(|x, y, coverage| {
+                    let row = top + y as i32;
+                    if x < width && row >= 0 && (row as u32) < line_height {
+                        let value = if font.aa_level == 0 {
+                            if coverage >= 0.5 { 255 } else { 0 }
+                        } else {
+                            (coverage.clamp(0.0, 1.0) * 255.0).round() as u8
+                        };
+                        alpha[(row as u32 * width + x) as usize] = value;
+                    }
+                });
+                GlyphCell { x: 0, y: 0, width, offset: bounds.min.x as i32, distance, alpha }
+            },
+            // No outline (space, control characters etc.) - nothing to draw, but the cursor still moves.
+            None => GlyphCell { x: 0, y: 0, width: 0, offset: 0, distance, alpha: Vec::new() },
+        };
+        cells.push((charcode, cell));
+    }
+
+    // Pack glyphs into rows, doubling the map width until the result is no taller than it is wide.
+    let widest = cells.iter().map(|(_, c)| c.width).max().unwrap_or(0) + GLYPH_PADDING;
+    let mut map_width = widest.next_power_of_two().max(16);
+    let map_height = loop {
+        let (mut x, mut y) = (0, 0);
+        for (_, cell) in cells.iter_mut() {
+            if x + cell.width > map_width {
+                x = 0;
+                y += line_height + GLYPH_PADDING;
+            }
+            cell.x = x;
+            cell.y = y;
+            x += cell.width + GLYPH_PADDING;
+        }
+        let height = y + line_height;
+        if height <= map_width {
+            break height
+        }
+        map_width *= 2;
+    };
+
+    // Blit everything into the final map and fill in the lookup table.
+    let mut pixel_map = vec![0u8; (map_width * map_height) as usize];
+    let mut dmap = [0u32; 0x600];
+    for (charcode, cell) in &cells {
+        for row in 0..line_height {
+            let src = (row * cell.width) as usize..((row + 1) * cell.width) as usize;
+            let dst = ((cell.y + row) * map_width + cell.x) as usize;
+            pixel_map[dst..dst + cell.width as usize].copy_from_slice(&cell.alpha[src]);
+        }
+        let index = *charcode as usize * 6;
+        dmap[index] = cell.x;
+        dmap[index + 1] = cell.y;
+        dmap[index + 2] = cell.width;
+        dmap[index + 3] = line_height;
+        dmap[index + 4] = cell.offset as u32;
+        dmap[index + 5] = cell.distance as u32;
+    }
+
+    font.range_end = range_end;
+    *font.dmap = dmap;
+    font.map_width = map_width;
+    font.map_height = map_height;
+    font.pixel_map = pixel_map.into_boxed_slice();
+    Ok(())
+}
+
+// Cuts a rasterised font's pixel map into a sprite with one frame per char in range_start..=range_end, each glyph
+// drawn in white at the top left of its frame. A gmk font only has room for the name of a system font, so this is
+// how the glyphs get into the game - `font_add_sprite(sprite, range_start, true, 1)` turns it back into a font.
+pub fn to_sprite(font: &Font, name: &str) -> Sprite {
+    let glyphs = (font.range_start..=font.range_end).map(|charcode| &font.dmap[charcode as usize * 6..][..6]);
+    let width = glyphs.clone().map(|glyph| glyph[2]).max().unwrap_or(0).max(1);
+    let height = glyphs.clone().map(|glyph| glyph[3]).max().unwrap_or(0).max(1);
+    let frames = glyphs
+        .map(|glyph| {
+            let (x, y, glyph_width, glyph_height) = (glyph[0], glyph[1], glyph[2], glyph[3]);
+            let mut data = vec![0u8; (width * height * 4) as usize];
+            for row in 0..glyph_height {
+                for col in 0..glyph_width {
+                    let alpha = font.pixel_map[((y + row) * font.map_width + x + col) as usize];
+                    let pixel = ((row * width + col) * 4) as usize;
+                    data[pixel..pixel + 4].copy_from_slice(&[0xFF, 0xFF, 0xFF, alpha]);
+                }
+            }
+            Frame { width, height, data: data.into_boxed_slice() }
+        })
+        .collect();
+    let collider = CollisionMap {
+        width,
+        height,
+        bbox_left: 0,
+        bbox_right: width - 1,
+        bbox_top: 0,
+        bbox_bottom: height - 1,
+        data: vec![true; (width * height) as usize].into_boxed_slice(),
+    };
+    Sprite {
+        name: name.into(),
+        origin_x: 0,
+        origin_y: 0,
+        frames,
+        colliders: vec![collider],
+        per_frame_colliders: false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TTF: &[u8] = include_bytes!("./res/DejaVuSansCondensed-Bold.ttf");
+
+    fn font(range_start: u32, range_end: u32, aa_level: u32) -> Font {
+        let mut font = Font {
+            name: "test".into(),
+            sys_name: "".into(),
+            size: 12,
+            bold: true,
+            italic: false,
+            range_start,
+            range_end,
+            charset: 0,
+            aa_level,
+            dmap: Box::new([0; 0x600]),
+            map_width: 0,
+            map_height: 0,
+            pixel_map: Box::new([]),
+        };
+        rasterize(&mut font, TTF).unwrap();
+        font
+    }
+
+    #[test]
+    fn maps_match_range() {
+        let font = font(32, 127, 1);
+        assert_eq!(font.pixel_map.len(), (font.map_width * font.map_height) as usize);
+        for charcode in 0..0x100 {
+            let glyph = &font.dmap[charcode * 6..][..6];
+            if !(32..=127).contains(&charcode) {
+                assert_eq!(glyph, [0; 6], "char {} is outside the range", charcode);
+                continue
+            }
+            let (x, y, width, height) = (glyph[0], glyph[1], glyph[2], glyph[3]);
+            assert!(height > 0 && y + height <= font.map_height, "char {} is off the bottom", charcode);
+            assert!(x + width <= font.map_width, "char {} is off the side", charcode);
+            assert!(glyph[5] > 0, "char {} doesn't move the cursor", charcode);
+            let inked = (0..height).any(|row| {
+                let start = ((y + row) * font.map_width + x) as usize;
+                font.pixel_map[start..start + width as usize].iter().any(|&alpha| alpha != 0)
+            });
+            // 127 isn't printable, but fonts tend to draw it as the missing glyph box
+            if charcode != 127 {
+                assert_eq!(inked, charcode != 32, "char {} has the wrong pixels", charcode);
+            }
+        }
+    }
+
+    #[test]
+    fn range_is_clamped() {
+        let font = font(250, 1000, 0);
+        assert_eq!(font.range_end, 255);
+        assert!(font.pixel_map.iter().all(|&alpha| alpha == 0 || alpha == 255));
+    }
+
+    #[test]
+    fn sprite_has_frame_per_char() {
+        let font = font(32, 127, 1);
+        let sprite = to_sprite(&font, "test");
+        assert_eq!(sprite.frames.len(), 96);
+        let a = &font.dmap[b'A' as usize * 6..][..6];
+        let frame = &sprite.frames[usize::from(b'A' - 32)];
+        for row in 0..a[3] {
+            for col in 0..a[2] {
+                let alpha = font.pixel_map[((a[1] + row) * font.map_width + a[0] + col) as usize];
+                assert_eq!(frame.data[((row * frame.width + col) * 4 + 3) as usize], alpha);
+            }
+        }
+    }
+}
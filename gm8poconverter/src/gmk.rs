@@ -3,6 +3,7 @@ use byteorder::{WriteBytesExt, LE};
 use flate2::{write::ZlibEncoder, Compression};
 use gm8exe::{
     asset::{self, included_file::ExportSetting, PascalString, WritePascalString},
+    rsrc::VersionInfo,
     settings::{GameHelpDialog, Settings},
     GameAssets, GameVersion,
 };
@@ -48,6 +49,7 @@ pub fn write_settings<W>(
     writer: &mut W,
     settings: &Settings,
     ico_file: Option<Vec<u8>>,
+    version_info: Option<&VersionInfo>,
     version: GameVersion,
 ) -> io::Result<()>
 where
@@ -155,15 +157,16 @@ where
     write_timestamp(&mut enc)?; // timestamp
     enc.write_pas_string(&"".into())?; // information
 
-    // TODO: extract all this stuff from .rsrc in gm8x
-    enc.write_u32::<LE>(1)?; // major version
-    enc.write_u32::<LE>(0)?; // minor version
-    enc.write_u32::<LE>(0)?; // release version
-    enc.write_u32::<LE>(0)?; // build version
-    enc.write_pas_string(&"".into())?; // company
-    enc.write_pas_string(&"".into())?; // product
-    enc.write_pas_string(&"".into())?; // copyright info
-    enc.write_pas_string(&"".into())?; // description
+    // GameMaker puts these in the exe's version resource
+    let info = version_info.cloned().unwrap_or(VersionInfo { version: [1, 0, 0, 0], ..Default::default() });
+    for number in info.version {
+        enc.write_u32::<LE>(number.into())?; // major, minor, release, build version
+    }
+    let string = |s: &Option<String>| PascalString::from(s.as_deref().unwrap_or(""));
+    enc.write_pas_string(&string(&info.company_name))?; // company
+    enc.write_pas_string(&string(&info.product_name))?; // product
+    enc.write_pas_string(&string(&info.legal_copyright))?; // copyright info
+    enc.write_pas_string(&string(&info.file_description))?; // description
     write_timestamp(&mut enc)?; // timestamp
 
     enc.finish(writer)?;
@@ -0,0 +1,7 @@
+/// ONLINE
+// argument0: A string
+// Returns its MD5 as lowercase hex
+hmd5_begin();
+hmd5_read_string(argument0);
+hmd5_end();
+return string_lower(hmd5_result());
\ No newline at end of file
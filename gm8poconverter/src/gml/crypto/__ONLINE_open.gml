@@ -0,0 +1,28 @@
+/// ONLINE
+// argument0: The buffer to put the decrypted payload in
+// argument1: The buffer holding the sealed message
+// argument2: Where the encrypted payload starts, after the header
+// argument3: The session key
+// argument4: "C" towards the server, "S" towards the client
+// argument5: The sequence number
+// Returns whether the MAC checked out, the payload is only written if it did
+var __ONLINE_prefix, __ONLINE_end, __ONLINE_inner;
+__ONLINE_end = hbuffer_get_length(argument1)-16;
+if(__ONLINE_end < argument2){
+return false;
+}
+__ONLINE_prefix = argument3+argument4+string(argument5);
+hmd5_begin();
+hmd5_read_string(__ONLINE_prefix);
+hmd5_read_buffer_part(argument1, 0, __ONLINE_end);
+hmd5_end();
+__ONLINE_inner = string_lower(hmd5_result());
+hbuffer_set_pos(argument1, __ONLINE_end);
+if(string_lower(hbuffer_read_hex(argument1, 16)) != __ONLINE_md5(argument3+__ONLINE_inner)){
+return false;
+}
+hbuffer_clear(argument0);
+hbuffer_write_buffer_part(argument0, argument1, argument2, __ONLINE_end-argument2);
+hbuffer_rc4_crypt(argument0, __ONLINE_md5(__ONLINE_prefix));
+hbuffer_set_pos(argument0, 0);
+return true;
\ No newline at end of file
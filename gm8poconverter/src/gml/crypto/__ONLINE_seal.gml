@@ -0,0 +1,16 @@
+/// ONLINE
+// argument0: The buffer to append the sealed payload to, after whatever header it already holds
+// argument1: The buffer holding the payload, which gets encrypted in place
+// argument2: The session key
+// argument3: "C" towards the server, "S" towards the client
+// argument4: The sequence number
+var __ONLINE_prefix, __ONLINE_inner;
+__ONLINE_prefix = argument2+argument3+string(argument4);
+hbuffer_rc4_crypt(argument1, __ONLINE_md5(__ONLINE_prefix));
+hbuffer_write_buffer(argument0, argument1);
+hmd5_begin();
+hmd5_read_string(__ONLINE_prefix);
+hmd5_read_buffer_part(argument0, 0, hbuffer_get_length(argument0));
+hmd5_end();
+__ONLINE_inner = string_lower(hmd5_result());
+hbuffer_write_hex(argument0, __ONLINE_md5(argument2+__ONLINE_inner));
\ No newline at end of file
@@ -0,0 +1,16 @@
+/// ONLINE
+// World: The name of the world object
+// Seals __ONLINE_buffer and sends it to the server over TCP, unless offline
+// Without sealing it's sent as it is
+with(World){
+if(__ONLINE_connState == 2){
+if(!__ONLINE_sealed){
+hsocket_write_message(__ONLINE_socket, __ONLINE_buffer);
+exit;
+}
+hbuffer_clear(__ONLINE_sealBuffer);
+__ONLINE_seal(__ONLINE_sealBuffer, __ONLINE_buffer, __ONLINE_sessionKey, "C", __ONLINE_tcpSendSeq);
+__ONLINE_tcpSendSeq += 1;
+hsocket_write_message(__ONLINE_socket, __ONLINE_sealBuffer);
+}
+}
\ No newline at end of file
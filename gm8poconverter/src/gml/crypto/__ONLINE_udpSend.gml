@@ -0,0 +1,19 @@
+/// ONLINE
+// World: The name of the world object
+// Seals __ONLINE_buffer and sends it to the server over UDP, unless offline
+// Without sealing it's sent as it is
+with(World){
+if(__ONLINE_connState == 2){
+if(!__ONLINE_sealed){
+hudpsocket_send(__ONLINE_udpsocket, __ONLINE_buffer);
+exit;
+}
+hbuffer_clear(__ONLINE_sealBuffer);
+hbuffer_write_uint8(__ONLINE_sealBuffer, 2);
+hbuffer_write_string(__ONLINE_sealBuffer, __ONLINE_selfID);
+hbuffer_write_uint32(__ONLINE_sealBuffer, __ONLINE_udpSendSeq);
+__ONLINE_seal(__ONLINE_sealBuffer, __ONLINE_buffer, __ONLINE_sessionKey, "C", __ONLINE_udpSendSeq);
+__ONLINE_udpSendSeq += 1;
+hudpsocket_send(__ONLINE_udpsocket, __ONLINE_sealBuffer);
+}
+}
\ No newline at end of file
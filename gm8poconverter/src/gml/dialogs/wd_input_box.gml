@@ -0,0 +1,3 @@
+/// ONLINE
+// Built-in replacement for the GM Windows Dialogs function of the same name
+return get_string(argument1, argument2);
\ No newline at end of file
@@ -0,0 +1,3 @@
+/// ONLINE
+// Built-in replacement for the GM Windows Dialogs function of the same name
+global.__ONLINE_wdText = argument0;
\ No newline at end of file
@@ -0,0 +1,7 @@
+/// ONLINE
+// Built-in replacement for the GM Windows Dialogs function of the same name
+// Only yes/no questions are used by the online templates
+if(show_question(global.__ONLINE_wdText)){
+return wd_mb_yes;
+}
+return wd_mb_no;
\ No newline at end of file
@@ -0,0 +1,4 @@
+/// ONLINE
+// Built-in replacement for the GM Windows Dialogs function of the same name
+show_message(argument0);
+return 0;
\ No newline at end of file
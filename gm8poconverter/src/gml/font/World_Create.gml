@@ -0,0 +1,6 @@
+/// ONLINE
+// The gmk has no room for pre-rendered glyphs, so the font is rebuilt from its sprite. Globals outlive
+// game_restart, and so would a second copy of the font
+if(!variable_global_exists("__ONLINE_ftOnlinePlayerName")){
+global.__ONLINE_ftOnlinePlayerName = font_add_sprite(__ONLINE_sprOnlinePlayerName, $FIRST_CHAR, true, 1);
+}
\ No newline at end of file
@@ -0,0 +1,2 @@
+/// ONLINE
+instance_create(0, 0, __ONLINE_ghost);
\ No newline at end of file
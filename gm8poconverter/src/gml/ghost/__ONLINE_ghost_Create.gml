@@ -0,0 +1,17 @@
+/// ONLINE
+// The recording is embedded by the converter, which also makes its times start at 0
+if(!variable_global_exists("__ONLINE_ghostBuffer")){
+global.__ONLINE_ghostBuffer = hbuffer_create();
+}
+__ONLINE_buffer = global.__ONLINE_ghostBuffer;
+hbuffer_clear(__ONLINE_buffer);
+hbuffer_read_from_file(__ONLINE_buffer, temp_directory+"\__ONLINE_ghost.gm8porec");
+hbuffer_read_string(__ONLINE_buffer);
+hbuffer_read_uint8(__ONLINE_buffer);
+hbuffer_read_string(__ONLINE_buffer);
+__ONLINE_name = hbuffer_read_string(__ONLINE_buffer);
+__ONLINE_started = false;
+__ONLINE_start = 0;
+__ONLINE_room = -1;
+visible = false;
+image_alpha = 0.4;
\ No newline at end of file
@@ -0,0 +1,26 @@
+/// ONLINE
+// Playback starts once the player first exists, whichever kid that is
+if(!__ONLINE_started){
+if(!instance_exists(__ONLINE_player())){
+exit;
+}
+__ONLINE_started = true;
+__ONLINE_start = current_time;
+}
+__ONLINE_elapsed = current_time - __ONLINE_start;
+while(!hbuffer_at_end(__ONLINE_buffer)){
+__ONLINE_pos = hbuffer_get_pos(__ONLINE_buffer);
+if(hbuffer_read_uint32(__ONLINE_buffer) > __ONLINE_elapsed){
+hbuffer_set_pos(__ONLINE_buffer, __ONLINE_pos);
+break;
+}
+__ONLINE_room = hbuffer_read_uint16(__ONLINE_buffer);
+x = hbuffer_read_int32(__ONLINE_buffer);
+y = hbuffer_read_int32(__ONLINE_buffer);
+sprite_index = hbuffer_read_int32(__ONLINE_buffer);
+image_speed = hbuffer_read_float32(__ONLINE_buffer);
+image_xscale = hbuffer_read_float32(__ONLINE_buffer);
+image_yscale = hbuffer_read_float32(__ONLINE_buffer);
+image_angle = hbuffer_read_float32(__ONLINE_buffer);
+}
+visible = __ONLINE_room == room && sprite_exists(sprite_index);
\ No newline at end of file
@@ -0,0 +1,39 @@
+/// ONLINE
+// argument0: The online player
+// argument1: The sender's current_time when it moved there
+// argument2: The room
+// argument3, argument4: The position
+// Keeps the last 16 positions for __ONLINE_onlinePlayer's End Step to move between
+var __ONLINE_j;
+with(argument0){
+if(__ONLINE_snapRoom != argument2){
+// a new room means a new spawn point, which shouldn't be slid to
+__ONLINE_snapRoom = argument2;
+__ONLINE_snapN = 0;
+__ONLINE_velX = 0;
+__ONLINE_velY = 0;
+}
+if(__ONLINE_snapN > 0){
+__ONLINE_j = __ONLINE_snapN-1;
+if(argument1 > __ONLINE_snapT[__ONLINE_j]){
+__ONLINE_velX = (argument3-__ONLINE_snapX[__ONLINE_j])/(argument1-__ONLINE_snapT[__ONLINE_j]);
+__ONLINE_velY = (argument4-__ONLINE_snapY[__ONLINE_j])/(argument1-__ONLINE_snapT[__ONLINE_j]);
+}
+}
+if(__ONLINE_snapN >= 16){
+for(__ONLINE_j = 0; __ONLINE_j < __ONLINE_snapN-1; __ONLINE_j += 1){
+__ONLINE_snapT[__ONLINE_j] = __ONLINE_snapT[__ONLINE_j+1];
+__ONLINE_snapX[__ONLINE_j] = __ONLINE_snapX[__ONLINE_j+1];
+__ONLINE_snapY[__ONLINE_j] = __ONLINE_snapY[__ONLINE_j+1];
+}
+__ONLINE_snapN -= 1;
+}
+__ONLINE_snapT[__ONLINE_snapN] = argument1;
+__ONLINE_snapX[__ONLINE_snapN] = argument3;
+__ONLINE_snapY[__ONLINE_snapN] = argument4;
+__ONLINE_snapN += 1;
+// the lowest offset seen is the one with the least network delay in it
+if(__ONLINE_snapN == 1 || current_time-argument1 < __ONLINE_clockOffset){
+__ONLINE_clockOffset = current_time-argument1;
+}
+}
\ No newline at end of file
@@ -4,15 +4,41 @@
 // 8002: The TCP port
 // 8003: The UDP port
 // The 'Needle': The game name
-// 1.1.9: The version
-hhttp_dll_init(0)
-__ONLINE_connected = false;
+// 1.4.0: The version
+// $SEALED: Whether traffic is sealed with the network key, which only gm8po-server understands
+// $NETWORK_KEY: The key shared with the server
+// $RECONNECT_ATTEMPTS: How many times to try reconnecting after losing the connection
+// $RECONNECT_DELAY: Milliseconds before the first retry
+// $INTERP_DELAY: Milliseconds other players are shown behind, to smooth out their movement
+// $DEAD_RECKONING: Whether other players keep moving through short gaps in their packets
+hhttp_dll_init($HTTP_DLL)
 __ONLINE_buffer = hbuffer_create();
+__ONLINE_rawBuffer = hbuffer_create();
+__ONLINE_sealBuffer = hbuffer_create();
 __ONLINE_selfID = "";
 __ONLINE_name = "";
 __ONLINE_selfGameID = "$GAME_ID";
 __ONLINE_server = "$SERVER_IP";
-__ONLINE_version = "1.1.9";
+__ONLINE_version = "1.4.0";
+__ONLINE_sealed = $SEALED;
+__ONLINE_networkKey = "$NETWORK_KEY";
+__ONLINE_sessionKey = "";
+__ONLINE_tcpSendSeq = 0;
+__ONLINE_tcpRecvSeq = 0;
+__ONLINE_udpSendSeq = 0;
+__ONLINE_udpRecvSeq = 0;
+__ONLINE_gameName = "$GAME_NAME";
+__ONLINE_hasPassword = false;
+__ONLINE_udpsocket = -1;
+__ONLINE_connState = 0;
+__ONLINE_connectStart = 0;
+__ONLINE_retries = 0;
+__ONLINE_retryAt = 0;
+__ONLINE_maxRetries = $RECONNECT_ATTEMPTS;
+__ONLINE_retryDelay = $RECONNECT_DELAY;
+__ONLINE_interpDelay = $INTERP_DELAY;
+__ONLINE_deadReckoning = $DEAD_RECKONING;
+__ONLINE_player2Flipped = $PLAYER2_FLIPPED;
 __ONLINE_race = false;
 __ONLINE_vis = 0;
 if(file_exists("tempOnline")){
@@ -25,6 +51,19 @@ __ONLINE_selfGameID = hbuffer_read_string(__ONLINE_buffer);
 __ONLINE_race = hbuffer_read_uint8(__ONLINE_buffer);
 __ONLINE_n = hbuffer_read_uint16(__ONLINE_buffer);
 __ONLINE_vis = hbuffer_read_uint16(__ONLINE_buffer);
+__ONLINE_sessionKey = hbuffer_read_string(__ONLINE_buffer);
+__ONLINE_tcpSendSeq = hbuffer_read_uint32(__ONLINE_buffer);
+__ONLINE_tcpRecvSeq = hbuffer_read_uint32(__ONLINE_buffer);
+__ONLINE_udpSendSeq = hbuffer_read_uint32(__ONLINE_buffer);
+__ONLINE_udpRecvSeq = hbuffer_read_uint32(__ONLINE_buffer);
+__ONLINE_hasPassword = hbuffer_read_uint8(__ONLINE_buffer);
+__ONLINE_connState = hbuffer_read_uint8(__ONLINE_buffer);
+__ONLINE_retries = hbuffer_read_uint16(__ONLINE_buffer);
+if(__ONLINE_connState < 2){
+// the restart interrupted an attempt, so start it over
+__ONLINE_connState = 0;
+__ONLINE_retryAt = current_time;
+}
 for(__ONLINE_i = 0; __ONLINE_i < __ONLINE_n; __ONLINE_i += 1){
 __ONLINE_oPlayer = instance_create(0, 0, __ONLINE_onlinePlayer);
 __ONLINE_oPlayer.__ONLINE_ID = hbuffer_read_string(__ONLINE_buffer);
@@ -37,10 +76,10 @@ __ONLINE_oPlayer.image_yscale = hbuffer_read_float32(__ONLINE_buffer);
 __ONLINE_oPlayer.image_angle = hbuffer_read_float32(__ONLINE_buffer);
 __ONLINE_oPlayer.__ONLINE_oRoom = hbuffer_read_uint16(__ONLINE_buffer);
 __ONLINE_oPlayer.__ONLINE_name = hbuffer_read_string(__ONLINE_buffer);
+__ONLINE_oPlayer.__ONLINE_form = hbuffer_read_uint8(__ONLINE_buffer);
 }
 }else{
 __ONLINE_socket = hsocket_create();
-hsocket_connect(__ONLINE_socket, __ONLINE_server, 8002);
 __ONLINE_name = wd_input_box("Name", "Enter your name:", "");
 if(__ONLINE_name == ""){
 __ONLINE_name = "Anonymous";
@@ -53,23 +92,15 @@ __ONLINE_password = wd_input_box("Password", "Leave it empty for no password:",
 if(string_length(__ONLINE_password) > 20){
 __ONLINE_password = string_copy(__ONLINE_password, 0, 20);
 }
+__ONLINE_hasPassword = __ONLINE_password != "";
+if(__ONLINE_sealed){
+__ONLINE_selfGameID = __ONLINE_md5(__ONLINE_selfGameID+__ONLINE_password);
+}else{
 __ONLINE_selfGameID += __ONLINE_password;
+}
 wd_message_set_text("Do you want to enable RACE mod? (shared saves will be disabled)");
 __ONLINE_race = wd_message_show(wd_mk_information, wd_mb_yes, wd_mb_no, 0) == wd_mb_yes;
-hbuffer_clear(__ONLINE_buffer);
-hbuffer_write_uint8(__ONLINE_buffer, 3);
-hbuffer_write_string(__ONLINE_buffer, __ONLINE_name);
-hbuffer_write_string(__ONLINE_buffer, __ONLINE_selfGameID);
-hbuffer_write_string(__ONLINE_buffer, "$GAME_NAME");
-hbuffer_write_string(__ONLINE_buffer, __ONLINE_version);
-hbuffer_write_uint8(__ONLINE_buffer, __ONLINE_password != "");
-hsocket_write_message(__ONLINE_socket, __ONLINE_buffer);
-__ONLINE_udpsocket = hudpsocket_create();
-hudpsocket_start(__ONLINE_udpsocket, false, 0);
-hudpsocket_set_destination(__ONLINE_udpsocket, __ONLINE_server, 8003);
-hbuffer_clear(__ONLINE_buffer);
-hbuffer_write_uint8(__ONLINE_buffer, 0);
-hudpsocket_send(__ONLINE_udpsocket, __ONLINE_buffer);
+__ONLINE_connect();
 }
 __ONLINE_pExists = false;
 __ONLINE_pX = 0;
@@ -82,6 +113,8 @@ __ONLINE_sX = 0;
 __ONLINE_sY = 0;
 __ONLINE_sRoom = 0;
 __ONLINE_sSaved = false;
+instance_create(0, 0, __ONLINE_connectionStatus);
+instance_create(0, 0, __ONLINE_playerList);
 sound_add_included("__ONLINE_sndChatbox.wav", 0, 1)
 sound_add_included("__ONLINE_sndSaved.wav", 0, 1)
 globalvar __ONLINE_sndChatbox, __ONLINE_sndSaved;
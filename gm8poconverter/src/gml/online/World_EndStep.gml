@@ -1,9 +1,25 @@
 /// ONLINE
 // Player: The name of the player object
-// : The name of the player2 object if it exists
+// $XSCALE, $YSCALE: The player's image scales as others should see them, see engine.rs
+if(__ONLINE_connState != 2){
+__ONLINE_reconnect();
+exit;
+}
 // TCP SOCKETS
 hsocket_update_read(__ONLINE_socket);
-while(hsocket_read_message(__ONLINE_socket, __ONLINE_buffer)){
+while(hsocket_read_message(__ONLINE_socket, __ONLINE_rawBuffer)){
+if(!__ONLINE_sealed){
+hbuffer_clear(__ONLINE_buffer);
+hbuffer_write_buffer(__ONLINE_buffer, __ONLINE_rawBuffer);
+hbuffer_set_pos(__ONLINE_buffer, 0);
+}else{
+if(!__ONLINE_open(__ONLINE_buffer, __ONLINE_rawBuffer, 0, __ONLINE_sessionKey, "S", __ONLINE_tcpRecvSeq)){
+wd_message_simple("Received a message which failed verification.");
+game_end();
+exit;
+}
+__ONLINE_tcpRecvSeq += 1;
+}
 switch(hbuffer_read_uint8(__ONLINE_buffer)){
 case 0:
 // CREATED
@@ -53,8 +69,17 @@ if(__ONLINE_oPlayer.__ONLINE_ID == __ONLINE_ID){
 __ONLINE_found = true;
 }
 }
+// Messages from the server itself are shown over our own player
+__ONLINE_system = __ONLINE_ID == "0";
+if(__ONLINE_system){
+__ONLINE_oPlayer = __ONLINE_player();
+__ONLINE_found = instance_exists(__ONLINE_oPlayer);
+}
 if(__ONLINE_found){
 __ONLINE_message = hbuffer_read_string(__ONLINE_buffer);
+if(__ONLINE_system){
+__ONLINE_message = "[Server] " + __ONLINE_message;
+}
 __ONLINE_oChatbox = instance_create(0, 0, __ONLINE_chatbox);
 __ONLINE_oChatbox.__ONLINE_message = __ONLINE_message;
 __ONLINE_oChatbox.__ONLINE_follower = __ONLINE_oPlayer;
@@ -88,37 +113,60 @@ case 6:
 // SELF ID
 __ONLINE_selfID = hbuffer_read_string(__ONLINE_buffer);
 break;
+case 7:
+// RACE RANKING
+__ONLINE_n = hbuffer_read_uint8(__ONLINE_buffer);
+__ONLINE_raceText = "";
+__ONLINE_selfRacePlace = 0;
+with(__ONLINE_onlinePlayer){
+__ONLINE_racePlace = 0;
+}
+for(__ONLINE_i = 0; __ONLINE_i < __ONLINE_n; __ONLINE_i += 1){
+__ONLINE_ID = hbuffer_read_string(__ONLINE_buffer);
+__ONLINE_rName = hbuffer_read_string(__ONLINE_buffer);
+__ONLINE_rFinished = hbuffer_read_uint8(__ONLINE_buffer);
+__ONLINE_rTime = hbuffer_read_uint32(__ONLINE_buffer);
+__ONLINE_rRoom = hbuffer_read_uint16(__ONLINE_buffer);
+// for the player list
+if(__ONLINE_ID == __ONLINE_selfID){
+__ONLINE_selfRacePlace = __ONLINE_i+1;
+__ONLINE_selfRaceFinished = __ONLINE_rFinished;
+__ONLINE_selfRaceTime = __ONLINE_rTime;
+}
+with(__ONLINE_onlinePlayer){
+if(__ONLINE_ID == other.__ONLINE_ID){
+__ONLINE_racePlace = other.__ONLINE_i+1;
+__ONLINE_raceFinished = other.__ONLINE_rFinished;
+__ONLINE_raceTime = other.__ONLINE_rTime;
+}
+}
+__ONLINE_raceText += string(__ONLINE_i+1)+". "+__ONLINE_rName+"  ";
+if(__ONLINE_rFinished){
+__ONLINE_raceText += __ONLINE_formatRaceTime(__ONLINE_rTime)+"#";
+}else{
+__ONLINE_raceText += room_get_name(__ONLINE_rRoom)+"#";
 }
 }
-__ONLINE_mustQuit = false;
-switch(hsocket_get_state(__ONLINE_socket)){
-case 2:
-if(!__ONLINE_connected){
-__ONLINE_connected = true;
-}
 break;
-case 4:
-wd_message_simple("Connection closed.");
-__ONLINE_mustQuit = true;
-break;
-case 5:
-hsocket_reset(__ONLINE_socket);
-__ONLINE_errorMessage = "Could not connect to the server.";
-if(__ONLINE_connected){
-__ONLINE_errorMessage = "Connection lost";
+case 8:
+// LEADERBOARD
+__ONLINE_n = hbuffer_read_uint8(__ONLINE_buffer);
+__ONLINE_raceBest = "";
+for(__ONLINE_i = 0; __ONLINE_i < __ONLINE_n; __ONLINE_i += 1){
+__ONLINE_rName = hbuffer_read_string(__ONLINE_buffer);
+__ONLINE_rTime = hbuffer_read_uint32(__ONLINE_buffer);
+__ONLINE_raceBest += string(__ONLINE_i+1)+". "+__ONLINE_rName+"  "+__ONLINE_formatRaceTime(__ONLINE_rTime)+"#";
 }
-wd_message_simple(__ONLINE_errorMessage);
-__ONLINE_mustQuit = true;
 break;
 }
-if(__ONLINE_mustQuit){
-if(file_exists("temp")){
-file_delete("temp");
 }
-game_end();
+switch(hsocket_get_state(__ONLINE_socket)){
+case 4:
+case 5:
+__ONLINE_disconnected();
 exit;
 }
-__ONLINE_p = Player;
+__ONLINE_p = __ONLINE_player();
 __ONLINE_exists = instance_exists(__ONLINE_p);
 __ONLINE_X = __ONLINE_pX;
 __ONLINE_Y = __ONLINE_pY;
@@ -127,7 +175,7 @@ if(__ONLINE_exists != __ONLINE_pExists){
 // SEND PLAYER CREATE
 hbuffer_clear(__ONLINE_buffer);
 hbuffer_write_uint8(__ONLINE_buffer, 0);
-hsocket_write_message(__ONLINE_socket, __ONLINE_buffer);
+__ONLINE_tcpSend();
 }
 __ONLINE_X = __ONLINE_p.x;
 __ONLINE_Y = __ONLINE_p.y;
@@ -151,11 +199,12 @@ hbuffer_write_int32(__ONLINE_buffer, __ONLINE_X);
 hbuffer_write_int32(__ONLINE_buffer, __ONLINE_Y);
 hbuffer_write_int32(__ONLINE_buffer, __ONLINE_p.sprite_index);
 hbuffer_write_float32(__ONLINE_buffer, __ONLINE_p.image_speed);
-hbuffer_write_float32(__ONLINE_buffer, __ONLINE_p.image_xscale);
-hbuffer_write_float32(__ONLINE_buffer, __ONLINE_p.image_yscale);
+hbuffer_write_float32(__ONLINE_buffer, $XSCALE);
+hbuffer_write_float32(__ONLINE_buffer, $YSCALE);
 hbuffer_write_float32(__ONLINE_buffer, __ONLINE_p.image_angle);
 hbuffer_write_string(__ONLINE_buffer, __ONLINE_name);
-hudpsocket_send(__ONLINE_udpsocket, __ONLINE_buffer);
+hbuffer_write_uint8(__ONLINE_buffer, __ONLINE_p != Player);
+__ONLINE_udpSend();
 }
 }
 __ONLINE_t += 1;
@@ -171,7 +220,7 @@ __ONLINE_message = string_copy(__ONLINE_message, 0, __ONLINE_message_max_length)
 hbuffer_clear(__ONLINE_buffer);
 hbuffer_write_uint8(__ONLINE_buffer, 4);
 hbuffer_write_string(__ONLINE_buffer, __ONLINE_message);
-hsocket_write_message(__ONLINE_socket, __ONLINE_buffer);
+__ONLINE_tcpSend();
 __ONLINE_oChatbox = instance_create(0, 0, __ONLINE_chatbox);
 __ONLINE_oChatbox.__ONLINE_message = __ONLINE_message;
 __ONLINE_oChatbox.__ONLINE_follower = __ONLINE_p;
@@ -183,7 +232,7 @@ if(__ONLINE_exists != __ONLINE_pExists){
 // SEND PLAYER DESTROYED
 hbuffer_clear(__ONLINE_buffer);
 hbuffer_write_uint8(__ONLINE_buffer, 1);
-hsocket_write_message(__ONLINE_socket, __ONLINE_buffer);
+__ONLINE_tcpSend();
 }
 }
 __ONLINE_pExists = __ONLINE_exists;
@@ -196,11 +245,29 @@ __ONLINE_heartbeat = 0;
 hbuffer_clear(__ONLINE_buffer);
 hbuffer_write_uint8(__ONLINE_buffer, 2);
 
-hsocket_write_message(__ONLINE_socket, __ONLINE_buffer);
+__ONLINE_tcpSend();
 }
 hsocket_update_write(__ONLINE_socket);
 // UDP SOCKETS
-while(hudpsocket_receive(__ONLINE_udpsocket, __ONLINE_buffer)){
+while(hudpsocket_receive(__ONLINE_udpsocket, __ONLINE_rawBuffer)){
+if(!__ONLINE_sealed){
+hbuffer_clear(__ONLINE_buffer);
+hbuffer_write_buffer(__ONLINE_buffer, __ONLINE_rawBuffer);
+hbuffer_set_pos(__ONLINE_buffer, 0);
+}else{
+if(hbuffer_read_uint8(__ONLINE_rawBuffer) != 2){
+continue;
+}
+// drop replays, late packets and anything that fails verification
+__ONLINE_seq = hbuffer_read_uint32(__ONLINE_rawBuffer);
+if(__ONLINE_seq < __ONLINE_udpRecvSeq){
+continue;
+}
+if(!__ONLINE_open(__ONLINE_buffer, __ONLINE_rawBuffer, 5, __ONLINE_sessionKey, "S", __ONLINE_seq)){
+continue;
+}
+__ONLINE_udpRecvSeq = __ONLINE_seq+1;
+}
 switch(hbuffer_read_uint8(__ONLINE_buffer)){
 case 1:
 // RECEIVED MOVED
@@ -222,14 +289,16 @@ __ONLINE_oPlayer.__ONLINE_oRoom = hbuffer_read_uint16(__ONLINE_buffer);
 __ONLINE_syncTime = hbuffer_read_uint64(__ONLINE_buffer);
 if(__ONLINE_oPlayer.__ONLINE_syncTime < __ONLINE_syncTime){
 __ONLINE_oPlayer.__ONLINE_syncTime = __ONLINE_syncTime;
-__ONLINE_oPlayer.x = hbuffer_read_int32(__ONLINE_buffer);
-__ONLINE_oPlayer.y = hbuffer_read_int32(__ONLINE_buffer);
+__ONLINE_mX = hbuffer_read_int32(__ONLINE_buffer);
+__ONLINE_mY = hbuffer_read_int32(__ONLINE_buffer);
+__ONLINE_pushSnapshot(__ONLINE_oPlayer, __ONLINE_syncTime, __ONLINE_oPlayer.__ONLINE_oRoom, __ONLINE_mX, __ONLINE_mY);
 __ONLINE_oPlayer.sprite_index = hbuffer_read_int32(__ONLINE_buffer);
 __ONLINE_oPlayer.image_speed = hbuffer_read_float32(__ONLINE_buffer);
 __ONLINE_oPlayer.image_xscale = hbuffer_read_float32(__ONLINE_buffer);
 __ONLINE_oPlayer.image_yscale = hbuffer_read_float32(__ONLINE_buffer);
 __ONLINE_oPlayer.image_angle = hbuffer_read_float32(__ONLINE_buffer);
 __ONLINE_oPlayer.__ONLINE_name = hbuffer_read_string(__ONLINE_buffer);
+__ONLINE_oPlayer.__ONLINE_form = hbuffer_read_uint8(__ONLINE_buffer);
 }
 break;
 default:
@@ -237,9 +306,7 @@ wd_message_simple("Received unexpected data from the server.");
 }
 }
 if(hudpsocket_get_state(__ONLINE_udpsocket) != 1){
-wd_message_simple("Connection to the UDP socket lost.");
-game_end();
-exit;
+__ONLINE_udpStart();
 }
 if(keyboard_check_pressed(ord('V'))){
 if(__ONLINE_vis == 0) __ONLINE_vis = 1;
@@ -0,0 +1,22 @@
+/// ONLINE
+// Player: The name of the player object
+// $XSCALE, $YSCALE: The player's image scales as others should see them, see engine.rs
+// Frames are buffered and written out once per second
+__ONLINE_p = __ONLINE_player();
+if(instance_exists(__ONLINE_p)){
+hbuffer_write_uint32(__ONLINE_recBuffer, current_time);
+hbuffer_write_uint16(__ONLINE_recBuffer, room);
+hbuffer_write_int32(__ONLINE_recBuffer, __ONLINE_p.x);
+hbuffer_write_int32(__ONLINE_recBuffer, __ONLINE_p.y);
+hbuffer_write_int32(__ONLINE_recBuffer, __ONLINE_p.sprite_index);
+hbuffer_write_float32(__ONLINE_recBuffer, __ONLINE_p.image_speed);
+hbuffer_write_float32(__ONLINE_recBuffer, $XSCALE);
+hbuffer_write_float32(__ONLINE_recBuffer, $YSCALE);
+hbuffer_write_float32(__ONLINE_recBuffer, __ONLINE_p.image_angle);
+}
+__ONLINE_recFrames += 1;
+if(__ONLINE_recFrames >= room_speed){
+__ONLINE_recFrames = 0;
+hbuffer_append_to_file(__ONLINE_recBuffer, __ONLINE_recFile);
+hbuffer_clear(__ONLINE_recBuffer);
+}
\ No newline at end of file
@@ -8,6 +8,8 @@ file_delete("tempOnline2");
 }
 }
 hbuffer_destroy(__ONLINE_buffer);
+hbuffer_destroy(__ONLINE_rawBuffer);
+hbuffer_destroy(__ONLINE_sealBuffer);
 if(!file_exists("tempOnline")){
 hsocket_destroy(__ONLINE_socket);
 hudpsocket_destroy(__ONLINE_udpsocket);
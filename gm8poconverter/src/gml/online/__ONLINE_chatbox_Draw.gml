@@ -1,5 +1,5 @@
 /// ONLINE
-draw_set_font(__ONLINE_ftOnlinePlayerName);
+draw_set_font(global.__ONLINE_ftOnlinePlayerName);
 __ONLINE_textHeight = string_height_ext(__ONLINE_message, __ONLINE_sep, __ONLINE_maxTextWidth);
 
 __ONLINE_height = __ONLINE_textHeight+2*__ONLINE_paddingText;
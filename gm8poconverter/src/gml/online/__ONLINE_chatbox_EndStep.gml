@@ -1,6 +1,5 @@
 /// ONLINE
 // Player: The name of the player object
-// : The name of the player2 object if it exists
 __ONLINE_f = __ONLINE_follower;
 if(instance_exists(__ONLINE_f)){
 x = __ONLINE_f.x;
@@ -17,9 +16,9 @@ exit;
 }
 }
 __ONLINE_alpha = 1;
-if(__ONLINE_follower != Player){
+if(__ONLINE_follower.object_index == __ONLINE_onlinePlayer){
 visible = __ONLINE_follower.visible;
-__ONLINE_p = Player;
+__ONLINE_p = __ONLINE_player();
 if(instance_exists(__ONLINE_p)){
 __ONLINE_dist = distance_to_object(__ONLINE_p);
 __ONLINE_alpha = __ONLINE_dist/100;
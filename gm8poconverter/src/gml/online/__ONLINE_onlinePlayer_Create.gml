@@ -0,0 +1,14 @@
+/// ONLINE
+__ONLINE_alpha = 1;
+__ONLINE_oRoom = -1;
+__ONLINE_name = "";
+__ONLINE_form = 0;
+__ONLINE_syncTime = 0;
+__ONLINE_snapN = 0;
+__ONLINE_snapRoom = -1;
+__ONLINE_clockOffset = 0;
+__ONLINE_velX = 0;
+__ONLINE_velY = 0;
+__ONLINE_racePlace = 0;
+__ONLINE_raceFinished = false;
+__ONLINE_raceTime = 0;
\ No newline at end of file
@@ -1,18 +1,24 @@
 /// ONLINE
 // World: The name of the world object
+// __ONLINE_form is 1 while the player is using their second player object, which some engines draw upside down
+__ONLINE_yscale = image_yscale;
+__ONLINE_padding = 30;
+if(__ONLINE_form == 1 && World.__ONLINE_player2Flipped){
+__ONLINE_yscale = -image_yscale;
+__ONLINE_padding = -30;
+}
 if(World.__ONLINE_vis <= 1){
 if(sprite_exists(sprite_index)){
-draw_sprite_ext(sprite_index, image_index, x, y, image_xscale, image_yscale, image_angle, c_white, image_alpha);
+draw_sprite_ext(sprite_index, image_index, x, y, image_xscale, __ONLINE_yscale, image_angle, c_white, image_alpha);
 if(World.__ONLINE_vis == 0){
 __ONLINE__alpha = draw_get_alpha();
 __ONLINE__color = draw_get_color();
 draw_set_alpha(image_alpha);
-draw_set_font(__ONLINE_ftOnlinePlayerName);
+draw_set_font(global.__ONLINE_ftOnlinePlayerName);
 draw_set_valign(fa_center);
 draw_set_halign(fa_center);
 draw_set_color(c_black);
 __ONLINE_border = 2;
-__ONLINE_padding = 30;
 __ONLINE_xx = x;
 __ONLINE_yy = y-__ONLINE_padding;
 draw_set_alpha(1);
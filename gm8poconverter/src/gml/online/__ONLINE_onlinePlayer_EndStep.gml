@@ -0,0 +1,39 @@
+/// ONLINE
+// Player: The name of the player object
+// World: The name of the world object
+// Positions are shown World.__ONLINE_interpDelay milliseconds late, so that there's usually one to move towards
+if(__ONLINE_snapN > 0){
+__ONLINE_renderTime = current_time-__ONLINE_clockOffset-World.__ONLINE_interpDelay;
+while(__ONLINE_snapN > 1 && __ONLINE_snapT[1] <= __ONLINE_renderTime){
+for(__ONLINE_i = 0; __ONLINE_i < __ONLINE_snapN-1; __ONLINE_i += 1){
+__ONLINE_snapT[__ONLINE_i] = __ONLINE_snapT[__ONLINE_i+1];
+__ONLINE_snapX[__ONLINE_i] = __ONLINE_snapX[__ONLINE_i+1];
+__ONLINE_snapY[__ONLINE_i] = __ONLINE_snapY[__ONLINE_i+1];
+}
+__ONLINE_snapN -= 1;
+}
+if(__ONLINE_renderTime <= __ONLINE_snapT[0]){
+x = __ONLINE_snapX[0];
+y = __ONLINE_snapY[0];
+}else if(__ONLINE_snapN > 1){
+__ONLINE_f = (__ONLINE_renderTime-__ONLINE_snapT[0])/(__ONLINE_snapT[1]-__ONLINE_snapT[0]);
+x = __ONLINE_snapX[0]+(__ONLINE_snapX[1]-__ONLINE_snapX[0])*__ONLINE_f;
+y = __ONLINE_snapY[0]+(__ONLINE_snapY[1]-__ONLINE_snapY[0])*__ONLINE_f;
+}else{
+// past the newest position, keep going the same way for a short gap if dead reckoning is on
+x = __ONLINE_snapX[0];
+y = __ONLINE_snapY[0];
+__ONLINE_gap = __ONLINE_renderTime-__ONLINE_snapT[0];
+if(World.__ONLINE_deadReckoning && __ONLINE_gap <= 250){
+x += __ONLINE_velX*__ONLINE_gap;
+y += __ONLINE_velY*__ONLINE_gap;
+}
+}
+}
+visible = __ONLINE_oRoom == room;
+image_alpha = __ONLINE_alpha;
+__ONLINE_p = __ONLINE_player();
+if(instance_exists(__ONLINE_p)){
+__ONLINE_dist = distance_to_object(__ONLINE_p);
+image_alpha = min(__ONLINE_alpha, __ONLINE_dist/100);
+}
\ No newline at end of file
@@ -13,7 +13,7 @@ __ONLINE__color = draw_get_color();
 draw_set_valign(fa_top);
 draw_set_halign(fa_left);
 draw_set_alpha(image_alpha);
-draw_set_font(__ONLINE_ftOnlinePlayerName);
+draw_set_font(global.__ONLINE_ftOnlinePlayerName);
 draw_set_color(c_black);
 draw_text(__ONLINE_xx+1, __ONLINE_yy, __ONLINE_text);
 draw_text(__ONLINE_xx, __ONLINE_yy+1, __ONLINE_text);
@@ -11,6 +11,14 @@ hbuffer_write_uint8(__ONLINE_buffer, __ONLINE_race);
 __ONLINE_n = instance_number(__ONLINE_onlinePlayer);
 hbuffer_write_uint16(__ONLINE_buffer, __ONLINE_n);
 hbuffer_write_uint16(__ONLINE_buffer, __ONLINE_vis);
+hbuffer_write_string(__ONLINE_buffer, __ONLINE_sessionKey);
+hbuffer_write_uint32(__ONLINE_buffer, __ONLINE_tcpSendSeq);
+hbuffer_write_uint32(__ONLINE_buffer, __ONLINE_tcpRecvSeq);
+hbuffer_write_uint32(__ONLINE_buffer, __ONLINE_udpSendSeq);
+hbuffer_write_uint32(__ONLINE_buffer, __ONLINE_udpRecvSeq);
+hbuffer_write_uint8(__ONLINE_buffer, __ONLINE_hasPassword);
+hbuffer_write_uint8(__ONLINE_buffer, __ONLINE_connState);
+hbuffer_write_uint16(__ONLINE_buffer, __ONLINE_retries);
 for(__ONLINE_i = 0; __ONLINE_i < __ONLINE_n; __ONLINE_i += 1){
 __ONLINE_oPlayer = instance_find(__ONLINE_onlinePlayer, __ONLINE_i);
 hbuffer_write_string(__ONLINE_buffer, __ONLINE_oPlayer.__ONLINE_ID);
@@ -23,13 +31,14 @@ hbuffer_write_float32(__ONLINE_buffer, __ONLINE_oPlayer.image_yscale);
 hbuffer_write_float32(__ONLINE_buffer, __ONLINE_oPlayer.image_angle);
 hbuffer_write_uint16(__ONLINE_buffer, __ONLINE_oPlayer.__ONLINE_oRoom);
 hbuffer_write_string(__ONLINE_buffer, __ONLINE_oPlayer.__ONLINE_name);
+hbuffer_write_uint8(__ONLINE_buffer, __ONLINE_oPlayer.__ONLINE_form);
 }
 hbuffer_write_to_file(__ONLINE_buffer, "tempOnline");
 }
 /// ONLINE
 // World: The name of the world object
 // Player: The name of the player object
-// : The name of the player2 object if it exists
+// $LOAD_GRAVITY: Sets the player's gravity from __ONLINE_sGravity
 if(file_exists("tempOnline2")){
 hbuffer_clear(World.__ONLINE_buffer);
 hbuffer_read_from_file(World.__ONLINE_buffer, "tempOnline2");
@@ -39,9 +48,8 @@ World.__ONLINE_sY = hbuffer_read_float64(World.__ONLINE_buffer);
 World.__ONLINE_sRoom = hbuffer_read_int16(World.__ONLINE_buffer);
 file_delete("tempOnline2");
 if(room_exists(World.__ONLINE_sRoom)){
-__ONLINE_p = Player;
-global.grav = __ONLINE_sGravity;
-__ONLINE_p = Player;
+$LOAD_GRAVITY
+__ONLINE_p = __ONLINE_player();
 __ONLINE_p.x = World.__ONLINE_sX;
 __ONLINE_p.y = World.__ONLINE_sY;
 room_goto(World.__ONLINE_sRoom);
@@ -51,7 +59,7 @@ World.__ONLINE_sSaved = false;
 /// ONLINE
 // World: The name of the world object
 // Player: The name of the player object
-// : The name of the player2 object if it exists
+// $LOAD_GRAVITY: Sets the player's gravity from __ONLINE_sGravity
 if(file_exists("tempOnline2")){
 hbuffer_clear(World.__ONLINE_buffer);
 hbuffer_read_from_file(World.__ONLINE_buffer, "tempOnline2");
@@ -61,9 +69,8 @@ World.__ONLINE_sY = hbuffer_read_float64(World.__ONLINE_buffer);
 World.__ONLINE_sRoom = hbuffer_read_int16(World.__ONLINE_buffer);
 file_delete("tempOnline2");
 if(room_exists(World.__ONLINE_sRoom)){
-__ONLINE_p = Player;
-global.grav = __ONLINE_sGravity;
-__ONLINE_p = Player;
+$LOAD_GRAVITY
+__ONLINE_p = __ONLINE_player();
 __ONLINE_p.x = World.__ONLINE_sX;
 __ONLINE_p.y = World.__ONLINE_sY;
 room_goto(World.__ONLINE_sRoom);
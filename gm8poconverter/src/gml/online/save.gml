@@ -1,19 +1,19 @@
 /// ONLINE
 // World: The name of the world object
 // Player: The name of the player object
-// : The name of the player2 object if it exists
+// $SAVE_CONDITION: Whether this call to the save script is a save worth sharing, see engine.rs
+// $SAVE_GRAVITY: The player's gravity as the load script expects it
 if(!World.__ONLINE_race){
-if(room != rmInit && room != rmTitle && room != rmMenu && room != rmOptions){
+if($SAVE_CONDITION){
 hbuffer_clear(World.__ONLINE_buffer);
-__ONLINE_p = Player;
+__ONLINE_p = __ONLINE_player();
 if(instance_exists(__ONLINE_p)){
 hbuffer_write_uint8(World.__ONLINE_buffer, 5);
-
-hbuffer_write_uint8(World.__ONLINE_buffer, global.grav);
+hbuffer_write_uint8(World.__ONLINE_buffer, $SAVE_GRAVITY);
 hbuffer_write_int32(World.__ONLINE_buffer, __ONLINE_p.x);
 hbuffer_write_float64(World.__ONLINE_buffer, __ONLINE_p.y);
 hbuffer_write_int16(World.__ONLINE_buffer, room);
-hsocket_write_message(World.__ONLINE_socket, World.__ONLINE_buffer);
+__ONLINE_tcpSend();
 }
 }
 }
\ No newline at end of file
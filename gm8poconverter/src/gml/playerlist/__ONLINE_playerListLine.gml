@@ -0,0 +1,18 @@
+/// ONLINE
+// argument0: The name
+// argument1: The room
+// argument2: The place in the race ranking, or 0 if not racing
+// argument3: Whether the race is finished
+// argument4: The race time in milliseconds
+// Returns one line of the player list
+var __ONLINE_line;
+__ONLINE_line = argument0+"  "+__ONLINE_roomName(argument1);
+if(argument2 > 0){
+__ONLINE_line += "  "+string(argument2)+". ";
+if(argument3){
+__ONLINE_line += __ONLINE_formatRaceTime(argument4);
+}else{
+__ONLINE_line += "racing";
+}
+}
+return __ONLINE_line+"#";
\ No newline at end of file
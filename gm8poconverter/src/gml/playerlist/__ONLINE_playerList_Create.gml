@@ -0,0 +1,2 @@
+/// ONLINE
+__ONLINE_show = false;
\ No newline at end of file
@@ -0,0 +1,40 @@
+/// ONLINE
+// World: The name of the world object
+if(!__ONLINE_show){
+exit;
+}
+__ONLINE_xx = 20;
+__ONLINE_yy = 20;
+if(World.__ONLINE_connState != 2){
+// below the connection status
+__ONLINE_yy += 20;
+}
+if(view_enabled && view_visible[0]){
+__ONLINE_xx += view_xview[0];
+__ONLINE_yy += view_yview[0];
+}
+__ONLINE_text = "Players: "+string(instance_number(__ONLINE_onlinePlayer)+1)+"#";
+__ONLINE_text += __ONLINE_playerListLine(World.__ONLINE_name+" (you)", room, World.__ONLINE_selfRacePlace, World.__ONLINE_selfRaceFinished, World.__ONLINE_selfRaceTime);
+with(__ONLINE_onlinePlayer){
+other.__ONLINE_text += __ONLINE_playerListLine(__ONLINE_name, __ONLINE_oRoom, __ONLINE_racePlace, __ONLINE_raceFinished, __ONLINE_raceTime);
+}
+__ONLINE__alpha = draw_get_alpha();
+__ONLINE__color = draw_get_color();
+draw_set_valign(fa_top);
+draw_set_halign(fa_left);
+draw_set_alpha(1);
+draw_set_font(global.__ONLINE_ftOnlinePlayerName);
+draw_set_color(c_black);
+draw_text(__ONLINE_xx+1, __ONLINE_yy, __ONLINE_text);
+draw_text(__ONLINE_xx, __ONLINE_yy+1, __ONLINE_text);
+draw_text(__ONLINE_xx-1, __ONLINE_yy, __ONLINE_text);
+draw_text(__ONLINE_xx, __ONLINE_yy-1, __ONLINE_text);
+draw_set_color(c_white);
+draw_text(__ONLINE_xx, __ONLINE_yy, __ONLINE_text);
+draw_set_alpha(__ONLINE__alpha);
+draw_set_color(__ONLINE__color);
+if(font_exists(0)){
+draw_set_font(0);
+}
+draw_set_valign(fa_top);
+draw_set_halign(fa_left);
\ No newline at end of file
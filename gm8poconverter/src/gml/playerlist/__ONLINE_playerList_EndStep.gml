@@ -0,0 +1,5 @@
+/// ONLINE
+// $PLAYER_LIST_KEY: The key which shows and hides the list
+if(keyboard_check_pressed($PLAYER_LIST_KEY)){
+__ONLINE_show = !__ONLINE_show;
+}
\ No newline at end of file
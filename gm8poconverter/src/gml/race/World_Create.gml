@@ -0,0 +1,13 @@
+/// ONLINE
+__ONLINE_raceState = 0;
+__ONLINE_raceRoom = -1;
+__ONLINE_raceStart = 0;
+__ONLINE_raceClock = 0;
+__ONLINE_raceText = "";
+__ONLINE_raceBest = "";
+__ONLINE_selfRacePlace = 0;
+__ONLINE_selfRaceFinished = false;
+__ONLINE_selfRaceTime = 0;
+if(__ONLINE_race){
+instance_create(0, 0, __ONLINE_raceHud);
+}
\ No newline at end of file
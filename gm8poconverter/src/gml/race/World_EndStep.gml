@@ -0,0 +1,33 @@
+/// ONLINE
+// $FINISH_ROOM: The room which ends the race, or -1 if there is none
+if(__ONLINE_race){
+if(__ONLINE_raceState == 0){
+// whichever kid is in use, so a game starting with the second one is timed too
+if(instance_exists(__ONLINE_player())){
+// SEND RACE START
+__ONLINE_raceState = 1;
+__ONLINE_raceRoom = room;
+__ONLINE_raceStart = current_time;
+hbuffer_clear(__ONLINE_buffer);
+hbuffer_write_uint8(__ONLINE_buffer, 6);
+hbuffer_write_uint16(__ONLINE_buffer, room);
+__ONLINE_tcpSend();
+}
+}else if(__ONLINE_raceState == 1){
+__ONLINE_raceClock = current_time-__ONLINE_raceStart;
+if(room != __ONLINE_raceRoom){
+__ONLINE_raceRoom = room;
+hbuffer_clear(__ONLINE_buffer);
+if(room == $FINISH_ROOM){
+// SEND RACE FINISH
+__ONLINE_raceState = 2;
+hbuffer_write_uint8(__ONLINE_buffer, 8);
+}else{
+// SEND RACE SPLIT
+hbuffer_write_uint8(__ONLINE_buffer, 7);
+hbuffer_write_uint16(__ONLINE_buffer, room);
+}
+__ONLINE_tcpSend();
+}
+}
+}
\ No newline at end of file
@@ -0,0 +1,8 @@
+/// ONLINE
+// argument0: A time in milliseconds
+// Returns the time as m:ss.cc
+var __ONLINE_cs, __ONLINE_s, __ONLINE_c;
+__ONLINE_cs = floor(argument0/10);
+__ONLINE_s = string_replace_all(string_format(floor(__ONLINE_cs/100) mod 60, 2, 0), " ", "0");
+__ONLINE_c = string_replace_all(string_format(__ONLINE_cs mod 100, 2, 0), " ", "0");
+return string(floor(__ONLINE_cs/6000))+":"+__ONLINE_s+"."+__ONLINE_c;
\ No newline at end of file
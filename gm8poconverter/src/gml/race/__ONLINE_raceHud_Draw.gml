@@ -0,0 +1,38 @@
+/// ONLINE
+// World: The name of the world object
+__ONLINE_xx = room_width-20;
+__ONLINE_yy = 20;
+if(view_enabled && view_visible[0]){
+__ONLINE_xx = view_xview[0]+view_wview[0]-20;
+__ONLINE_yy += view_yview[0];
+}
+__ONLINE_text = "Time: "+__ONLINE_formatRaceTime(World.__ONLINE_raceClock);
+if(World.__ONLINE_raceState == 2){
+__ONLINE_text += " (finished)";
+}
+if(World.__ONLINE_raceText != ""){
+__ONLINE_text += "##"+World.__ONLINE_raceText;
+}
+if(World.__ONLINE_raceBest != ""){
+__ONLINE_text += "#Best:#"+World.__ONLINE_raceBest;
+}
+__ONLINE__alpha = draw_get_alpha();
+__ONLINE__color = draw_get_color();
+draw_set_valign(fa_top);
+draw_set_halign(fa_right);
+draw_set_alpha(1);
+draw_set_font(global.__ONLINE_ftOnlinePlayerName);
+draw_set_color(c_black);
+draw_text(__ONLINE_xx+1, __ONLINE_yy, __ONLINE_text);
+draw_text(__ONLINE_xx, __ONLINE_yy+1, __ONLINE_text);
+draw_text(__ONLINE_xx-1, __ONLINE_yy, __ONLINE_text);
+draw_text(__ONLINE_xx, __ONLINE_yy-1, __ONLINE_text);
+draw_set_color(c_white);
+draw_text(__ONLINE_xx, __ONLINE_yy, __ONLINE_text);
+draw_set_alpha(__ONLINE__alpha);
+draw_set_color(__ONLINE__color);
+if(font_exists(0)){
+draw_set_font(0);
+}
+draw_set_valign(fa_top);
+draw_set_halign(fa_left);
\ No newline at end of file
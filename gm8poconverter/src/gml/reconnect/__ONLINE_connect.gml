@@ -0,0 +1,24 @@
+/// ONLINE
+// Starts connecting to the server, __ONLINE_reconnect finishes the key exchange and joins
+// Without sealing there's no key exchange, so it joins straight away and waits for its ID instead
+__ONLINE_sessionKey = "";
+__ONLINE_tcpSendSeq = 0;
+__ONLINE_tcpRecvSeq = 0;
+__ONLINE_udpSendSeq = 0;
+__ONLINE_udpRecvSeq = 0;
+hsocket_reset(__ONLINE_socket);
+hsocket_connect(__ONLINE_socket, __ONLINE_server, 8002);
+if(__ONLINE_sealed){
+// KEY EXCHANGE
+__ONLINE_nonce = __ONLINE_md5(string(current_time)+" "+string(random(1000000000))+" "+string(date_current_datetime()));
+hbuffer_clear(__ONLINE_buffer);
+hbuffer_write_uint8(__ONLINE_buffer, 9);
+hbuffer_write_string(__ONLINE_buffer, __ONLINE_version);
+hbuffer_write_string(__ONLINE_buffer, __ONLINE_nonce);
+hsocket_write_message(__ONLINE_socket, __ONLINE_buffer);
+}else{
+__ONLINE_join();
+}
+__ONLINE_udpStart();
+__ONLINE_connState = 1;
+__ONLINE_connectStart = current_time;
\ No newline at end of file
@@ -1,19 +1,28 @@
 /// ONLINE
+// World: The name of the world object
+if(World.__ONLINE_connState == 2){
+exit;
+}
 __ONLINE_xx = 20;
 __ONLINE_yy = 20;
 if(view_enabled && view_visible[0]){
 __ONLINE_xx += view_xview[0];
 __ONLINE_yy += view_yview[0];
 }
-__ONLINE_text = "hjw";
-if(__ONLINE_state != -1) __ONLINE_text = "player visual mode: "+string(__ONLINE_state);
-else __ONLINE_text = __ONLINE_name+" saved!";
+if(World.__ONLINE_connState == 0){
+__ONLINE_wait = max(ceil((World.__ONLINE_retryAt-current_time)/1000), 0);
+__ONLINE_text = "Connection lost, retrying in "+string(__ONLINE_wait)+"s ("+string(World.__ONLINE_retries+1)+"/"+string(World.__ONLINE_maxRetries)+")";
+}else if(World.__ONLINE_connState == 1){
+__ONLINE_text = "Connecting...";
+}else{
+__ONLINE_text = "Offline";
+}
 __ONLINE__alpha = draw_get_alpha();
 __ONLINE__color = draw_get_color();
 draw_set_valign(fa_top);
 draw_set_halign(fa_left);
-draw_set_alpha(image_alpha);
-draw_set_font(__ONLINE_ftOnlinePlayerName);
+draw_set_alpha(1);
+draw_set_font(global.__ONLINE_ftOnlinePlayerName);
 draw_set_color(c_black);
 draw_text(__ONLINE_xx+1, __ONLINE_yy, __ONLINE_text);
 draw_text(__ONLINE_xx, __ONLINE_yy+1, __ONLINE_text);
@@ -0,0 +1,15 @@
+/// ONLINE
+// Forgets everyone else and schedules the next attempt to reconnect, or gives up once there are none left
+// The delay doubles after each failed attempt, up to a minute
+with(__ONLINE_onlinePlayer){
+instance_destroy();
+}
+__ONLINE_selfID = "";
+__ONLINE_pExists = false;
+hsocket_reset(__ONLINE_socket);
+if(__ONLINE_retries >= __ONLINE_maxRetries){
+__ONLINE_connState = 3;
+}else{
+__ONLINE_connState = 0;
+__ONLINE_retryAt = current_time+min(__ONLINE_retryDelay*power(2, __ONLINE_retries), 60000);
+}
\ No newline at end of file
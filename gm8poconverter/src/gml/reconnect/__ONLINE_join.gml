@@ -0,0 +1,14 @@
+/// ONLINE
+// Joins the lobby, once the key exchange is done if there is one
+hbuffer_clear(__ONLINE_buffer);
+hbuffer_write_uint8(__ONLINE_buffer, 3);
+hbuffer_write_string(__ONLINE_buffer, __ONLINE_name);
+hbuffer_write_string(__ONLINE_buffer, __ONLINE_selfGameID);
+hbuffer_write_string(__ONLINE_buffer, __ONLINE_gameName);
+hbuffer_write_string(__ONLINE_buffer, __ONLINE_version);
+hbuffer_write_uint8(__ONLINE_buffer, __ONLINE_hasPassword);
+if(__ONLINE_sealed){
+__ONLINE_tcpSend();
+}else{
+hsocket_write_message(__ONLINE_socket, __ONLINE_buffer);
+}
\ No newline at end of file
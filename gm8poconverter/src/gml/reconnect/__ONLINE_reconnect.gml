@@ -0,0 +1,48 @@
+/// ONLINE
+// Runs instead of the usual networking while not connected
+if(__ONLINE_connState == 0){
+if(current_time >= __ONLINE_retryAt){
+__ONLINE_retries += 1;
+__ONLINE_connect();
+}
+}else if(__ONLINE_connState == 1){
+hsocket_update_write(__ONLINE_socket);
+hsocket_update_read(__ONLINE_socket);
+while(__ONLINE_connState == 1 && hsocket_read_message(__ONLINE_socket, __ONLINE_buffer)){
+switch(hbuffer_read_uint8(__ONLINE_buffer)){
+case 9:
+// KEY EXCHANGE
+if(__ONLINE_sealed){
+__ONLINE_sessionKey = __ONLINE_md5(__ONLINE_networkKey+__ONLINE_nonce+hbuffer_read_string(__ONLINE_buffer));
+__ONLINE_connState = 2;
+__ONLINE_retries = 0;
+__ONLINE_heartbeat = 0;
+__ONLINE_join();
+hsocket_update_write(__ONLINE_socket);
+}
+break;
+case 6:
+// SELF ID, the first reply to joining without sealing
+if(!__ONLINE_sealed){
+__ONLINE_selfID = hbuffer_read_string(__ONLINE_buffer);
+__ONLINE_connState = 2;
+__ONLINE_retries = 0;
+__ONLINE_heartbeat = 0;
+}
+break;
+case 2:
+// INCOMPATIBLE VERSION
+__ONLINE_lastVersion = hbuffer_read_string(__ONLINE_buffer);
+__ONLINE_errorMessage = "Your tool uses the version "+__ONLINE_version+" but the oldest compatible version is "+__ONLINE_lastVersion+". Please update your tool.";
+wd_message_simple(__ONLINE_errorMessage);
+game_end();
+exit;
+break;
+}
+}
+if(__ONLINE_connState == 1){
+if(hsocket_get_state(__ONLINE_socket) >= 4 || current_time-__ONLINE_connectStart > 10000){
+__ONLINE_disconnected();
+}
+}
+}
\ No newline at end of file
@@ -0,0 +1,11 @@
+/// ONLINE
+// (Re)creates the UDP socket and says hello, so that replies find their way back through NAT
+if(hudpsocket_exists(__ONLINE_udpsocket)){
+hudpsocket_destroy(__ONLINE_udpsocket);
+}
+__ONLINE_udpsocket = hudpsocket_create();
+hudpsocket_start(__ONLINE_udpsocket, false, 0);
+hudpsocket_set_destination(__ONLINE_udpsocket, __ONLINE_server, 8003);
+hbuffer_clear(__ONLINE_buffer);
+hbuffer_write_uint8(__ONLINE_buffer, 0);
+hudpsocket_send(__ONLINE_udpsocket, __ONLINE_buffer);
\ No newline at end of file
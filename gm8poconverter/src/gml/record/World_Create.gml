@@ -0,0 +1,20 @@
+/// ONLINE
+// $GAME_ID: The ID of the game
+// Starts a new recording each time the game is launched, keeping the previous one around
+__ONLINE_recBuffer = hbuffer_create();
+__ONLINE_recFile = "__ONLINE_recording.gm8porec";
+__ONLINE_recFrames = 0;
+if(!file_exists("tempOnline") || !file_exists(__ONLINE_recFile)){
+if(file_exists(__ONLINE_recFile)){
+if(file_exists("__ONLINE_recording_prev.gm8porec")){
+file_delete("__ONLINE_recording_prev.gm8porec");
+}
+file_rename(__ONLINE_recFile, "__ONLINE_recording_prev.gm8porec");
+}
+hbuffer_write_string(__ONLINE_recBuffer, "GM8POREC");
+hbuffer_write_uint8(__ONLINE_recBuffer, 1);
+hbuffer_write_string(__ONLINE_recBuffer, "$GAME_ID");
+hbuffer_write_string(__ONLINE_recBuffer, __ONLINE_name);
+hbuffer_write_to_file(__ONLINE_recBuffer, __ONLINE_recFile);
+hbuffer_clear(__ONLINE_recBuffer);
+}
\ No newline at end of file
@@ -0,0 +1,3 @@
+/// ONLINE
+hbuffer_append_to_file(__ONLINE_recBuffer, __ONLINE_recFile);
+hbuffer_destroy(__ONLINE_recBuffer);
\ No newline at end of file
@@ -1,11 +0,0 @@
-/// ONLINE
-__ONLINE_paddingText = 10;
-__ONLINE_width = 250;
-__ONLINE_padding = 15;
-__ONLINE_alpha = 1;
-__ONLINE_fade = false;
-__ONLINE_fadeAlpha = 1;
-__ONLINE_t = 70;
-__ONLINE_sep = -1;
-__ONLINE_maxTextWidth = __ONLINE_width-2*__ONLINE_paddingText;
-__ONLINE_hasDestroyed = false;
\ No newline at end of file
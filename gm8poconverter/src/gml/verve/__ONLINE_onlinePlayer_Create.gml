@@ -1,5 +0,0 @@
-/// ONLINE
-__ONLINE_alpha = 1;
-__ONLINE_oRoom = -1;
-__ONLINE_name = "";
-__ONLINE_syncTime = 0;
\ No newline at end of file
@@ -1,5 +0,0 @@
-/// ONLINE
-image_alpha -= 0.01;
-if(image_alpha <= 0){
-instance_destroy();
-}
\ No newline at end of file
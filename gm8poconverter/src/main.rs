@@ -1,17 +1,23 @@
 use gm8exe::{
-    asset::{CodeAction, Extension, Font, IncludedFile, Object, PascalString, Script},
+    asset::{CodeAction, Constant, Extension, Font, IncludedFile, Object, PascalString, Script},
+    gamedata::{self, Detector},
+    reader::{Limits, ReaderEvent},
     GameAssets, GameVersion,
 };
+use gm8po_online::{crypto::DEFAULT_NETWORK_KEY, recording::Recording};
 use rayon::vec;
 use std::{
     collections::HashMap,
-    env, fs,
+    env, fs, io,
     path::{Path, PathBuf},
     process,
+    time::Duration,
 };
 
 pub mod collision;
 pub mod deobfuscate;
+pub mod engine;
+pub mod font;
 pub mod gmk;
 pub mod mappings;
 pub mod zlib;
@@ -65,11 +71,26 @@ fn main() {
     let mut opts = getopts::Options::new();
     opts.optflag("h", "help", "print this help message")
         .optflag("l", "lazy", "disable various data integrity checks")
+        .optflag("t", "tolerant", "leave out assets which can't be read instead of failing")
+        .optflag("", "untrusted", "limit how much memory a malformed game can make the reader use")
+        .optopt("", "protection", "only look for the gamedata in this way", "NAME")
         .optflag("v", "verbose", "enable verbose logging for decompilation")
         .optopt("d", "deobfuscate", "set deobfuscation mode auto/on/off (default=auto)", "")
         .optflag("p", "preserve", "preserve broken events (instead of trying to fix them)")
         .optflag("s", "singlethread", "decompile gamedata synchronously (lower RAM usage)")
-        .optopt("o", "output", "specify output filename", "FILE");
+        .optopt("o", "output", "specify output filename", "FILE")
+        .optopt("", "http-dll", "embed this http_dll_2_3.dll instead of the bundled one", "FILE")
+        .optopt("", "dialogs", "set dialogs mode extension/builtin (default=extension)", "")
+        .optopt("", "server", "connect to this gm8po-server instead of the public one, sealing traffic", "HOST")
+        .optopt("", "server-key", "network key shared with the gm8po-server given with --server", "KEY")
+        .optopt("", "reconnect-attempts", "times to try reconnecting after losing the connection (default=10)", "N")
+        .optopt("", "reconnect-delay", "seconds before the first reconnect attempt (default=2)", "SECS")
+        .optopt("", "interp-delay", "milliseconds other players are shown behind (default=100)", "MS")
+        .optflag("", "dead-reckoning", "keep other players moving through short gaps in their packets")
+        .optopt("", "player-list-key", "key which shows and hides the player list (default=tab)", "KEY")
+        .optflag("", "record", "record the player's movement to a file while playing")
+        .optopt("", "ghost", "embed a recording and play it back as a ghost", "FILE")
+        .optopt("", "finish-room", "finish races on entering this room", "ROOM");
 
     // parse command line arguments
     let matches = match opts.parse(&args[1..]) {
@@ -101,12 +122,39 @@ fn main() {
 Options:
     -h, --help                print this help message
     -l, --lazy                disable various data integrity checks
+    -t, --tolerant            leave out assets which can't be read instead of failing
+                              (for salvaging what's left of a broken game)
+    --untrusted               limit how much memory a malformed game can make the reader use
+                              (for converting games from unknown sources)
+    --protection <name>       only look for the gamedata in this way, instead of trying each in turn
+                              ({})
     -v, --verbose             enable verbose logging for decompilation
     -d, --deobfuscate <mode>  set deobfuscation mode auto/on/off (defaults to auto)
     -p, --preserve            preserve broken events (instead of trying to fix them)
     -s, --singlethread        decompile gamedata synchronously (lower RAM usage)
-    -o, --output <file>       specify output filename",
-            process_path
+    -o, --output <file>       specify output filename
+    --http-dll <file>         embed this http_dll_2_3.dll instead of the bundled one
+    --dialogs <mode>          set dialogs mode extension/builtin (defaults to extension)
+    --server <host>           connect to this gm8po-server instead of the public one, sealing traffic with the key
+                              (it has to listen on TCP 8002 and UDP 8003, the public server doesn't seal)
+    --server-key <key>        network key shared with the server given with --server, which has to be started
+                              with the same one (defaults to gm8po-server's default key, {})
+    --reconnect-attempts <n>  times to try reconnecting after losing the connection before playing offline
+                              (defaults to 10, 0 goes offline right away)
+    --reconnect-delay <secs>  seconds before the first reconnect attempt, doubling after each one up to a minute
+                              (defaults to 2)
+    --interp-delay <ms>       milliseconds other players are shown behind, to smooth out their movement
+                              (defaults to 100, 0 shows them where they were last heard of)
+    --dead-reckoning          keep other players moving through short gaps in their packets
+    --player-list-key <key>   key which shows and hides the player list (defaults to tab)
+                              (a letter, a digit, tab, enter, shift, control, alt, backspace or f1 to f12)
+    --record                  record the player's movement to __ONLINE_recording.gm8porec while playing
+    --ghost <file>            embed a recording and play it back as a ghost
+    --finish-room <room>      finish races on entering this room
+                              (defaults to the engine's usual ending room, if the game has it)",
+            process_path,
+            gamedata::DETECTORS.iter().map(|detector| detector.name).collect::<Vec<_>>().join("/"),
+            DEFAULT_NETWORK_KEY
         );
         if should_pause {
             pause(true);
@@ -129,6 +177,19 @@ Options:
     // extract flags & input path
     let input = &matches.free[0];
     let lazy = matches.opt_present("l");
+    let tolerant = matches.opt_present("t");
+    let limits = if matches.opt_present("untrusted") { Limits::UNTRUSTED } else { Limits::NONE };
+    let detectors = match matches.opt_str("protection") {
+        Some(name) => match gamedata::detector(&name) {
+            Some(detector) => std::slice::from_ref(detector),
+            None => {
+                let names = gamedata::DETECTORS.iter().map(|detector| detector.name).collect::<Vec<_>>();
+                eprintln!("Invalid protection: {} (valid protections are {})", name, names.join("/"));
+                process::exit(1);
+            },
+        },
+        None => gamedata::DETECTORS,
+    };
     let singlethread = matches.opt_present("s");
     let verbose = matches.opt_present("v");
     let deobfuscate = match matches.opt_str("d").as_deref() {
@@ -142,6 +203,98 @@ Options:
     };
     let out_path = matches.opt_str("o");
     let preserve = matches.opt_present("p");
+    let http_dll = match matches.opt_str("http-dll") {
+        Some(path) => Some(PathBuf::from(path)),
+        None if !BUNDLED_HTTP_DLL.is_empty() => None,
+        // built without one, so see if there's one next to the converter
+        None => env::current_exe()
+            .ok()
+            .map(|exe| exe.with_file_name(HTTP_DLL_NAME))
+            .filter(|path| path.is_file()),
+    };
+    let dialogs = match matches.opt_str("dialogs").as_deref() {
+        Some("extension") | None => DialogsMode::Extension,
+        Some("builtin") => DialogsMode::Builtin,
+        Some(x) => {
+            eprintln!("Invalid dialogs setting: {} (valid settings are extension/builtin)", x);
+            process::exit(1);
+        },
+    };
+    // only gm8po-server understands sealed traffic, the public server still speaks the cleartext protocol
+    let sealed = matches.opt_present("server");
+    if matches.opt_present("server-key") && !sealed {
+        eprintln!("--server-key needs --server: the public server doesn't seal traffic");
+        process::exit(1);
+    }
+    let server = match matches.opt_str("server") {
+        Some(host) if host.is_empty() || host.contains('"') => {
+            eprintln!("Invalid server: it can't be empty or contain '\"'");
+            process::exit(1);
+        },
+        Some(host) => host,
+        None => DEFAULT_SERVER.to_string(),
+    };
+    let server_key = match matches.opt_str("server-key") {
+        Some(key) if key.is_empty() || key.contains('"') => {
+            eprintln!("Invalid server key: it can't be empty or contain '\"'");
+            process::exit(1);
+        },
+        Some(key) => key,
+        None => DEFAULT_NETWORK_KEY.to_string(),
+    };
+    let reconnect_attempts = match matches.opt_str("reconnect-attempts").map(|n| n.parse::<u16>()) {
+        Some(Ok(n)) => n,
+        Some(Err(_)) => {
+            eprintln!("Invalid reconnect attempts: expected a number from 0 to {}", u16::MAX);
+            process::exit(1);
+        },
+        None => DEFAULT_RECONNECT_ATTEMPTS,
+    };
+    let reconnect_delay = match matches.opt_str("reconnect-delay").map(|secs| secs.parse::<f64>()) {
+        Some(Ok(secs)) if (0.0..=60.0).contains(&secs) => Duration::from_secs_f64(secs),
+        Some(_) => {
+            eprintln!("Invalid reconnect delay: expected a number of seconds from 0 to 60");
+            process::exit(1);
+        },
+        None => DEFAULT_RECONNECT_DELAY,
+    };
+    let interp_delay = match matches.opt_str("interp-delay").map(|ms| ms.parse::<u16>()) {
+        Some(Ok(ms)) if ms <= 1000 => ms,
+        Some(_) => {
+            eprintln!("Invalid interpolation delay: expected a number of milliseconds from 0 to 1000");
+            process::exit(1);
+        },
+        None => DEFAULT_INTERP_DELAY,
+    };
+    let dead_reckoning = matches.opt_present("dead-reckoning");
+    let player_list_key = match matches.opt_str("player-list-key") {
+        Some(key) => match gml_key(&key) {
+            Some(key) => key,
+            None => {
+                eprintln!("Invalid player list key: {}", key);
+                process::exit(1);
+            },
+        },
+        None => "vk_tab".to_string(),
+    };
+    let record = matches.opt_present("record");
+    let ghost = matches.opt_str("ghost").map(PathBuf::from);
+    let finish_room = matches.opt_str("finish-room");
+    let patch_options = PatchOptions {
+        http_dll,
+        dialogs,
+        server,
+        sealed,
+        server_key,
+        reconnect_attempts,
+        reconnect_delay,
+        interp_delay,
+        dead_reckoning,
+        player_list_key,
+        record,
+        ghost,
+        finish_room,
+    };
     // no_pause extracted before help
 
     // print flags for confirmation
@@ -149,6 +302,15 @@ Options:
     if lazy {
         println!("Lazy mode ON: data integrity checking disabled");
     }
+    if tolerant {
+        println!("Tolerant mode ON: assets which can't be read will be left out");
+    }
+    if limits != Limits::NONE {
+        println!("Untrusted mode ON: the reader will refuse to allocate more than a game plausibly needs");
+    }
+    if let [detector] = detectors {
+        println!("Specified protection: will only look for {}", detector.description);
+    }
     if verbose {
         println!("Verbose logging ON: verbose console output enabled");
     }
@@ -166,6 +328,43 @@ Options:
     if preserve {
         println!("Preserve mode ON: broken events will be preserved and will not be fixed");
     }
+    match &patch_options.http_dll {
+        Some(path) => println!("Embedding http dll: {}", path.display()),
+        None if !BUNDLED_HTTP_DLL.is_empty() => (),
+        None => println!("No http dll found: the game will expect {} in its own folder", HTTP_DLL_NAME),
+    }
+    if let DialogsMode::Builtin = patch_options.dialogs {
+        println!("Built-in dialogs ON: GM Windows Dialogs calls will use get_string/show_question instead");
+    }
+    if patch_options.sealed {
+        println!("Specified server: {} (traffic is sealed, so it has to be a gm8po-server)", patch_options.server);
+    }
+    if patch_options.server_key != DEFAULT_NETWORK_KEY {
+        println!("Specified server key: the game will only work with servers started with the same key");
+    }
+    if patch_options.reconnect_attempts != DEFAULT_RECONNECT_ATTEMPTS
+        || patch_options.reconnect_delay != DEFAULT_RECONNECT_DELAY
+    {
+        println!(
+            "Specified reconnecting: up to {} attempts, the first after {:?}",
+            patch_options.reconnect_attempts, patch_options.reconnect_delay
+        );
+    }
+    if patch_options.interp_delay != DEFAULT_INTERP_DELAY {
+        println!("Specified interpolation delay: {} ms", patch_options.interp_delay);
+    }
+    if patch_options.dead_reckoning {
+        println!("Dead reckoning ON: other players will keep moving through short gaps in their packets");
+    }
+    if patch_options.record {
+        println!("Recording ON: the game will record the player's movement while playing");
+    }
+    if let Some(path) = &patch_options.ghost {
+        println!("Embedding ghost: {}", path.display());
+    }
+    if let Some(room) = &patch_options.finish_room {
+        println!("Specified finish room: {}", room);
+    }
 
     // resolve input path
     let input_path = Path::new(input);
@@ -175,7 +374,19 @@ Options:
     }
 
     // allow decompile to handle the rest of main
-    if let Err(e) = decompile(input_path, out_path, !lazy, !singlethread, verbose, deobfuscate, !preserve) {
+    if let Err(e) = decompile(
+        input_path,
+        out_path,
+        !lazy,
+        !singlethread,
+        tolerant,
+        limits,
+        detectors,
+        verbose,
+        deobfuscate,
+        !preserve,
+        &patch_options,
+    ) {
         eprintln!("Error parsing gamedata:\n{}", e);
         process::exit(1);
     }
@@ -185,24 +396,50 @@ Options:
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn decompile(
     in_path: &Path,
     out_path: Option<String>,
     strict: bool,
     multithread: bool,
+    tolerant: bool,
+    limits: Limits,
+    detectors: &[Detector],
     verbose: bool,
     deobf_mode: deobfuscate::Mode,
     fix_events: bool,
+    patch_options: &PatchOptions,
 ) -> Result<(), String> {
     // slurp in file contents
     let file = fs::read(&in_path).map_err(|e| format!("Failed to read '{}': {}", in_path.display(), e))?;
 
     // parse (entire) gamedata
-    let logger = if verbose { Some(|msg: &str| println!("{}", msg)) } else { None };
-    let mut assets = gm8exe::reader::from_exe(file, logger, strict, multithread) // huge call
-        .map_err(|e| format!("Reader error: {}", e))?;
-
-    println!("Successfully parsed game!");
+    // what was found and removed is always worth knowing, the rest is only for verbose logging
+    let events = |event: ReaderEvent| match event {
+        ReaderEvent::Message(msg) if verbose => println!("{}", msg),
+        ReaderEvent::ProtectionDetected(protection) => println!("Removed protection: {}", protection),
+        ReaderEvent::Detected(detector) => println!("Found gamedata: {}", detector.description),
+        _ => (),
+    };
+    let mut assets = gm8exe::reader::from_exe(file, Some(events), strict, multithread, tolerant, limits, detectors)
+        .map_err(|e| format!("Reader error: {}", e))?; // huge call
+
+    if assets.diagnostics.is_empty() {
+        println!("Successfully parsed game!");
+    } else {
+        for invalid in &assets.diagnostics {
+            println!("Left out {}", invalid);
+        }
+        println!("Parsed game, leaving out {} asset(s) which couldn't be read", assets.diagnostics.len());
+    }
+    if let Some(info) = &assets.version_info {
+        if let Some(title) = &info.product_name {
+            println!("Title: {}", title);
+        }
+        if let Some(author) = &info.company_name {
+            println!("Author: {}", author);
+        }
+    }
 
     //Do we want to deobfuscate, yes or no?
     let deobfuscate = match deobf_mode {
@@ -293,7 +530,7 @@ fn decompile(
         deobfuscate::process(&mut assets);
     }
 
-    patch(&mut assets, in_path);
+    patch(&mut assets, in_path, patch_options)?;
 
     let mut gmk = fs::File::create(&out_path)
         .map_err(|e| format!("Failed to create output file '{}': {}", out_path.display(), e))?;
@@ -304,7 +541,7 @@ fn decompile(
 
     println!("Writing {} settings...", out_expected_ext);
     let ico_file = assets.ico_file_raw.take();
-    gmk::write_settings(&mut gmk, &assets.settings, ico_file, assets.version)
+    gmk::write_settings(&mut gmk, &assets.settings, ico_file, assets.version_info.as_ref(), assets.version)
         .map_err(|e| format!("Failed to write settings block: {}", e))?;
 
     println!("Writing {} triggers...", assets.triggers.len());
@@ -389,265 +626,390 @@ fn decompile(
     Ok(())
 }
 
-fn patch(assets: &mut GameAssets, in_path: &Path) {
-    // Test engine
-    enum Engine {
-        Unknown,
-        Renex,
-        Verve,
-    }
+/// Name of the http dll as expected by `hhttp_dll_init`.
+const HTTP_DLL_NAME: &str = "http_dll_2_3.dll";
 
-    let mut engine = Engine::Unknown;
+/// The http dll which was in `src/res` when the converter was built, or nothing if there wasn't one.
+const BUNDLED_HTTP_DLL: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/http_dll_2_3.dll"));
 
-    for scr in assets.scripts.iter() {
-        if let Some(scr) = scr {
-            engine = match scr.name.to_string().as_str() {
-                "save_save" | "player_air_jump" => Engine::Verve,
-                "custom_sound_properties" => Engine::Renex,
-                _ => engine,
-            };
-        }
-    }
+/// Name of the extension the online templates use for their dialogs.
+const DIALOGS_EXTENSION_NAME: &str = "GM Windows Dialogs";
+
+/// The public server patched games connect to unless told otherwise. It only speaks the cleartext protocol.
+const DEFAULT_SERVER: &str = "81.70.53.71";
+
+/// How patched games try to get back online after losing the connection, unless told otherwise.
+const DEFAULT_RECONNECT_ATTEMPTS: u16 = 10;
+const DEFAULT_RECONNECT_DELAY: Duration = Duration::from_secs(2);
+
+/// How far behind other players are shown unless told otherwise.
+/// Games send a position every frame while moving (20 ms at 50 fps) and every third frame otherwise.
+const DEFAULT_INTERP_DELAY: u16 = 100;
+
+/// How the `wd_*` dialog functions used by the online templates are provided.
+enum DialogsMode {
+    /// Reference the GM Windows Dialogs extension, which has to be installed in GameMaker.
+    Extension,
+    /// Define `wd_*` scripts and constants which fall back to `get_string`/`show_question`.
+    Builtin,
+}
+
+/// Settings for the online patch which can be changed from the command line.
+struct PatchOptions {
+    http_dll: Option<PathBuf>,
+    dialogs: DialogsMode,
+    server: String,
+    /// Whether traffic is sealed with `server_key`, which only gm8po-server understands.
+    sealed: bool,
+    server_key: String,
+    reconnect_attempts: u16,
+    reconnect_delay: Duration,
+    interp_delay: u16,
+    dead_reckoning: bool,
+    /// GML expression for the key code, such as `vk_tab` or `ord('P')`.
+    player_list_key: String,
+    record: bool,
+    ghost: Option<PathBuf>,
+    finish_room: Option<String>,
+}
+
+fn patch(assets: &mut GameAssets, in_path: &Path, options: &PatchOptions) -> Result<(), String> {
+    let engine = engine::detect(assets)?;
+    println!("{} engine detected!", engine.name);
 
     let data = fs::read(in_path).unwrap();
     let game_id = format!("{:x}", md5::compute(data));
-    let game_name = in_path.file_stem().unwrap().to_str().unwrap();
-    let server_ip = "81.70.53.71";
-
-    match engine {
-        Engine::Verve => {
-            println!("Verve engine detected!");
-            println!("Adding http dll scripts...");
-            add_http_scripts(assets);
-
-            println!("Adding online objects...");
-            add_online_objects(
-                assets,
-                include_str!("./gml/verve/__ONLINE_onlinePlayer_Create.gml").into(),
-                include_str!("./gml/verve/__ONLINE_onlinePlayer_EndStep.gml").into(),
-                include_str!("./gml/verve/__ONLINE_onlinePlayer_Draw.gml").into(),
-                include_str!("./gml/verve/__ONLINE_chatbox_Create.gml").into(),
-                include_str!("./gml/verve/__ONLINE_chatbox_EndStep.gml").into(),
-                include_str!("./gml/verve/__ONLINE_chatbox_Draw.gml").into(),
-                include_str!("./gml/verve/__ONLINE_playerSaved_Draw.gml").into(),
-                include_str!("./gml/verve/__ONLINE_playerSaved_EndStep.gml").into(),
+    // the title the game was built with, rather than whatever the exe has been renamed to, if it has one
+    // it goes in a GML string, which has no way of escaping quotes
+    let game_name = match assets.version_info.as_ref().and_then(|info| info.product_name.as_deref()) {
+        Some(title) => title,
+        None => in_path.file_stem().unwrap().to_str().unwrap(),
+    }
+    .replace('"', "'");
+    let server_ip = options.server.as_str();
+    let templates = &engine::TEMPLATES;
+    let player2 = find_player2(assets, engine);
+    let player2_flipped = player2.is_some() && engine.player2.as_ref().is_some_and(|p| p.flipped);
+
+    println!("Adding http dll scripts...");
+    add_http_scripts(assets);
+
+    println!("Adding encryption scripts...");
+    add_crypto_scripts(assets);
+
+    println!("Adding reconnection...");
+    add_reconnect_objects(assets);
+
+    println!("Adding online objects...");
+    add_online_objects(assets, templates, player2, &options.player_list_key);
+    add_interpolation_scripts(assets);
+
+    println!("Adding dialogs...");
+    add_dialogs(assets, &options.dialogs);
+
+    println!("Adding http dll...");
+    let http_dll_path = add_http_dll(assets, options.http_dll.as_deref())?;
+
+    println!("Adding included sounds...");
+    let snd_chatbox = include_bytes!("./res/__ONLINE_sndChatbox.wav");
+    let snd_saved = include_bytes!("./res/__ONLINE_sndSaved.wav");
+    assets.included_files.push(IncludedFile {
+        file_name: "__ONLINE_sndChatbox.wav".into(),
+        source_path: "__ONLINE_sndChatbox.wav".into(),
+        data_exists: true,
+        source_length: snd_chatbox.len(),
+        stored_in_gmk: true,
+        embedded_data: Some(Box::new(*snd_chatbox)),
+        export_settings: gm8exe::asset::included_file::ExportSetting::NoExport,
+        overwrite_file: true,
+        free_memory: true,
+        remove_at_end: true,
+    });
+    assets.included_files.push(IncludedFile {
+        file_name: "__ONLINE_sndSaved.wav".into(),
+        source_path: "__ONLINE_sndSaved.wav".into(),
+        data_exists: true,
+        source_length: snd_chatbox.len(),
+        stored_in_gmk: true,
+        embedded_data: Some(Box::new(*snd_saved)),
+        export_settings: gm8exe::asset::included_file::ExportSetting::NoExport,
+        overwrite_file: true,
+        free_memory: true,
+        remove_at_end: true,
+    });
+
+    println!("Adding font...");
+    let font = online_font(engine.font_aa_level);
+    assets.sprites.push(Some(Box::new(font::to_sprite(&font, "__ONLINE_sprOnlinePlayerName"))));
+
+    println!("Adding race mode...");
+    let finish_room = find_finish_room(assets, engine, options.finish_room.as_deref())?;
+    add_race_objects(assets);
+
+    let ghost = match &options.ghost {
+        Some(path) => {
+            println!("Adding ghost...");
+            add_ghost(assets, path, &game_id)?;
+            true
+        },
+        None => false,
+    };
+
+    println!("Patching objects...");
+    let race_end_step = include_str!("./gml/race/World_EndStep.gml").replace("$FINISH_ROOM", &finish_room.to_string());
+    for obj in assets.objects.iter_mut().flatten() {
+        if obj.name.0.as_ref() != engine.world_object.as_bytes() {
+            continue
+        }
+        object_add_code(
+            obj,
+            EVENT_CREATE,
+            include_str!("./gml/font/World_Create.gml")
+                .replace("$FIRST_CHAR", &font.range_start.to_string())
+                .as_str()
+                .into(),
+        );
+        object_add_code(
+            obj,
+            EVENT_CREATE,
+            templates
+                .world_create
+                .replace("$HTTP_DLL", &http_dll_path)
+                .replace("$GAME_ID", &game_id)
+                .replace("$SERVER_IP", server_ip)
+                .replace("$SEALED", if options.sealed { "true" } else { "false" })
+                .replace("$NETWORK_KEY", &options.server_key)
+                .replace("$RECONNECT_ATTEMPTS", &options.reconnect_attempts.to_string())
+                .replace("$RECONNECT_DELAY", &options.reconnect_delay.as_millis().to_string())
+                .replace("$INTERP_DELAY", &options.interp_delay.to_string())
+                .replace("$DEAD_RECKONING", if options.dead_reckoning { "true" } else { "false" })
+                .replace("$PLAYER2_FLIPPED", if player2_flipped { "true" } else { "false" })
+                // last, so that placeholders in the game's own title are left as they are
+                .replace("$GAME_NAME", &game_name)
+                .as_str()
+                .into(),
+        );
+        object_add_code(obj, EVENT_END_STEP, engine.fill(templates.world_end_step).as_str().into());
+        object_add_code(obj, EVENT_GAME_END, templates.world_game_end.into());
+        if options.record {
+            object_add_code(
+                obj,
+                EVENT_CREATE,
+                include_str!("./gml/record/World_Create.gml").replace("$GAME_ID", &game_id).as_str().into(),
             );
+            object_add_code(obj, EVENT_END_STEP, engine.fill(templates.world_end_step_record).as_str().into());
+            object_add_code(obj, EVENT_GAME_END, include_str!("./gml/record/World_GameEnd.gml").into());
+        }
+        object_add_code(obj, EVENT_CREATE, include_str!("./gml/race/World_Create.gml").into());
+        object_add_code(obj, EVENT_END_STEP, race_end_step.as_str().into());
+        if ghost {
+            object_add_code(obj, EVENT_CREATE, include_str!("./gml/ghost/World_Create.gml").into());
+        }
+    }
 
-            println!("Adding extension...");
-            assets.extensions.push(Extension {
-                name: "GM Windows Dialogs".into(),
-                folder_name: "".into(),
-                files: vec![],
-            });
+    println!("Patching scripts...");
+    for scr in assets.scripts.iter_mut().flatten() {
+        let code = match scr.name.0.as_ref() {
+            name if name == engine.save_script.as_bytes() => templates.save,
+            name if name == engine.load_script.as_bytes() => templates.load,
+            _ => continue,
+        };
+        scr.source = format!("{}\n{}", scr.source, engine.fill(code)).as_str().into();
+    }
 
-            println!("Adding included sounds...");
-            let snd_chatbox = include_bytes!("./res/__ONLINE_sndChatbox.wav");
-            let snd_saved = include_bytes!("./res/__ONLINE_sndSaved.wav");
-            assets.included_files.push(IncludedFile {
-                file_name: "__ONLINE_sndChatbox.wav".into(),
-                source_path: "__ONLINE_sndChatbox.wav".into(),
-                data_exists: true,
-                source_length: snd_chatbox.len(),
-                stored_in_gmk: true,
-                embedded_data: Some(Box::new(*snd_chatbox)),
-                export_settings: gm8exe::asset::included_file::ExportSetting::NoExport,
-                overwrite_file: true,
-                free_memory: true,
-                remove_at_end: true,
-            });
-            assets.included_files.push(IncludedFile {
-                file_name: "__ONLINE_sndSaved.wav".into(),
-                source_path: "__ONLINE_sndSaved.wav".into(),
-                data_exists: true,
-                source_length: snd_chatbox.len(),
-                stored_in_gmk: true,
-                embedded_data: Some(Box::new(*snd_saved)),
-                export_settings: gm8exe::asset::included_file::ExportSetting::NoExport,
-                overwrite_file: true,
-                free_memory: true,
-                remove_at_end: true,
-            });
+    rename_objects(assets, engine);
 
-            println!("Adding font...");
-            assets.fonts.push(Some(Box::new(Font {
-                name: "__ONLINE_ftOnlinePlayerName".into(),
-                sys_name: "Berlin Sans FB Demi".into(),
-                size: 12,
-                bold: false,
-                italic: false,
-                range_start: 32,
-                range_end: 127,
-                charset: 0,
-                aa_level: 3,
-                dmap: Box::new([0; 1536]),
-                map_width: 0,
-                map_height: 0,
-                pixel_map: Box::new([]),
-            })));
+    Ok(())
+}
 
-            println!("Patching objects...");
-            for obj in assets.objects.iter_mut() {
-                match obj {
-                    Some(obj) => match obj.name.to_string().as_str() {
-                        "World" => {
-                            object_add_code(
-                                obj,
-                                EVENT_CREATE,
-                                include_str!("./gml/verve/World_Create.gml")
-                                    .replace("$GAME_ID", &game_id)
-                                    .replace("$GAME_NAME", game_name)
-                                    .replace("$SERVER_IP", server_ip)
-                                    .as_str()
-                                    .into(),
-                            );
-                            object_add_code(obj, EVENT_END_STEP, include_str!("./gml/verve/World_EndStep.gml").into());
-                            object_add_code(obj, EVENT_GAME_END, include_str!("./gml/verve/World_GameEnd.gml").into());
-                        },
-                        _ => (),
-                    },
-                    _ => (),
-                }
-            }
+// Replaces `from` with `to` wherever it's a whole identifier
+fn replace_identifier(source: &str, from: &str, to: &str) -> String {
+    let is_ident = |c: char| c.is_ascii_alphanumeric() || c == '_';
+    let mut out = String::with_capacity(source.len());
+    let mut rest = source;
+    while let Some(pos) = rest.find(from) {
+        let (before, after) = (&rest[..pos], &rest[pos + from.len()..]);
+        out += before;
+        let whole = !before.ends_with(is_ident) && !after.starts_with(is_ident);
+        out += if whole { to } else { from };
+        rest = after;
+    }
+    out += rest;
+    out
+}
 
-            println!("Patching scripts...");
-            for scr in assets.scripts.iter_mut() {
-                match scr {
-                    Some(scr) => match scr.name.to_string().as_str() {
-                        "save_save" => {
-                            scr.source = format!("{}\n{}", scr.source, include_str!("./gml/verve/save_save.gml"))
-                                .as_str()
-                                .into();
-                        },
-                        "save_load" => {
-                            scr.source = format!("{}\n{}", scr.source, include_str!("./gml/verve/save_load.gml"))
-                                .as_str()
-                                .into();
-                        },
-                        _ => (),
-                    },
-                    _ => (),
+// The templates call the world and player objects World and Player, as their headers say.
+// This renames them in everything that was injected, which always starts with a `/// ONLINE` line.
+fn rename_objects(assets: &mut GameAssets, engine: &engine::Profile) {
+    if engine.world_object == "World" && engine.player_object == "Player" {
+        return
+    }
+    let rename = |source: &str| {
+        let source = replace_identifier(source, "World", engine.world_object);
+        replace_identifier(&source, "Player", engine.player_object)
+    };
+    // the game's own code is left byte for byte as it was, since it might not be UTF-8
+    let marker = b"/// ONLINE";
+    for scr in assets.scripts.iter_mut().flatten() {
+        if let Some(start) = scr.source.0.windows(marker.len()).position(|w| w == marker) {
+            let mut source = scr.source.0[..start].to_vec();
+            source.extend_from_slice(rename(&String::from_utf8_lossy(&scr.source.0[start..])).as_bytes());
+            scr.source = PascalString(source.into_boxed_slice());
+        }
+    }
+    for obj in assets.objects.iter_mut().flatten() {
+        for (_, actions) in obj.events.iter_mut().flatten() {
+            for action in actions {
+                if action.param_strings[0].0.starts_with(marker) {
+                    action.param_strings[0] = rename(&action.param_strings[0].to_string()).as_str().into();
                 }
             }
+        }
+    }
+}
+
+// Looks for the engine's second player object, returning its name if the game has one
+fn find_player2(assets: &GameAssets, engine: &engine::Profile) -> Option<&'static str> {
+    let names = engine.player2.as_ref()?.names;
+    let found = names.iter().copied().find(|name| {
+        assets.objects.iter().flatten().any(|o| o.name.0.as_ref() == name.as_bytes())
+    });
+    if let Some(name) = found {
+        println!("Found second player object {}", name);
+    }
+    found
+}
+
+// Works out which room ends a race, returning its index or -1 if there is none
+fn find_finish_room(assets: &GameAssets, engine: &engine::Profile, name: Option<&str>) -> Result<i32, String> {
+    let index_of = |name: &str| {
+        assets.rooms.iter().position(|r| r.as_ref().is_some_and(|r| r.name.0.as_ref() == name.as_bytes()))
+    };
+    if let Some(name) = name {
+        return match index_of(name) {
+            Some(index) => Ok(index as i32),
+            None => Err(format!("There's no room called '{}' to finish races in", name)),
+        }
+    }
+    match engine.finish_rooms.iter().find_map(|name| Some((*name, index_of(name)?))) {
+        Some((name, index)) => {
+            println!("Races will finish in {}", name);
+            Ok(index as i32)
         },
-        Engine::Renex => {
-            println!("Renex engine detected!");
-
-            println!("Adding http dll scripts...");
-            add_http_scripts(assets);
-
-            println!("Adding online objects...");
-            add_online_objects(
-                assets,
-                include_str!("./gml/renex/__ONLINE_onlinePlayer_Create.gml").into(),
-                include_str!("./gml/renex/__ONLINE_onlinePlayer_EndStep.gml").into(),
-                include_str!("./gml/renex/__ONLINE_onlinePlayer_Draw.gml").into(),
-                include_str!("./gml/renex/__ONLINE_chatbox_Create.gml").into(),
-                include_str!("./gml/renex/__ONLINE_chatbox_EndStep.gml").into(),
-                include_str!("./gml/renex/__ONLINE_chatbox_Draw.gml").into(),
-                include_str!("./gml/renex/__ONLINE_playerSaved_Draw.gml").into(),
-                include_str!("./gml/renex/__ONLINE_playerSaved_EndStep.gml").into(),
-            );
+        None => {
+            println!("Note: no ending room found, so races can't be finished -- you can pick one with '--finish-room'");
+            Ok(-1)
+        },
+    }
+}
 
-            println!("Adding extension...");
+fn add_dialogs(assets: &mut GameAssets, mode: &DialogsMode) {
+    // The game might already use the extension itself, in which case everything needed is there
+    if assets.extensions.iter().any(|ext| ext.name.0.as_ref() == DIALOGS_EXTENSION_NAME.as_bytes()) {
+        return
+    }
+
+    match mode {
+        DialogsMode::Extension => {
             assets.extensions.push(Extension {
-                name: "GM Windows Dialogs".into(),
+                name: DIALOGS_EXTENSION_NAME.into(),
                 folder_name: "".into(),
                 files: vec![],
             });
+        },
+        DialogsMode::Builtin => {
+            for (name, value) in [("wd_mk_information", "64"), ("wd_mb_yes", "6"), ("wd_mb_no", "7")] {
+                assets.constants.push(Constant { name: name.into(), expression: value.into() });
+            }
 
-            println!("Adding included sounds...");
-            let snd_chatbox = include_bytes!("./res/__ONLINE_sndChatbox.wav");
-            let snd_saved = include_bytes!("./res/__ONLINE_sndSaved.wav");
-            assets.included_files.push(IncludedFile {
-                file_name: "__ONLINE_sndChatbox.wav".into(),
-                source_path: "__ONLINE_sndChatbox.wav".into(),
-                data_exists: true,
-                source_length: snd_chatbox.len(),
-                stored_in_gmk: true,
-                embedded_data: Some(Box::new(*snd_chatbox)),
-                export_settings: gm8exe::asset::included_file::ExportSetting::NoExport,
-                overwrite_file: true,
-                free_memory: true,
-                remove_at_end: true,
-            });
-            assets.included_files.push(IncludedFile {
-                file_name: "__ONLINE_sndSaved.wav".into(),
-                source_path: "__ONLINE_sndSaved.wav".into(),
-                data_exists: true,
-                source_length: snd_chatbox.len(),
-                stored_in_gmk: true,
-                embedded_data: Some(Box::new(*snd_saved)),
-                export_settings: gm8exe::asset::included_file::ExportSetting::NoExport,
-                overwrite_file: true,
-                free_memory: true,
-                remove_at_end: true,
-            });
+            assets.scripts.push(Some(Box::new(Script {
+                name: "wd_input_box".into(),
+                source: include_str!("./gml/dialogs/wd_input_box.gml").into(),
+            })));
 
-            println!("Adding font...");
-            assets.fonts.push(Some(Box::new(Font {
-                name: "__ONLINE_ftOnlinePlayerName".into(),
-                sys_name: "Berlin Sans FB Demi".into(),
-                size: 12,
-                bold: false,
-                italic: false,
-                range_start: 32,
-                range_end: 127,
-                charset: 0,
-                aa_level: 4,
-                dmap: Box::new([0; 1536]),
-                map_width: 0,
-                map_height: 0,
-                pixel_map: Box::new([]),
+            assets.scripts.push(Some(Box::new(Script {
+                name: "wd_message_simple".into(),
+                source: include_str!("./gml/dialogs/wd_message_simple.gml").into(),
             })));
 
-            println!("Patching objects...");
-            for obj in assets.objects.iter_mut() {
-                match obj {
-                    Some(obj) => match obj.name.to_string().as_str() {
-                        "World" => {
-                            object_add_code(
-                                obj,
-                                EVENT_CREATE,
-                                include_str!("./gml/renex/World_Create.gml")
-                                    .replace("$GAME_ID", &game_id)
-                                    .replace("$GAME_NAME", game_name)
-                                    .replace("$SERVER_IP", server_ip)
-                                    .as_str()
-                                    .into(),
-                            );
-                            object_add_code(obj, EVENT_END_STEP, include_str!("./gml/renex/World_EndStep.gml").into());
-                            object_add_code(obj, EVENT_GAME_END, include_str!("./gml/renex/World_GameEnd.gml").into());
-                        },
-                        _ => (),
-                    },
-                    _ => (),
-                }
-            }
+            assets.scripts.push(Some(Box::new(Script {
+                name: "wd_message_set_text".into(),
+                source: include_str!("./gml/dialogs/wd_message_set_text.gml").into(),
+            })));
 
-            println!("Patching scripts...");
-            for scr in assets.scripts.iter_mut() {
-                match scr {
-                    Some(scr) => match scr.name.to_string().as_str() {
-                        "savedata_save" => {
-                            scr.source = format!("{}\n{}", scr.source, include_str!("./gml/renex/savedata_save.gml"))
-                                .as_str()
-                                .into();
-                        },
-                        "savedata_load" => {
-                            scr.source = format!("{}\n{}", scr.source, include_str!("./gml/renex/savedata_load.gml"))
-                                .as_str()
-                                .into();
-                        },
-                        _ => (),
-                    },
-                    _ => (),
-                }
-            }
-        },
-        Engine::Unknown => {
-            panic!("Unsuppported engine! Please contact Cube.");
+            assets.scripts.push(Some(Box::new(Script {
+                name: "wd_message_show".into(),
+                source: include_str!("./gml/dialogs/wd_message_show.gml").into(),
+            })));
         },
+    }
+}
+
+/// Name of the embedded recording in the temp folder, as expected by `__ONLINE_ghost`.
+const GHOST_FILE_NAME: &str = "__ONLINE_ghost.gm8porec";
+
+// Embeds a recording along with the object playing it back
+fn add_ghost(assets: &mut GameAssets, path: &Path, game_id: &str) -> Result<(), String> {
+    let file = fs::File::open(path).map_err(|e| format!("Failed to open '{}': {}", path.display(), e))?;
+    let mut recording = Recording::read(io::BufReader::new(file))
+        .map_err(|e| format!("Failed to read recording '{}': {}", path.display(), e))?;
+    if recording.game_id.0.as_ref() != game_id.as_bytes() {
+        println!("Warning: the ghost was recorded in a different version of this game ({})", recording.game_id);
+    }
+    recording.normalize();
+    let mut data = Vec::new();
+    recording.write(&mut data).map_err(|e| e.to_string())?;
+
+    assets.included_files.push(IncludedFile {
+        file_name: GHOST_FILE_NAME.into(),
+        source_path: GHOST_FILE_NAME.into(),
+        data_exists: true,
+        source_length: data.len(),
+        stored_in_gmk: true,
+        embedded_data: Some(data.into_boxed_slice()),
+        export_settings: gm8exe::asset::included_file::ExportSetting::TempFolder,
+        overwrite_file: true,
+        free_memory: true,
+        remove_at_end: true,
+    });
+
+    let mut ghost = Object {
+        name: "__ONLINE_ghost".into(),
+        visible: false,
+        depth: -9,
+        sprite_index: -1,
+        mask_index: -1,
+        parent_index: -1,
+        solid: false,
+        persistent: true,
+        events: vec![vec![], vec![], vec![], vec![], vec![], vec![], vec![], vec![], vec![], vec![], vec![]],
     };
+    object_add_code(&mut ghost, EVENT_CREATE, include_str!("./gml/ghost/__ONLINE_ghost_Create.gml").into());
+    object_add_code(&mut ghost, EVENT_END_STEP, include_str!("./gml/ghost/__ONLINE_ghost_EndStep.gml").into());
+    assets.objects.push(Some(Box::new(ghost)));
+    Ok(())
+}
+
+// Embeds the given http dll, or the bundled one, returning the GML expression for its path at runtime
+fn add_http_dll(assets: &mut GameAssets, path: Option<&Path>) -> Result<String, String> {
+    let data = match path {
+        Some(path) => fs::read(path).map_err(|e| format!("Failed to read '{}': {}", path.display(), e))?,
+        None if !BUNDLED_HTTP_DLL.is_empty() => BUNDLED_HTTP_DLL.to_vec(),
+        None => return Ok("0".into()), // hhttp_dll_init falls back to the game folder
+    };
+    assets.included_files.push(IncludedFile {
+        file_name: HTTP_DLL_NAME.into(),
+        source_path: HTTP_DLL_NAME.into(),
+        data_exists: true,
+        source_length: data.len(),
+        stored_in_gmk: true,
+        embedded_data: Some(data.into_boxed_slice()),
+        export_settings: gm8exe::asset::included_file::ExportSetting::TempFolder,
+        overwrite_file: true,
+        free_memory: true,
+        remove_at_end: true,
+    });
+    Ok(format!("temp_directory+\"\\{}\"", HTTP_DLL_NAME))
 }
 
 fn add_http_scripts(assets: &mut GameAssets) {
@@ -1350,17 +1712,139 @@ fn object_add_code(obj: &mut Object, (event_index, subevent_index): (usize, u32)
     subevents.push((subevent_index, vec![code_action]));
 }
 
+fn online_font(aa_level: u32) -> Font {
+    let mut font = Font {
+        name: "__ONLINE_ftOnlinePlayerName".into(),
+        sys_name: "DejaVu Sans Condensed".into(),
+        size: 12,
+        bold: true,
+        italic: false,
+        range_start: 32,
+        range_end: 127,
+        charset: 0,
+        aa_level,
+        dmap: Box::new([0; 1536]),
+        map_width: 0,
+        map_height: 0,
+        pixel_map: Box::new([]),
+    };
+
+    // Pre-render the glyphs so the font doesn't depend on what's installed on the player's system. This only
+    // exists to be cut up by font::to_sprite, it isn't a font asset of its own
+    font::rasterize(&mut font, include_bytes!("./res/DejaVuSansCondensed-Bold.ttf"))
+        .expect("bundled font is a valid TrueType file");
+    font
+}
+
+fn add_crypto_scripts(assets: &mut GameAssets) {
+    for (name, source) in [
+        ("__ONLINE_md5", include_str!("./gml/crypto/__ONLINE_md5.gml")),
+        ("__ONLINE_seal", include_str!("./gml/crypto/__ONLINE_seal.gml")),
+        ("__ONLINE_open", include_str!("./gml/crypto/__ONLINE_open.gml")),
+        ("__ONLINE_tcpSend", include_str!("./gml/crypto/__ONLINE_tcpSend.gml")),
+        ("__ONLINE_udpSend", include_str!("./gml/crypto/__ONLINE_udpSend.gml")),
+    ] {
+        assets.scripts.push(Some(Box::new(Script { name: name.into(), source: source.into() })));
+    }
+}
+
+fn add_interpolation_scripts(assets: &mut GameAssets) {
+    assets.scripts.push(Some(Box::new(Script {
+        name: "__ONLINE_pushSnapshot".into(),
+        source: include_str!("./gml/interpolation/__ONLINE_pushSnapshot.gml").into(),
+    })));
+}
+
+fn add_reconnect_objects(assets: &mut GameAssets) {
+    for (name, source) in [
+        ("__ONLINE_connect", include_str!("./gml/reconnect/__ONLINE_connect.gml")),
+        ("__ONLINE_join", include_str!("./gml/reconnect/__ONLINE_join.gml")),
+        ("__ONLINE_udpStart", include_str!("./gml/reconnect/__ONLINE_udpStart.gml")),
+        ("__ONLINE_disconnected", include_str!("./gml/reconnect/__ONLINE_disconnected.gml")),
+        ("__ONLINE_reconnect", include_str!("./gml/reconnect/__ONLINE_reconnect.gml")),
+    ] {
+        assets.scripts.push(Some(Box::new(Script { name: name.into(), source: source.into() })));
+    }
+
+    let mut status = Object {
+        name: "__ONLINE_connectionStatus".into(),
+        visible: true,
+        depth: -11,
+        sprite_index: -1,
+        mask_index: -1,
+        parent_index: -1,
+        solid: false,
+        persistent: true,
+        events: vec![vec![], vec![], vec![], vec![], vec![], vec![], vec![], vec![], vec![], vec![], vec![]],
+    };
+    object_add_code(&mut status, EVENT_DRAW, include_str!("./gml/reconnect/__ONLINE_connectionStatus_Draw.gml").into());
+    assets.objects.push(Some(Box::new(status)));
+}
+
+// Turns a key name from the command line into the GML for its key code
+fn gml_key(name: &str) -> Option<String> {
+    let name = name.to_ascii_lowercase();
+    match name.as_str() {
+        "tab" | "enter" | "shift" | "control" | "alt" | "backspace" => Some(format!("vk_{}", name)),
+        _ if name.len() == 1 && name.as_bytes()[0].is_ascii_alphanumeric() => {
+            Some(format!("ord('{}')", name.to_ascii_uppercase()))
+        },
+        _ => match name.strip_prefix('f').and_then(|n| n.parse::<u8>().ok()) {
+            Some(n @ 1..=12) => Some(format!("vk_f{}", n)),
+            _ => None,
+        },
+    }
+}
+
+// Generates a script which maps room indices to the names they had when the game was patched
+fn room_name_script(assets: &GameAssets) -> String {
+    let mut source = String::from("/// ONLINE\n// argument0: A room index\n");
+    source += "// Returns the room's name, or an empty string if there's no such room\nswitch(argument0){\n";
+    for (index, room) in assets.rooms.iter().enumerate() {
+        if let Some(room) = room {
+            let name = room.name.to_string();
+            // GML strings can't escape quotes, but they can use either kind
+            let quote = if name.contains('"') { '\'' } else { '"' };
+            if !name.contains(quote) {
+                source += &format!("case {}: return {}{}{};\n", index, quote, name, quote);
+            }
+        }
+    }
+    source += "}\nreturn \"\";";
+    source
+}
+
+// Generates a script which returns the player object in use, falling back to the second one when it's around
+fn player_script(player2: Option<&str>) -> String {
+    let mut source = String::from("/// ONLINE\n// Player: The name of the player object\n");
+    source += "// Returns the player object in use\n";
+    if let Some(name) = player2 {
+        source += &format!("if(!instance_exists(Player) && instance_exists({})){{\nreturn {};\n}}\n", name, name);
+    }
+    source += "return Player;";
+    source
+}
+
 fn add_online_objects(
     assets: &mut GameAssets,
-    online_player_create: PascalString,
-    online_player_endstep: PascalString,
-    online_player_draw: PascalString,
-    chatbox_create: PascalString,
-    chatbox_endstep: PascalString,
-    chatbox_draw: PascalString,
-    player_saved_draw: PascalString,
-    player_saved_endstep: PascalString,
+    templates: &engine::Templates,
+    player2: Option<&str>,
+    player_list_key: &str,
 ) {
+    assets.scripts.push(Some(Box::new(Script {
+        name: "__ONLINE_player".into(),
+        source: player_script(player2).as_str().into(),
+    })));
+    let room_names = room_name_script(assets);
+    assets.scripts.push(Some(Box::new(Script {
+        name: "__ONLINE_roomName".into(),
+        source: room_names.as_str().into(),
+    })));
+    assets.scripts.push(Some(Box::new(Script {
+        name: "__ONLINE_playerListLine".into(),
+        source: include_str!("./gml/playerlist/__ONLINE_playerListLine.gml").into(),
+    })));
+
     let mut online_player = Object {
         name: "__ONLINE_onlinePlayer".into(),
         visible: false,
@@ -1372,9 +1856,9 @@ fn add_online_objects(
         persistent: true,
         events: vec![vec![], vec![], vec![], vec![], vec![], vec![], vec![], vec![], vec![], vec![], vec![]],
     };
-    object_add_code(&mut online_player, EVENT_CREATE, online_player_create);
-    object_add_code(&mut online_player, EVENT_END_STEP, online_player_endstep);
-    object_add_code(&mut online_player, EVENT_DRAW, online_player_draw);
+    object_add_code(&mut online_player, EVENT_CREATE, templates.online_player_create.into());
+    object_add_code(&mut online_player, EVENT_END_STEP, templates.online_player_end_step.into());
+    object_add_code(&mut online_player, EVENT_DRAW, templates.online_player_draw.into());
     assets.objects.push(Some(Box::new(online_player)));
 
     let mut online_chatbox = Object {
@@ -1388,11 +1872,31 @@ fn add_online_objects(
         persistent: true,
         events: vec![vec![], vec![], vec![], vec![], vec![], vec![], vec![], vec![], vec![], vec![], vec![]],
     };
-    object_add_code(&mut online_chatbox, EVENT_CREATE, chatbox_create);
-    object_add_code(&mut online_chatbox, EVENT_END_STEP, chatbox_endstep);
-    object_add_code(&mut online_chatbox, EVENT_DRAW, chatbox_draw);
+    object_add_code(&mut online_chatbox, EVENT_CREATE, templates.chatbox_create.into());
+    object_add_code(&mut online_chatbox, EVENT_END_STEP, templates.chatbox_end_step.into());
+    object_add_code(&mut online_chatbox, EVENT_DRAW, templates.chatbox_draw.into());
     assets.objects.push(Some(Box::new(online_chatbox)));
 
+    let mut player_list = Object {
+        name: "__ONLINE_playerList".into(),
+        visible: true,
+        depth: -11,
+        sprite_index: -1,
+        mask_index: -1,
+        parent_index: -1,
+        solid: false,
+        persistent: true,
+        events: vec![vec![], vec![], vec![], vec![], vec![], vec![], vec![], vec![], vec![], vec![], vec![]],
+    };
+    let create = include_str!("./gml/playerlist/__ONLINE_playerList_Create.gml");
+    let end_step =
+        include_str!("./gml/playerlist/__ONLINE_playerList_EndStep.gml").replace("$PLAYER_LIST_KEY", player_list_key);
+    let draw = include_str!("./gml/playerlist/__ONLINE_playerList_Draw.gml");
+    object_add_code(&mut player_list, EVENT_CREATE, create.into());
+    object_add_code(&mut player_list, EVENT_END_STEP, end_step.as_str().into());
+    object_add_code(&mut player_list, EVENT_DRAW, draw.into());
+    assets.objects.push(Some(Box::new(player_list)));
+
     let mut online_player_saved = Object {
         name: "__ONLINE_playerSaved".into(),
         visible: true,
@@ -1404,7 +1908,28 @@ fn add_online_objects(
         persistent: false,
         events: vec![vec![], vec![], vec![], vec![], vec![], vec![], vec![], vec![], vec![], vec![], vec![]],
     };
-    object_add_code(&mut online_player_saved, EVENT_END_STEP, player_saved_endstep);
-    object_add_code(&mut online_player_saved, EVENT_DRAW, player_saved_draw);
+    object_add_code(&mut online_player_saved, EVENT_END_STEP, templates.player_saved_end_step.into());
+    object_add_code(&mut online_player_saved, EVENT_DRAW, templates.player_saved_draw.into());
     assets.objects.push(Some(Box::new(online_player_saved)));
 }
+
+fn add_race_objects(assets: &mut GameAssets) {
+    assets.scripts.push(Some(Box::new(Script {
+        name: "__ONLINE_formatRaceTime".into(),
+        source: include_str!("./gml/race/__ONLINE_formatRaceTime.gml").into(),
+    })));
+
+    let mut race_hud = Object {
+        name: "__ONLINE_raceHud".into(),
+        visible: true,
+        depth: -11,
+        sprite_index: -1,
+        mask_index: -1,
+        parent_index: -1,
+        solid: false,
+        persistent: true,
+        events: vec![vec![], vec![], vec![], vec![], vec![], vec![], vec![], vec![], vec![], vec![], vec![]],
+    };
+    object_add_code(&mut race_hud, EVENT_DRAW, include_str!("./gml/race/__ONLINE_raceHud_Draw.gml").into());
+    assets.objects.push(Some(Box::new(race_hud)));
+}